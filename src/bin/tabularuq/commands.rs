@@ -0,0 +1,236 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use encoding_rs::UTF_8;
+use tabularuq::pipeline::{
+    ExportFormat, ExportOptions, ImportMapping, ImportMode, ImportOptions, InferOptions,
+    SqlDialect, export, import_stream, infer_schema,
+};
+use tabularuq::rdb_qry_handler::{
+    DataSourceInform, QueryHandler, bulk::ColumnMapping, catalog::Catalog, datatype::DataType,
+    dyn_handler::BoxedQueryHandler, qry_handler_from_dsi,
+};
+use tabularuq::tabular_porter::{TabularCursor, WriteOptions, async_cursor::BlockingTabularCursor};
+
+use crate::error::CliError;
+use crate::render::{format_record, render_table};
+use crate::repl::Repl;
+use crate::source::{TabularSource, open_writer};
+use crate::{Command, FileFormat, ImportModeArg};
+
+pub async fn run(command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Query { config, datasource, sql, file, max_rows } => {
+            let sql = read_sql(sql, file)?;
+            let mut handler = connect(&config, datasource.as_deref()).await?;
+            let output = run_query(&mut handler, &sql, max_rows).await;
+            handler.close().await?;
+            print!("{}", output?);
+        }
+        Command::Export {
+            config,
+            datasource,
+            sql,
+            file,
+            output,
+            format,
+            no_header,
+            null,
+            date_format,
+            rows_per_part,
+            encoding,
+            bom,
+        } => {
+            let sql = read_sql(sql, file)?;
+            let format = format.map(|format| match format {
+                FileFormat::Csv => ExportFormat::Csv,
+                FileFormat::Xlsx => ExportFormat::Xlsx,
+                FileFormat::Json => ExportFormat::Json,
+                FileFormat::Jsonl => ExportFormat::JsonLines,
+            });
+            let mut options =
+                ExportOptions::new(!no_header, &null, date_format.as_deref(), rows_per_part);
+            options.set_encoding(encoding.unwrap_or(UTF_8), bom);
+
+            let mut handler = connect(&config, datasource.as_deref()).await?;
+            let exported = export(&mut handler, &sql, None, &output, format, options).await;
+            handler.close().await?;
+            eprintln!("{} rows exported to {}", exported?, output.display());
+        }
+        Command::Import {
+            config,
+            datasource,
+            input,
+            table,
+            sheet,
+            entry,
+            encoding,
+            mode,
+            batch_size,
+            mappings,
+        } => {
+            let mut source =
+                TabularSource::open(&input, sheet.as_deref(), entry.as_deref(), encoding)?;
+            let mapping = if mappings.is_empty() {
+                ImportMapping::ByHeader
+            } else {
+                ImportMapping::Explicit(parse_mappings(&mappings, source.headers()?.as_deref())?)
+            };
+            let mode = match mode {
+                ImportModeArg::Append => ImportMode::Append,
+                ImportModeArg::Truncate => ImportMode::Truncate,
+                ImportModeArg::Create => ImportMode::CreateIfMissing,
+            };
+
+            let mut options = ImportOptions::new(mode, mapping, batch_size);
+            if mode == ImportMode::CreateIfMissing {
+                let mut source =
+                    TabularSource::open(&input, sheet.as_deref(), entry.as_deref(), encoding)?;
+                options.set_schema(infer_schema(&mut source, InferOptions::default())?);
+            }
+
+            let mut handler = connect(&config, datasource.as_deref()).await?;
+            let source = BlockingTabularCursor::new(source);
+            let imported = import_stream(&mut handler, source, &table, options).await;
+            handler.close().await?;
+            eprintln!("{} rows imported into {}", imported?, table);
+        }
+        Command::Shell { config, datasource, history, page_size } => {
+            let mut handler = connect(&config, datasource.as_deref()).await?;
+            let mut repl = Repl::new(history.unwrap_or_else(Repl::default_history), page_size)?;
+            let result = repl.run(&mut handler).await;
+            handler.close().await?;
+            result?;
+        }
+        Command::Convert { input, output, sheet, entry, encoding, output_encoding, bom } => {
+            let mut source =
+                TabularSource::open(&input, sheet.as_deref(), entry.as_deref(), encoding)?;
+            let mut options = WriteOptions::default();
+            options.set_encoding(output_encoding.unwrap_or(UTF_8), bom);
+            let converted = convert(&mut source, &output, options)?;
+            eprintln!("{} rows written to {}", converted, output.display());
+        }
+        Command::Infer { input, table, sheet, entry, encoding, sample } => {
+            let mut source =
+                TabularSource::open(&input, sheet.as_deref(), entry.as_deref(), encoding)?;
+            let schema = infer_schema(&mut source, InferOptions::new(sample))?;
+            print!("{}", schema.to_ddl(&table, SqlDialect::SqlServer));
+            eprintln!("{} rows scanned", schema.rows_scanned);
+        }
+        Command::Preview { input, sheet, entry, encoding, rows } => {
+            let mut source =
+                TabularSource::open(&input, sheet.as_deref(), entry.as_deref(), encoding)?;
+            let headers = source.headers()?.unwrap_or_default();
+            let records: Vec<Vec<String>> =
+                source.try_cursor()?.take(rows).collect::<Result<_, _>>()?;
+            print!("{}", render_table(&headers, &records));
+        }
+    }
+
+    Ok(())
+}
+
+//NOTE: with a datasource name the config file is read as a catalog of named datasources.
+async fn connect(
+    config: &Path,
+    datasource: Option<&str>,
+) -> Result<BoxedQueryHandler, Box<dyn std::error::Error>> {
+    let inform = match datasource {
+        Some(name) => Catalog::from_file(config).and_then(|catalog| catalog.inform(name)),
+        None => DataSourceInform::from_file(config),
+    }
+    .map_err(|e| CliError::InvalidArgument(format!("Cannot read {}: {}", config.display(), e)))?;
+    let mut handler = qry_handler_from_dsi(inform)
+        .map_err(|e| CliError::ConnectionFailed(format!("{}: {}", config.display(), e)))?;
+    handler.connect().await?;
+    Ok(handler)
+}
+
+fn read_sql(
+    sql: Option<String>,
+    file: Option<PathBuf>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    match (sql, file) {
+        (Some(sql), None) => Ok(sql),
+        (None, Some(file)) => Ok(std::fs::read_to_string(file)?),
+        _ => Err(Box::new(CliError::InvalidArgument(
+            "Give the SQL either as an argument or with --file".to_string(),
+        ))),
+    }
+}
+
+pub async fn run_query<H>(
+    handler: &mut H,
+    sql: &str,
+    max_rows: Option<usize>,
+) -> Result<String, Box<dyn std::error::Error>>
+where
+    H: QueryHandler,
+{
+    let fetched = Arc::new(AtomicUsize::new(0));
+    let fetch_more: tabularuq::rdb_qry_handler::FetchMore = match max_rows {
+        //NOTE: the column metadata comes first, so stopping there returns only the header.
+        Some(max_rows) => Box::new(move |_, record| {
+            if record.is_none() {
+                return max_rows > 0;
+            }
+            fetched.fetch_add(1, Ordering::Relaxed) + 1 < max_rows
+        }),
+        None => H::default_fetch_more(),
+    };
+
+    let rows = handler.query(sql, None, fetch_more).await?;
+    let headers = rows.column_meta().map(|meta| meta.to_vec()).unwrap_or_default();
+    let records: Vec<Vec<String>> = rows.records().iter().map(format_record).collect();
+    Ok(render_table(&headers, &records))
+}
+
+fn parse_mappings(
+    mappings: &[String],
+    headers: Option<&[String]>,
+) -> Result<ColumnMapping, Box<dyn std::error::Error>> {
+    let mut pairs = Vec::with_capacity(mappings.len());
+    for mapping in mappings {
+        let (source, target) = mapping.split_once('=').ok_or(CliError::InvalidArgument(
+            format!("Mapping {} is not in the form source=target", mapping),
+        ))?;
+        let index = match source.parse::<usize>() {
+            Ok(position) if position >= 1 => position - 1,
+            _ => headers
+                .and_then(|headers| headers.iter().position(|header| header == source))
+                .ok_or(CliError::InvalidArgument(format!("Unknown source column {}", source)))?,
+        };
+        pairs.push((index, target.to_string()));
+    }
+    Ok(ColumnMapping::new(pairs))
+}
+
+fn convert<C>(
+    source: &mut C,
+    output: &Path,
+    options: WriteOptions,
+) -> Result<u64, Box<dyn std::error::Error>>
+where
+    C: TabularCursor,
+{
+    let mut writer = open_writer(output, options)?;
+
+    if let Some(headers) = source.headers()? {
+        writer.write_header(&headers)?;
+    }
+    let mut converted = 0;
+    for record in source.try_nullable_cursor()? {
+        let cells: Vec<Option<DataType>> =
+            record?.into_iter().map(|cell| cell.map(DataType::String)).collect();
+        writer.write_record(&cells)?;
+        converted += 1;
+    }
+    writer.finish()?;
+
+    Ok(converted)
+}
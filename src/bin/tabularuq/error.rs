@@ -0,0 +1,21 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    InvalidArgument(String),
+    UnsupportedFormat(String),
+    ConnectionFailed(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CliError::InvalidArgument(ref msg) => write!(f, "Invalid Argument: {}", msg),
+            CliError::UnsupportedFormat(ref msg) => write!(f, "Unsupported Format: {}", msg),
+            CliError::ConnectionFailed(ref msg) => write!(f, "Connection Failed: {}", msg),
+        }
+    }
+}
+
+impl Error for CliError {}
@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use encoding_rs::Encoding;
+use tabularuq::tabular_porter::encoding::TextEncoding;
+
+mod commands;
+mod error;
+mod render;
+mod repl;
+mod source;
+
+#[derive(Parser)]
+#[command(name = "tabularuq", version, about = "Query databases and move tabular data")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run SQL against a datasource and print the result as a table
+    Query {
+        /// Datasource TOML file, or a catalog file when --datasource is given
+        #[arg(short, long)]
+        config: PathBuf,
+        /// Named datasource of the catalog file
+        #[arg(long)]
+        datasource: Option<String>,
+        /// SQL text; read from --file when omitted
+        sql: Option<String>,
+        /// File containing the SQL text
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+        /// Stop after this many rows
+        #[arg(long)]
+        max_rows: Option<usize>,
+    },
+    /// Run SQL against a datasource and write the result to a CSV, XLSX or JSON file
+    Export {
+        #[arg(short, long)]
+        config: PathBuf,
+        #[arg(long)]
+        datasource: Option<String>,
+        sql: Option<String>,
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+        /// Destination file; the format follows the extension unless --format is given. Each
+        /// further result set goes to a new file or sheet
+        #[arg(short, long)]
+        output: PathBuf,
+        #[arg(long, value_enum)]
+        format: Option<FileFormat>,
+        #[arg(long)]
+        no_header: bool,
+        /// Text written for NULL cells
+        #[arg(long, default_value = "")]
+        null: String,
+        /// strftime style format for date/time cells
+        #[arg(long)]
+        date_format: Option<String>,
+        /// Start a new file or sheet after this many rows
+        #[arg(long)]
+        rows_per_part: Option<usize>,
+        /// Text encoding of CSV output such as `cp949`, `shift_jis` or `utf-16le`; UTF-8 by default
+        #[arg(long, value_parser = parse_output_encoding)]
+        encoding: Option<&'static Encoding>,
+        /// Start CSV output with a byte order mark
+        #[arg(long)]
+        bom: bool,
+    },
+    /// Load a CSV, JSON or JSON Lines, or spreadsheet (XLSX, XLS, XLSB, ODS) file, or the
+    /// matching files of a ZIP archive, into a table
+    Import {
+        #[arg(short, long)]
+        config: PathBuf,
+        #[arg(long)]
+        datasource: Option<String>,
+        #[arg(short, long)]
+        input: PathBuf,
+        #[arg(short, long)]
+        table: String,
+        /// Worksheet, Excel Table, defined name or range like `Sheet1!B4:H200` to read from
+        /// spreadsheets; the first sheet when omitted
+        #[arg(long)]
+        sheet: Option<String>,
+        /// ZIP entry name or glob like `orders/*.csv`; every CSV and spreadsheet entry when
+        /// omitted
+        #[arg(long)]
+        entry: Option<String>,
+        /// Text encoding of CSV input such as `cp949`, `shift_jis` or `utf-16`; told by the BOM or
+        /// guessed when omitted
+        #[arg(long, value_parser = parse_encoding, default_value = "auto")]
+        encoding: TextEncoding,
+        /// Not atomic: `truncate` empties the table first, and batches inserted before an
+        /// error are kept. `create` makes a missing table with the column types inferred from
+        /// the whole input
+        #[arg(long, value_enum, default_value_t = ImportModeArg::Append)]
+        mode: ImportModeArg,
+        #[arg(long, default_value_t = 500)]
+        batch_size: usize,
+        /// Explicit `source=target` mapping; source is a header name or a 1-based position
+        #[arg(long = "map")]
+        mappings: Vec<String>,
+    },
+    /// Open an interactive SQL shell
+    Shell {
+        #[arg(short, long)]
+        config: PathBuf,
+        #[arg(long)]
+        datasource: Option<String>,
+        /// History file; defaults to ~/.tabularuq_history
+        #[arg(long)]
+        history: Option<PathBuf>,
+        /// Rows shown before pausing
+        #[arg(long, default_value_t = 40)]
+        page_size: usize,
+    },
+    /// Convert a CSV, JSON or spreadsheet file to CSV, XLSX or JSON
+    Convert {
+        #[arg(short, long)]
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        #[arg(long)]
+        sheet: Option<String>,
+        /// ZIP entry name or glob; every CSV and spreadsheet entry when omitted
+        #[arg(long)]
+        entry: Option<String>,
+        /// Text encoding of CSV input such as `cp949`, `shift_jis` or `utf-16`; told by the BOM or
+        /// guessed when omitted
+        #[arg(long, value_parser = parse_encoding, default_value = "auto")]
+        encoding: TextEncoding,
+        /// Text encoding of CSV output; UTF-8 by default
+        #[arg(long, value_parser = parse_output_encoding)]
+        output_encoding: Option<&'static Encoding>,
+        /// Start CSV output with a byte order mark
+        #[arg(long)]
+        bom: bool,
+    },
+    /// Infer column types from a CSV, JSON or spreadsheet file and print CREATE TABLE DDL
+    Infer {
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Table name used in the DDL
+        #[arg(short, long)]
+        table: String,
+        #[arg(long)]
+        sheet: Option<String>,
+        /// ZIP entry name or glob; every CSV and spreadsheet entry when omitted
+        #[arg(long)]
+        entry: Option<String>,
+        /// Text encoding of CSV input such as `cp949`, `shift_jis` or `utf-16`; told by the BOM or
+        /// guessed when omitted
+        #[arg(long, value_parser = parse_encoding, default_value = "auto")]
+        encoding: TextEncoding,
+        /// Rows to scan; every row is scanned when omitted
+        #[arg(long)]
+        sample: Option<usize>,
+    },
+    /// Print the first rows of a CSV, JSON or spreadsheet file
+    Preview {
+        #[arg(short, long)]
+        input: PathBuf,
+        #[arg(long)]
+        sheet: Option<String>,
+        /// ZIP entry name or glob; every CSV and spreadsheet entry when omitted
+        #[arg(long)]
+        entry: Option<String>,
+        /// Text encoding of CSV input such as `cp949`, `shift_jis` or `utf-16`; told by the BOM or
+        /// guessed when omitted
+        #[arg(long, value_parser = parse_encoding, default_value = "auto")]
+        encoding: TextEncoding,
+        #[arg(short = 'n', long, default_value_t = 10)]
+        rows: usize,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FileFormat {
+    Csv,
+    Xlsx,
+    Json,
+    Jsonl,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ImportModeArg {
+    Append,
+    Truncate,
+    Create,
+}
+
+fn parse_encoding(label: &str) -> Result<TextEncoding, String> {
+    TextEncoding::from_label(label).ok_or(format!("unknown encoding {}", label))
+}
+
+fn parse_output_encoding(label: &str) -> Result<&'static Encoding, String> {
+    match parse_encoding(label)? {
+        TextEncoding::Fixed(encoding) => Ok(encoding),
+        TextEncoding::Auto => Err("output needs an explicit encoding".to_string()),
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match commands::run(cli.command).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
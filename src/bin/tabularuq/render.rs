@@ -0,0 +1,55 @@
+use tabularuq::rdb_qry_handler::{DataRecord, datatype::DataType};
+
+pub const NULL_TEXT: &str = "NULL";
+
+pub fn format_record(record: &DataRecord) -> Vec<String> {
+    record.cells().iter().map(|cell| format_cell(cell.as_ref())).collect()
+}
+
+pub fn format_cell(cell: Option<&DataType>) -> String {
+    match cell {
+        Some(value) => value.to_string(),
+        None => NULL_TEXT.to_string(),
+    }
+}
+
+//NOTE: renders rows as an aligned text table; newlines and tabs inside cells are escaped.
+pub fn render_table(headers: &[String], rows: &[Vec<String>]) -> String {
+    let escape = |text: &str| text.replace('\n', "\\n").replace('\t', "\\t");
+    let headers: Vec<String> = headers.iter().map(|h| escape(h)).collect();
+    let rows: Vec<Vec<String>> =
+        rows.iter().map(|row| row.iter().map(|cell| escape(cell)).collect()).collect();
+
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0).max(headers.len());
+    let mut widths = vec![0; columns];
+    for row in std::iter::once(&headers).chain(rows.iter()) {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    let line = |row: &[String]| {
+        let cells: Vec<String> = (0..columns)
+            .map(|i| {
+                let cell = row.get(i).map(|c| c.as_str()).unwrap_or("");
+                format!("{}{}", cell, " ".repeat(widths[i] - cell.chars().count()))
+            })
+            .collect();
+        cells.join(" | ").trim_end().to_string()
+    };
+
+    let mut output = String::new();
+    if !headers.is_empty() {
+        output.push_str(&line(&headers));
+        output.push('\n');
+        let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+        output.push_str(&separator.join("-+-"));
+        output.push('\n');
+    }
+    for row in &rows {
+        output.push_str(&line(row));
+        output.push('\n');
+    }
+    output.push_str(&format!("({} rows)\n", rows.len()));
+    output
+}
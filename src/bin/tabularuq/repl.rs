@@ -0,0 +1,394 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use rustyline::{DefaultEditor, error::ReadlineError};
+use tabularuq::rdb_qry_handler::{
+    DataRows, QueryHandler, QueryResult,
+    schema::{SchemaInspector, TableKind},
+};
+use tabularuq::tabular_porter::WriteOptions;
+
+use crate::error::CliError;
+use crate::render::{format_record, render_table};
+use crate::source::open_writer;
+
+const PROMPT: &str = "tabularuq> ";
+const CONTINUATION_PROMPT: &str = "        -> ";
+const MORE_PROMPT: &str = "-- more (Enter to continue, q to stop) --";
+//NOTE: EXEC is left out because stored procedures usually return rows.
+const MUTATION_KEYWORDS: [&str; 8] =
+    ["INSERT", "UPDATE", "DELETE", "MERGE", "CREATE", "DROP", "ALTER", "TRUNCATE"];
+const HELP: &str = "\
+Statements end with `;` or a line containing only `GO`.
+  \\tables [schema]    list tables and views
+  \\describe <table>   list the columns of a table
+  \\export <file>      write the last result to a CSV or XLSX file
+  \\timing             toggle statement timing
+  \\help               show this message
+  \\q                  quit
+";
+
+pub struct Repl {
+    editor: DefaultEditor,
+    history: PathBuf,
+    page_size: usize,
+    timing: bool,
+    last_result: Option<DataRows>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Input {
+    Statement(String),
+    Meta(String),
+    Quit,
+}
+
+#[derive(Debug, PartialEq)]
+enum MetaCommand {
+    Help,
+    Timing,
+    Tables(Option<String>),
+    Describe(String),
+    Export(PathBuf),
+    Unknown(String),
+}
+
+impl Repl {
+    pub fn new(history: PathBuf, page_size: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut editor = DefaultEditor::new()?;
+        //NOTE: a missing history file is expected on the first run.
+        let _ = editor.load_history(&history);
+        Ok(Repl { editor, history, page_size: page_size.max(1), timing: false, last_result: None })
+    }
+
+    pub fn default_history() -> PathBuf {
+        let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+        home.join(".tabularuq_history")
+    }
+
+    pub async fn run<H>(&mut self, handler: &mut H) -> Result<(), Box<dyn std::error::Error>>
+    where
+        H: QueryHandler + SchemaInspector,
+    {
+        println!("Type \\help for help, \\q to quit.");
+        loop {
+            let input = match self.read_input()? {
+                Some(input) => input,
+                None => break,
+            };
+            let result = match input {
+                Input::Quit => break,
+                Input::Meta(command) => self.run_meta(handler, &command).await,
+                Input::Statement(sql) => self.run_statement(handler, &sql).await,
+            };
+            if let Err(e) = result {
+                eprintln!("error: {}", e);
+            }
+        }
+
+        self.editor.save_history(&self.history)?;
+        Ok(())
+    }
+
+    fn read_input(&mut self) -> Result<Option<Input>, Box<dyn std::error::Error>> {
+        let mut buffer = String::new();
+        loop {
+            let prompt = if buffer.is_empty() { PROMPT } else { CONTINUATION_PROMPT };
+            let line = match self.editor.readline(prompt) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => {
+                    buffer.clear();
+                    continue;
+                }
+                Err(ReadlineError::Eof) => return Ok(None),
+                Err(e) => return Err(Box::new(e)),
+            };
+            if let Some((input, history)) = accept_line(&mut buffer, &line) {
+                if let Some(history) = history {
+                    self.editor.add_history_entry(history)?;
+                }
+                return Ok(Some(input));
+            }
+        }
+    }
+
+    async fn run_meta<H>(
+        &mut self,
+        handler: &mut H,
+        command: &str,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        H: QueryHandler + SchemaInspector,
+    {
+        match parse_meta(command) {
+            MetaCommand::Help => print!("{}", HELP),
+            MetaCommand::Timing => {
+                self.timing = !self.timing;
+                println!("Timing is {}.", if self.timing { "on" } else { "off" });
+            }
+            MetaCommand::Tables(schema) => {
+                let tables = handler.tables(schema.as_deref()).await?;
+                let headers = ["schema", "name", "kind"].map(String::from);
+                let rows: Vec<Vec<String>> = tables
+                    .into_iter()
+                    .map(|table| {
+                        let kind = match table.kind {
+                            TableKind::Table => "table",
+                            TableKind::View => "view",
+                        };
+                        vec![table.schema, table.name, kind.to_string()]
+                    })
+                    .collect();
+                self.page(&render_table(&headers, &rows))?;
+            }
+            MetaCommand::Describe(table) => {
+                let table = table.as_str();
+                let columns = handler.columns(table).await?;
+                if columns.is_empty() {
+                    return Err(Box::new(CliError::InvalidArgument(format!(
+                        "Table {} does not exist",
+                        table
+                    ))));
+                }
+                let primary_key = handler.primary_key(table).await?;
+                let key_columns = primary_key.map(|key| key.columns).unwrap_or_default();
+
+                let headers = ["column", "type", "nullable", "default", "key"].map(String::from);
+                let rows: Vec<Vec<String>> = columns
+                    .into_iter()
+                    .map(|column| {
+                        let mut key = Vec::new();
+                        if key_columns.contains(&column.name) {
+                            key.push("PK");
+                        }
+                        if column.identity {
+                            key.push("IDENTITY");
+                        }
+                        let declared_type = column.declared_type();
+                        let nullable = if column.nullable { "YES" } else { "NO" };
+                        vec![
+                            column.name,
+                            declared_type,
+                            nullable.to_string(),
+                            column.default.unwrap_or_default(),
+                            key.join(" "),
+                        ]
+                    })
+                    .collect();
+                self.page(&render_table(&headers, &rows))?;
+            }
+            MetaCommand::Export(file) => {
+                let written = self.export_last_result(&file)?;
+                println!("{} rows written to {}", written, file.display());
+            }
+            MetaCommand::Unknown(command) => {
+                return Err(Box::new(CliError::InvalidArgument(format!(
+                    "Unknown command {}; type \\help for the list",
+                    command
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    async fn run_statement<H>(
+        &mut self,
+        handler: &mut H,
+        sql: &str,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        H: QueryHandler + SchemaInspector,
+    {
+        if is_mutation(sql) {
+            let started = Instant::now();
+            let result = handler.mutate(sql, None).await?;
+            let affected_rows = result.affected_rows();
+            let elapsed = started.elapsed();
+            println!("({} rows affected)", affected_rows);
+            self.print_timing(elapsed);
+            return Ok(());
+        }
+        self.run_query(handler, sql).await
+    }
+
+    async fn run_query<H>(
+        &mut self,
+        handler: &mut H,
+        sql: &str,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        H: QueryHandler + SchemaInspector,
+    {
+        let started = Instant::now();
+        let rows = handler.query(sql, None, H::default_fetch_more()).await?;
+        //NOTE: measured before paging so the wait for the user is left out.
+        let elapsed = started.elapsed();
+
+        let headers = rows.column_meta().map(|meta| meta.to_vec()).unwrap_or_default();
+        let records: Vec<Vec<String>> = rows.records().iter().map(format_record).collect();
+        self.page(&render_table(&headers, &records))?;
+        self.print_timing(elapsed);
+
+        self.last_result = Some(rows);
+        Ok(())
+    }
+
+    fn page(&mut self, output: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let lines: Vec<&str> = output.lines().collect();
+        for (i, chunk) in lines.chunks(self.page_size).enumerate() {
+            if i > 0 {
+                match self.editor.readline(MORE_PROMPT) {
+                    Ok(answer) if answer.trim().eq_ignore_ascii_case("q") => break,
+                    Ok(_) => {}
+                    Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+                    Err(e) => return Err(Box::new(e)),
+                }
+            }
+            for line in chunk {
+                println!("{}", line);
+            }
+        }
+        Ok(())
+    }
+
+    fn print_timing(&self, elapsed: Duration) {
+        if self.timing {
+            println!("Time: {:.3} ms", elapsed.as_secs_f64() * 1000.0);
+        }
+    }
+
+    fn export_last_result(&self, path: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+        let rows = self
+            .last_result
+            .as_ref()
+            .ok_or(CliError::InvalidArgument("There is no result to export yet".to_string()))?;
+        let mut writer = open_writer(path, WriteOptions::default())?;
+
+        if let Some(meta) = rows.column_meta() {
+            writer.write_header(meta)?;
+        }
+        for record in rows.records() {
+            writer.write_record(record.cells())?;
+        }
+        writer.finish()?;
+        Ok(rows.records().len())
+    }
+}
+
+//NOTE: adds a line to `buffer` and gives back the input once it is complete, with the entry to
+//      keep in the history.
+fn accept_line(buffer: &mut String, line: &str) -> Option<(Input, Option<String>)> {
+    let trimmed = line.trim();
+    if buffer.is_empty() {
+        if trimmed.is_empty() {
+            return None;
+        }
+        if matches!(trimmed, "\\q" | "quit" | "exit") {
+            return Some((Input::Quit, None));
+        }
+        if trimmed.starts_with('\\') {
+            return Some((Input::Meta(trimmed.to_string()), Some(trimmed.to_string())));
+        }
+    }
+
+    if trimmed.eq_ignore_ascii_case("go") {
+        if buffer.trim().is_empty() {
+            buffer.clear();
+            return None;
+        }
+        let history = format!("{}\nGO", buffer.trim_end());
+        return Some((Input::Statement(std::mem::take(buffer)), Some(history)));
+    }
+
+    buffer.push_str(line);
+    buffer.push('\n');
+    if trimmed.ends_with(';') {
+        let history = buffer.trim_end().to_string();
+        return Some((Input::Statement(std::mem::take(buffer)), Some(history)));
+    }
+    None
+}
+
+fn parse_meta(command: &str) -> MetaCommand {
+    let (name, argument) = match command.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (command, ""),
+    };
+    match (name, argument) {
+        ("\\help", _) | ("\\?", _) => MetaCommand::Help,
+        ("\\timing", _) => MetaCommand::Timing,
+        ("\\tables", "") => MetaCommand::Tables(None),
+        ("\\tables", schema) => MetaCommand::Tables(Some(schema.to_string())),
+        ("\\describe", table) if !table.is_empty() => MetaCommand::Describe(table.to_string()),
+        ("\\export", file) if !file.is_empty() => MetaCommand::Export(PathBuf::from(file)),
+        _ => MetaCommand::Unknown(command.to_string()),
+    }
+}
+
+fn is_mutation(sql: &str) -> bool {
+    let keyword = sql.split_whitespace().next().unwrap_or_default().to_uppercase();
+    MUTATION_KEYWORDS.contains(&keyword.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept_lines(lines: &[&str]) -> Vec<(Input, Option<String>)> {
+        let mut buffer = String::new();
+        lines.iter().filter_map(|line| accept_line(&mut buffer, line)).collect()
+    }
+
+    #[test]
+    fn accept_line_test() {
+        assert_eq!(
+            accept_lines(&["", "SELECT *", "FROM t;", "\\tables dbo", "quit"]),
+            vec![
+                (
+                    Input::Statement("SELECT *\nFROM t;\n".to_string()),
+                    Some("SELECT *\nFROM t;".to_string())
+                ),
+                (Input::Meta("\\tables dbo".to_string()), Some("\\tables dbo".to_string())),
+                (Input::Quit, None),
+            ]
+        );
+        assert_eq!(
+            accept_lines(&["go", "EXEC refresh", "  GO  "]),
+            vec![(
+                Input::Statement("EXEC refresh\n".to_string()),
+                Some("EXEC refresh\nGO".to_string())
+            )]
+        );
+        //NOTE: a backslash inside a statement is part of the SQL.
+        assert_eq!(
+            accept_lines(&["SELECT 1", "\\q", ";"]),
+            vec![(
+                Input::Statement("SELECT 1\n\\q\n;\n".to_string()),
+                Some("SELECT 1\n\\q\n;".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_meta_test() {
+        assert_eq!(parse_meta("\\?"), MetaCommand::Help);
+        assert_eq!(parse_meta("\\timing"), MetaCommand::Timing);
+        assert_eq!(parse_meta("\\tables"), MetaCommand::Tables(None));
+        assert_eq!(parse_meta("\\tables  sales "), MetaCommand::Tables(Some("sales".to_string())));
+        assert_eq!(parse_meta("\\describe dbo.orders"), MetaCommand::Describe("dbo.orders".into()));
+        assert_eq!(parse_meta("\\export out.csv"), MetaCommand::Export(PathBuf::from("out.csv")));
+        assert_eq!(parse_meta("\\describe"), MetaCommand::Unknown("\\describe".to_string()));
+        assert_eq!(parse_meta("\\nope"), MetaCommand::Unknown("\\nope".to_string()));
+    }
+
+    #[test]
+    fn is_mutation_test() {
+        assert!(is_mutation("insert into t values (1);"));
+        assert!(is_mutation("\n  TRUNCATE TABLE t"));
+        assert!(!is_mutation("EXEC sp_who;"));
+        assert!(!is_mutation("SELECT 1"));
+        assert!(!is_mutation(""));
+    }
+}
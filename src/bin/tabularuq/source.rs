@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use tabularuq::pipeline::ExportFormat;
+use tabularuq::tabular_porter::{
+    TabularCursor, TabularNullableRecord, TabularPorter, TabularStringRecord, TabularWriter,
+    WriteOptions,
+    compression::Compression,
+    csv::{CsvPorter, CsvWriter},
+    encoding::TextEncoding,
+    error::TabularPortError,
+    json::{JsonFormat, JsonPorter, JsonWriter},
+    spreadsheet::{SpreadsheetFormat, SpreadsheetPorter},
+    xslx::XlsxWriter,
+    zip::{ZipEntries, ZipPorter},
+};
+
+use crate::error::CliError;
+
+//NOTE: spreadsheet sources are read with their first row as the header, like CSV files.
+pub enum TabularSource {
+    Csv(CsvPorter),
+    Spreadsheet(Box<SpreadsheetPorter>),
+    Json(JsonPorter),
+    Zip(ZipEntries),
+}
+
+impl TabularSource {
+    pub fn open(
+        path: &Path,
+        sheet: Option<&str>,
+        entry: Option<&str>,
+        encoding: TextEncoding,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let (_, name) = Compression::split_path(path);
+        let extension =
+            name.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+        if extension == "zip" && sheet.is_some() {
+            return Err(Box::new(CliError::InvalidArgument(
+                "--sheet does not apply to ZIP archives; use --entry".to_string(),
+            )));
+        }
+        if extension != "zip" && entry.is_some() {
+            return Err(Box::new(CliError::InvalidArgument(
+                "--entry applies only to ZIP archives".to_string(),
+            )));
+        }
+        match extension.as_str() {
+            "csv" | "txt" => Ok(TabularSource::Csv(CsvPorter::open(path, encoding)?)),
+            _ if SpreadsheetFormat::from_extension(&extension).is_some() => {
+                let mut porter = SpreadsheetPorter::new(path)?;
+                match sheet {
+                    Some(sheet) => porter.init_named(sheet)?,
+                    None => porter.init_range_by_index(0)?,
+                }
+                porter.set_header_row(true);
+                Ok(TabularSource::Spreadsheet(Box::new(porter)))
+            }
+            _ if JsonFormat::from_extension(&extension).is_some() => {
+                Ok(TabularSource::Json(JsonPorter::new(path)?))
+            }
+            "zip" => {
+                let porter = ZipPorter::new(path)?;
+                let entries = match entry {
+                    Some(entry) => porter.open_all(entry)?,
+                    None => porter.open_tabular()?,
+                };
+                Ok(TabularSource::Zip(entries))
+            }
+            _ => Err(Box::new(CliError::UnsupportedFormat(path.display().to_string()))),
+        }
+    }
+}
+
+impl TabularCursor for TabularSource {
+    fn cursor(
+        &mut self,
+    ) -> Result<impl Iterator<Item = TabularStringRecord>, Box<dyn std::error::Error>> {
+        Ok(self.try_cursor()?.map_while(Result::ok))
+    }
+
+    fn try_cursor(
+        &mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<TabularStringRecord, TabularPortError>>,
+        Box<dyn std::error::Error>,
+    > {
+        let cursor: Box<dyn Iterator<Item = Result<TabularStringRecord, TabularPortError>> + '_> =
+            match self {
+                TabularSource::Csv(porter) => Box::new(porter.try_cursor()?),
+                TabularSource::Spreadsheet(porter) => Box::new(porter.try_cursor()?),
+                TabularSource::Json(porter) => Box::new(porter.try_cursor()?),
+                TabularSource::Zip(entries) => Box::new(entries.try_cursor()?),
+            };
+        Ok(cursor)
+    }
+
+    fn try_nullable_cursor(
+        &mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<TabularNullableRecord, TabularPortError>>,
+        Box<dyn std::error::Error>,
+    > {
+        let cursor: Box<dyn Iterator<Item = Result<TabularNullableRecord, TabularPortError>> + '_> =
+            match self {
+                TabularSource::Csv(porter) => Box::new(porter.try_nullable_cursor()?),
+                TabularSource::Spreadsheet(porter) => Box::new(porter.try_nullable_cursor()?),
+                TabularSource::Json(porter) => Box::new(porter.try_nullable_cursor()?),
+                TabularSource::Zip(entries) => Box::new(entries.try_nullable_cursor()?),
+            };
+        Ok(cursor)
+    }
+
+    fn headers(&mut self) -> Result<Option<TabularStringRecord>, Box<dyn std::error::Error>> {
+        match self {
+            TabularSource::Csv(porter) => porter.headers(),
+            TabularSource::Spreadsheet(porter) => porter.headers(),
+            TabularSource::Json(porter) => porter.headers(),
+            TabularSource::Zip(entries) => entries.headers(),
+        }
+    }
+}
+
+pub fn open_writer(
+    path: &Path,
+    options: WriteOptions,
+) -> Result<Box<dyn TabularWriter>, Box<dyn std::error::Error>> {
+    let format = ExportFormat::from_path(path)
+        .ok_or(CliError::UnsupportedFormat(path.display().to_string()))?;
+    let writer: Box<dyn TabularWriter> = match format {
+        ExportFormat::Csv => Box::new(CsvWriter::create(path, options)?),
+        ExportFormat::Xlsx => Box::new(XlsxWriter::create(path, options)?),
+        ExportFormat::Json => {
+            Box::new(JsonWriter::create_with_format(path, JsonFormat::Array, options)?)
+        }
+        ExportFormat::JsonLines => {
+            Box::new(JsonWriter::create_with_format(path, JsonFormat::Lines, options)?)
+        }
+    };
+    Ok(writer)
+}
@@ -1,2 +1,3 @@
+pub mod pipeline;
 pub mod rdb_qry_handler;
 pub mod tabular_porter;
@@ -0,0 +1,29 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum PipelineError {
+    TableNotFound(String),
+    InvalidMapping(String),
+    InvalidValue(String),
+    InvalidDestination(String),
+    WriteFailed(String),
+    Unknown(String),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PipelineError::TableNotFound(ref msg) => write!(f, "Table Not Found: {}", msg),
+            PipelineError::InvalidMapping(ref msg) => write!(f, "Invalid Mapping: {}", msg),
+            PipelineError::InvalidValue(ref msg) => write!(f, "Invalid Value: {}", msg),
+            PipelineError::InvalidDestination(ref msg) => {
+                write!(f, "Invalid Destination: {}", msg)
+            }
+            PipelineError::WriteFailed(ref msg) => write!(f, "Write Failed: {}", msg),
+            PipelineError::Unknown(ref msg) => write!(f, "Unknown: {}", msg),
+        }
+    }
+}
+
+impl Error for PipelineError {}
@@ -0,0 +1,207 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use encoding_rs::Encoding;
+
+use crate::rdb_qry_handler::{DataRecord, QueryHandler, datatype::DataType};
+use crate::tabular_porter::{
+    TabularWriter, WriteOptions,
+    compression::Compression,
+    csv::CsvWriter,
+    json::{JsonFormat, JsonWriter},
+    xslx::XlsxWriter,
+};
+
+use super::error::PipelineError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+    Json,
+    JsonLines,
+}
+
+impl ExportFormat {
+    //NOTE: a compression extension is skipped, so `out.csv.gz` is a CSV file.
+    pub fn from_path<P>(path: P) -> Option<ExportFormat>
+    where
+        P: AsRef<Path>,
+    {
+        let (_, path) = Compression::split_path(path.as_ref());
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "csv" | "txt" => Some(ExportFormat::Csv),
+            "xlsx" => Some(ExportFormat::Xlsx),
+            "json" => Some(ExportFormat::Json),
+            "jsonl" | "ndjson" => Some(ExportFormat::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    header: bool,
+    rows_per_part: Option<usize>,
+    write_options: WriteOptions,
+}
+
+impl ExportOptions {
+    pub fn new(
+        header: bool,
+        null_value: &str,
+        date_format: Option<&str>,
+        rows_per_part: Option<usize>,
+    ) -> Self {
+        ExportOptions {
+            header,
+            rows_per_part: rows_per_part.map(|rows| rows.max(1)),
+            write_options: WriteOptions::new(null_value, date_format),
+        }
+    }
+
+    pub fn set_encoding(&mut self, encoding: &'static Encoding, bom: bool) {
+        self.write_options.set_encoding(encoding, bom);
+    }
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions::new(true, "", None, None)
+    }
+}
+
+struct ExportState {
+    writer: Box<dyn TabularWriter + Send>,
+    header: bool,
+    column_meta: Option<Vec<String>>,
+    rows_per_part: Option<usize>,
+    rows_in_part: usize,
+    error: Option<String>,
+}
+
+impl ExportState {
+    fn write(&mut self, column_meta: Option<&[String]>, record: Option<DataRecord>) -> bool {
+        let result = self.try_write(column_meta, record);
+        if let Err(e) = result {
+            self.error = Some(e.to_string());
+            return false;
+        }
+        true
+    }
+
+    fn try_write(
+        &mut self,
+        column_meta: Option<&[String]>,
+        record: Option<DataRecord>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        //NOTE: every further result set starts a part (a file or a sheet) of its own, headed
+        //      by its own columns.
+        if let Some(meta) = column_meta {
+            if self.column_meta.is_some() {
+                self.writer.next_part()?;
+                self.rows_in_part = 0;
+            }
+            self.column_meta = Some(meta.to_vec());
+            self.write_header()?;
+        }
+
+        if let Some(record) = record {
+            if self.rows_per_part.is_some_and(|rows| self.rows_in_part >= rows) {
+                self.writer.next_part()?;
+                self.rows_in_part = 0;
+                self.write_header()?;
+            }
+            self.writer.write_record(record.cells())?;
+            self.rows_in_part += 1;
+        }
+        Ok(())
+    }
+
+    fn write_header(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let (true, Some(meta)) = (self.header, self.column_meta.as_ref()) {
+            self.writer.write_header(meta)?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn export<H, P>(
+    handler: &mut H,
+    query: &str,
+    bind_variables: Option<Arc<[DataType]>>,
+    destination: P,
+    format: Option<ExportFormat>,
+    options: ExportOptions,
+) -> Result<u64, Box<dyn std::error::Error>>
+where
+    H: QueryHandler + Send,
+    P: AsRef<Path>,
+{
+    let format = format.or(ExportFormat::from_path(&destination)).ok_or(
+        PipelineError::InvalidDestination(format!(
+            "Cannot infer export format from {}",
+            destination.as_ref().display()
+        )),
+    )?;
+    if format == ExportFormat::Xlsx && Compression::split_path(destination.as_ref()).0.is_some() {
+        return Err(Box::new(PipelineError::InvalidDestination(format!(
+            "XLSX files cannot be compressed: {}",
+            destination.as_ref().display()
+        ))));
+    }
+    let writer: Box<dyn TabularWriter + Send> = match format {
+        ExportFormat::Csv => Box::new(CsvWriter::create(&destination, options.write_options)?),
+        ExportFormat::Xlsx => Box::new(XlsxWriter::create(&destination, options.write_options)?),
+        ExportFormat::Json => Box::new(JsonWriter::create_with_format(
+            &destination,
+            JsonFormat::Array,
+            options.write_options,
+        )?),
+        ExportFormat::JsonLines => Box::new(JsonWriter::create_with_format(
+            &destination,
+            JsonFormat::Lines,
+            options.write_options,
+        )?),
+    };
+
+    let header_rows = if options.header { 1 } else { 0 };
+    let rows_per_part = match (options.rows_per_part, writer.max_rows_per_part()) {
+        (Some(rows), Some(max)) => Some(rows.min(max - header_rows)),
+        (None, Some(max)) => Some(max - header_rows),
+        (rows, None) => rows,
+    };
+    let state = Arc::new(Mutex::new(ExportState {
+        writer,
+        header: options.header,
+        column_meta: None,
+        rows_per_part,
+        rows_in_part: 0,
+        error: None,
+    }));
+
+    let sink_state = state.clone();
+    let exported = handler
+        .query_for_each(
+            query,
+            bind_variables,
+            Box::new(move |column_meta, record| match sink_state.lock() {
+                Ok(mut state) => state.write(column_meta, record),
+                Err(_) => false,
+            }),
+        )
+        .await?;
+
+    let mut state = state
+        .lock()
+        .map_err(|e| PipelineError::Unknown(format!("Export state is poisoned: {}", e)))?;
+    if let Some(error) = state.error.take() {
+        return Err(Box::new(PipelineError::WriteFailed(error)));
+    }
+    state.writer.finish()?;
+
+    Ok(exported)
+}
@@ -0,0 +1,316 @@
+use std::{pin::pin, sync::Arc};
+
+use futures::{Stream, StreamExt, stream};
+
+use crate::rdb_qry_handler::{
+    QueryHandler, QueryResult,
+    bulk::ColumnMapping,
+    datatype::{DataType, DataTypeKind, parse_date_time_with_format},
+    schema::SchemaInspector,
+};
+use crate::tabular_porter::{
+    TabularCursor, TabularNullableRecord, TabularStringRecord, async_cursor::AsyncTabularCursor,
+    error::TabularPortError,
+};
+
+use super::{
+    error::PipelineError,
+    infer::{InferredSchema, SqlDialect},
+    quote_ident, quote_table,
+};
+
+//NOTE: SQL Server accepts at most 2100 parameters and 1000 rows per INSERT ... VALUES statement.
+const MAX_PARAMETERS: usize = 2000;
+const MAX_VALUES_ROWS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    Append,
+    Truncate,
+    CreateIfMissing,
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportMapping {
+    ByHeader,
+    Explicit(ColumnMapping),
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    mode: ImportMode,
+    mapping: ImportMapping,
+    batch_size: usize,
+    date_formats: Vec<(String, String)>,
+    schema: Option<InferredSchema>,
+}
+
+impl ImportOptions {
+    pub fn new(mode: ImportMode, mapping: ImportMapping, batch_size: usize) -> Self {
+        ImportOptions {
+            mode,
+            mapping,
+            batch_size: batch_size.max(1),
+            date_formats: Vec::new(),
+            schema: None,
+        }
+    }
+
+    //NOTE: `(column, format)` pairs, such as `InferredSchema::date_formats`, for date columns
+    //      whose text the default formats would misread, like day-first dates. A column given
+    //      several formats takes the first that reads its value.
+    pub fn set_date_formats(&mut self, date_formats: Vec<(String, String)>) {
+        self.date_formats = date_formats;
+    }
+
+    //NOTE: the schema of the source, such as `infer_schema` over it, that `CreateIfMissing` types
+    //      the columns of a new table with; its date formats are added too. Without one the
+    //      columns are created as nvarchar(max).
+    pub fn set_schema(&mut self, schema: InferredSchema) {
+        self.date_formats.extend(schema.date_formats());
+        self.schema = Some(schema);
+    }
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions::new(ImportMode::Append, ImportMapping::ByHeader, 500)
+    }
+}
+
+struct ImportColumn {
+    source: usize,
+    name: String,
+    kind: DataTypeKind,
+    date_formats: Vec<String>,
+}
+
+//NOTE: not atomic. TRUNCATE and every batch are committed as separate statements, so a failure
+//      part way leaves the table truncated with the batches inserted before it.
+pub async fn import<H, P>(
+    handler: &mut H,
+    porter: &mut P,
+    table: &str,
+    options: ImportOptions,
+) -> Result<u64, Box<dyn std::error::Error>>
+where
+    H: QueryHandler + SchemaInspector,
+    P: TabularCursor,
+{
+    let headers = match options.mapping {
+        ImportMapping::ByHeader => porter.headers()?,
+        ImportMapping::Explicit(_) => None,
+    };
+    let columns = import_columns(handler, table, &options, headers).await?;
+    let records = stream::iter(porter.try_nullable_cursor()?);
+    insert_records(handler, table, &options, &columns, records).await
+}
+
+//NOTE: same as `import`, but the source is read on its own while each batch is inserted.
+pub async fn import_stream<H, C>(
+    handler: &mut H,
+    mut cursor: C,
+    table: &str,
+    options: ImportOptions,
+) -> Result<u64, Box<dyn std::error::Error>>
+where
+    H: QueryHandler + SchemaInspector,
+    C: AsyncTabularCursor,
+{
+    let headers = match options.mapping {
+        ImportMapping::ByHeader => cursor.headers().await?,
+        ImportMapping::Explicit(_) => None,
+    };
+    let columns = import_columns(handler, table, &options, headers).await?;
+    insert_records(handler, table, &options, &columns, cursor.nullable_stream()).await
+}
+
+//NOTE: inserts the records in batches of as many rows as one statement can take.
+async fn insert_records<H, S>(
+    handler: &mut H,
+    table: &str,
+    options: &ImportOptions,
+    columns: &[ImportColumn],
+    records: S,
+) -> Result<u64, Box<dyn std::error::Error>>
+where
+    H: QueryHandler,
+    S: Stream<Item = Result<TabularNullableRecord, TabularPortError>>,
+{
+    let rows_per_statement = rows_per_statement(options, columns);
+    let mut imported = 0;
+    let mut batch = Vec::with_capacity(rows_per_statement);
+    let mut records = pin!(records.enumerate());
+    while let Some((i, record)) = records.next().await {
+        batch.push(convert_record(columns, &record?, i + 1)?);
+        if batch.len() >= rows_per_statement {
+            imported += insert_batch(handler, table, columns, &mut batch).await?;
+        }
+    }
+    if !batch.is_empty() {
+        imported += insert_batch(handler, table, columns, &mut batch).await?;
+    }
+
+    Ok(imported)
+}
+
+async fn import_columns<H>(
+    handler: &mut H,
+    table: &str,
+    options: &ImportOptions,
+    headers: Option<TabularStringRecord>,
+) -> Result<Vec<ImportColumn>, Box<dyn std::error::Error>>
+where
+    H: QueryHandler + SchemaInspector,
+{
+    let mapping = match &options.mapping {
+        ImportMapping::ByHeader => {
+            let headers = headers.ok_or(PipelineError::InvalidMapping(
+                "Source has no header row to map by name".to_string(),
+            ))?;
+            ColumnMapping::by_headers(&headers)
+        }
+        ImportMapping::Explicit(mapping) => mapping.clone(),
+    };
+
+    let mut table_columns = handler.columns(table).await?;
+    if table_columns.is_empty() {
+        if options.mode != ImportMode::CreateIfMissing {
+            return Err(Box::new(PipelineError::TableNotFound(table.to_string())));
+        }
+        create_table(handler, table, &mapping, options.schema.as_ref()).await?;
+        table_columns = handler.columns(table).await?;
+    }
+
+    let mut columns = Vec::new();
+    for (source, target) in mapping.pairs() {
+        let column = table_columns
+            .iter()
+            .find(|column| column.name.to_lowercase() == target.to_lowercase())
+            .ok_or(PipelineError::InvalidMapping(format!(
+                "Column {} does not exist in {}",
+                target, table
+            )))?;
+        let date_formats = options
+            .date_formats
+            .iter()
+            .filter(|(name, _)| name.to_lowercase() == column.name.to_lowercase())
+            .map(|(_, format)| format.clone())
+            .collect();
+        columns.push(ImportColumn {
+            source: *source,
+            name: column.name.clone(),
+            kind: column.kind(),
+            date_formats,
+        });
+    }
+    if columns.is_empty() {
+        return Err(Box::new(PipelineError::InvalidMapping("No columns to import".to_string())));
+    }
+
+    if options.mode == ImportMode::Truncate {
+        handler.mutate(&format!("TRUNCATE TABLE {}", quote_table(table)), None).await?;
+    }
+    Ok(columns)
+}
+
+fn rows_per_statement(options: &ImportOptions, columns: &[ImportColumn]) -> usize {
+    options.batch_size.min(MAX_VALUES_ROWS).min((MAX_PARAMETERS / columns.len()).max(1))
+}
+
+async fn create_table<H>(
+    handler: &mut H,
+    table: &str,
+    mapping: &ColumnMapping,
+    schema: Option<&InferredSchema>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    H: QueryHandler,
+{
+    let definitions: Vec<String> = mapping
+        .pairs()
+        .iter()
+        .map(|(source, column)| match schema.and_then(|schema| schema.columns.get(*source)) {
+            Some(inferred) => format!(
+                "{} {} {}",
+                quote_ident(column),
+                inferred.data_type.sql_type(SqlDialect::SqlServer),
+                if inferred.nullable { "NULL" } else { "NOT NULL" }
+            ),
+            None => format!("{} nvarchar(max) NULL", quote_ident(column)),
+        })
+        .collect();
+    handler
+        .mutate(&format!("CREATE TABLE {} ({})", quote_table(table), definitions.join(", ")), None)
+        .await?;
+    Ok(())
+}
+
+fn convert_record(
+    columns: &[ImportColumn],
+    record: &[Option<String>],
+    row_number: usize,
+) -> Result<Vec<Option<DataType>>, PipelineError> {
+    columns
+        .iter()
+        .map(|column| {
+            let text = match record.get(column.source) {
+                Some(Some(text)) => text,
+                _ => return Ok(None),
+            };
+            if text.is_empty() && column.kind != DataTypeKind::String {
+                return Ok(None);
+            }
+            let value = match (column.date_formats.is_empty(), column.kind) {
+                (false, DataTypeKind::DateTime) => column
+                    .date_formats
+                    .iter()
+                    .find_map(|format| parse_date_time_with_format(text.trim(), format))
+                    .map(DataType::DateTime),
+                _ => column.kind.parse(text),
+            };
+            value.map(Some).ok_or(PipelineError::InvalidValue(format!(
+                "Row {}: cannot convert '{}' to {:?} for column {}",
+                row_number, text, column.kind, column.name
+            )))
+        })
+        .collect()
+}
+
+//NOTE: NULL cells are written as literals because bind variables cannot carry a NULL.
+async fn insert_batch<H>(
+    handler: &mut H,
+    table: &str,
+    columns: &[ImportColumn],
+    batch: &mut Vec<Vec<Option<DataType>>>,
+) -> Result<u64, Box<dyn std::error::Error>>
+where
+    H: QueryHandler,
+{
+    let column_list: Vec<String> = columns.iter().map(|column| quote_ident(&column.name)).collect();
+    let mut binds = Vec::new();
+    let mut values = Vec::with_capacity(batch.len());
+    for row in batch.drain(..) {
+        let cells: Vec<String> = row
+            .into_iter()
+            .map(|cell| match cell {
+                Some(value) => {
+                    binds.push(value);
+                    format!("@P{}", binds.len())
+                }
+                None => "NULL".to_string(),
+            })
+            .collect();
+        values.push(format!("({})", cells.join(", ")));
+    }
+
+    let query = format!(
+        "INSERT INTO {} ({}) VALUES {}",
+        quote_table(table),
+        column_list.join(", "),
+        values.join(", ")
+    );
+    let bind_variables = if binds.is_empty() { None } else { Some(Arc::from(binds)) };
+    let result = handler.mutate(&query, bind_variables).await?;
+    Ok(result.affected_rows())
+}
@@ -0,0 +1,379 @@
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::rdb_qry_handler::datatype::{DATE_FORMATS, DATE_TIME_FORMATS, DATE_TIME_OFFSET_FORMATS};
+use crate::tabular_porter::TabularCursor;
+
+use super::{quote_ident, quote_table};
+
+const BOOL_VALUES: [&str; 8] = ["true", "false", "yes", "no", "t", "f", "y", "n"];
+const MAX_DECIMAL_PRECISION: usize = 38;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlDialect {
+    SqlServer,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InferredType {
+    Bool,
+    TinyInt,
+    SmallInt,
+    Int,
+    BigInt,
+    Decimal { precision: usize, scale: usize },
+    Float,
+    Date { format: &'static str },
+    //NOTE: `date_format` reads the date-only values of a column that mixes them with timestamps.
+    DateTime { format: &'static str, date_format: Option<&'static str> },
+    DateTimeOffset { format: &'static str },
+    Text { max_length: usize, unicode: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InferredColumn {
+    pub name: String,
+    pub data_type: InferredType,
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InferredSchema {
+    pub columns: Vec<InferredColumn>,
+    pub rows_scanned: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InferOptions {
+    sample_rows: Option<usize>,
+}
+
+impl InferOptions {
+    //NOTE: `None` scans every row.
+    pub fn new(sample_rows: Option<usize>) -> Self {
+        InferOptions { sample_rows }
+    }
+}
+
+impl InferredSchema {
+    //NOTE: the formats each date or time column was inferred with, to read it back on import.
+    pub fn date_formats(&self) -> Vec<(String, String)> {
+        self.columns
+            .iter()
+            .flat_map(|column| {
+                let formats = match column.data_type {
+                    InferredType::Date { format }
+                    | InferredType::DateTime { format, date_format: None }
+                    | InferredType::DateTimeOffset { format } => vec![format],
+                    InferredType::DateTime { format, date_format: Some(date_format) } => {
+                        vec![format, date_format]
+                    }
+                    _ => Vec::new(),
+                };
+                formats.into_iter().map(|format| (column.name.clone(), format.to_string()))
+            })
+            .collect()
+    }
+
+    pub fn to_ddl(&self, table: &str, dialect: SqlDialect) -> String {
+        let definitions: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| {
+                format!(
+                    "    {} {} {}",
+                    quote_ident(&column.name),
+                    column.data_type.sql_type(dialect),
+                    if column.nullable { "NULL" } else { "NOT NULL" }
+                )
+            })
+            .collect();
+        format!("CREATE TABLE {} (\n{}\n);\n", quote_table(table), definitions.join(",\n"))
+    }
+}
+
+impl InferredType {
+    pub fn sql_type(&self, dialect: SqlDialect) -> String {
+        match dialect {
+            SqlDialect::SqlServer => match self {
+                InferredType::Bool => "bit".to_string(),
+                InferredType::TinyInt => "tinyint".to_string(),
+                InferredType::SmallInt => "smallint".to_string(),
+                InferredType::Int => "int".to_string(),
+                InferredType::BigInt => "bigint".to_string(),
+                InferredType::Decimal { precision, scale } => {
+                    format!("decimal({},{})", precision, scale)
+                }
+                InferredType::Float => "float".to_string(),
+                InferredType::Date { .. } => "date".to_string(),
+                InferredType::DateTime { .. } => "datetime2".to_string(),
+                InferredType::DateTimeOffset { .. } => "datetimeoffset".to_string(),
+                InferredType::Text { max_length, unicode } => {
+                    let (name, limit) =
+                        if *unicode { ("nvarchar", 4000) } else { ("varchar", 8000) };
+                    if *max_length > limit {
+                        format!("{}(max)", name)
+                    } else {
+                        format!("{}({})", name, (*max_length).max(1))
+                    }
+                }
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum TemporalKind {
+    Date,
+    DateTime,
+    DateTimeOffset,
+}
+
+enum Candidate {
+    Empty,
+    Bool,
+    Number { int_digits: usize, scale: usize, min: i128, max: i128, float: bool },
+    Temporal { kind: TemporalKind, formats: Vec<&'static str>, date_formats: Vec<&'static str> },
+    Text,
+}
+
+struct ColumnState {
+    name: String,
+    candidate: Candidate,
+    nullable: bool,
+    max_length: usize,
+    unicode: bool,
+}
+
+pub fn infer_schema<P>(
+    porter: &mut P,
+    options: InferOptions,
+) -> Result<InferredSchema, Box<dyn std::error::Error>>
+where
+    P: TabularCursor,
+{
+    let headers = porter.headers()?.unwrap_or_default();
+    let mut states: Vec<ColumnState> = headers.into_iter().map(ColumnState::new).collect();
+
+    let mut rows_scanned = 0;
+    let sample_rows = options.sample_rows.unwrap_or(usize::MAX);
+    for record in porter.try_cursor()?.take(sample_rows) {
+        let record = record?;
+        while states.len() < record.len() {
+            let mut state = ColumnState::new(format!("column{}", states.len() + 1));
+            state.nullable = rows_scanned > 0;
+            states.push(state);
+        }
+        for (i, state) in states.iter_mut().enumerate() {
+            match record.get(i) {
+                Some(text) => state.observe(text),
+                None => state.nullable = true,
+            }
+        }
+        rows_scanned += 1;
+    }
+
+    let columns = states.into_iter().map(ColumnState::finish).collect();
+    Ok(InferredSchema { columns, rows_scanned })
+}
+
+impl ColumnState {
+    fn new(name: String) -> Self {
+        ColumnState {
+            name,
+            candidate: Candidate::Empty,
+            nullable: false,
+            max_length: 0,
+            unicode: false,
+        }
+    }
+
+    fn observe(&mut self, text: &str) {
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            self.nullable = true;
+            return;
+        }
+        self.max_length = self.max_length.max(text.chars().count());
+        self.unicode |= !text.is_ascii();
+
+        self.candidate = match std::mem::replace(&mut self.candidate, Candidate::Text) {
+            Candidate::Empty => classify(trimmed),
+            Candidate::Text => Candidate::Text,
+            Candidate::Bool if is_bool(trimmed) => Candidate::Bool,
+            Candidate::Bool => Candidate::Text,
+            Candidate::Number { int_digits, scale, min, max, float } => {
+                match classify_number(trimmed) {
+                    Some(Candidate::Number {
+                        int_digits: digits,
+                        scale: value_scale,
+                        min: value,
+                        float: value_float,
+                        ..
+                    }) => Candidate::Number {
+                        int_digits: int_digits.max(digits),
+                        scale: scale.max(value_scale),
+                        min: min.min(value),
+                        max: max.max(value),
+                        float: float || value_float,
+                    },
+                    _ => Candidate::Text,
+                }
+            }
+            Candidate::Temporal { kind, formats, date_formats } => {
+                let matching: Vec<&'static str> = formats
+                    .iter()
+                    .copied()
+                    .filter(|format| parses(trimmed, kind, format))
+                    .collect();
+                if !matching.is_empty() {
+                    Candidate::Temporal { kind, formats: matching, date_formats }
+                } else {
+                    //NOTE: a date column that also holds timestamps becomes a datetime column,
+                    //      keeping the formats its dates still match.
+                    match classify_temporal(trimmed) {
+                        Some(Candidate::Temporal {
+                            kind: value_kind,
+                            formats: value_formats,
+                            ..
+                        }) if kind != TemporalKind::DateTimeOffset
+                            && value_kind != TemporalKind::DateTimeOffset
+                            && kind != value_kind =>
+                        {
+                            let (formats, date_formats) = if value_kind > kind {
+                                (value_formats, formats)
+                            } else if date_formats.is_empty() {
+                                (formats, value_formats)
+                            } else {
+                                let date_formats = date_formats
+                                    .into_iter()
+                                    .filter(|format| value_formats.contains(format))
+                                    .collect();
+                                (formats, date_formats)
+                            };
+                            if date_formats.is_empty() {
+                                Candidate::Text
+                            } else {
+                                Candidate::Temporal {
+                                    kind: TemporalKind::DateTime,
+                                    formats,
+                                    date_formats,
+                                }
+                            }
+                        }
+                        _ => Candidate::Text,
+                    }
+                }
+            }
+        };
+    }
+
+    fn finish(self) -> InferredColumn {
+        let text = InferredType::Text { max_length: self.max_length, unicode: self.unicode };
+        let data_type = match self.candidate {
+            Candidate::Empty | Candidate::Text => text,
+            Candidate::Bool => InferredType::Bool,
+            Candidate::Number { float: true, .. } => InferredType::Float,
+            Candidate::Number { int_digits, scale, .. }
+                if int_digits + scale > MAX_DECIMAL_PRECISION =>
+            {
+                InferredType::Float
+            }
+            Candidate::Number { int_digits, scale, .. } if scale > 0 => {
+                InferredType::Decimal { precision: (int_digits + scale).max(1), scale }
+            }
+            Candidate::Number { min, max, int_digits, .. } => {
+                if min >= 0 && max <= u8::MAX as i128 {
+                    InferredType::TinyInt
+                } else if min >= i16::MIN as i128 && max <= i16::MAX as i128 {
+                    InferredType::SmallInt
+                } else if min >= i32::MIN as i128 && max <= i32::MAX as i128 {
+                    InferredType::Int
+                } else if min >= i64::MIN as i128 && max <= i64::MAX as i128 {
+                    InferredType::BigInt
+                } else {
+                    InferredType::Decimal { precision: int_digits, scale: 0 }
+                }
+            }
+            Candidate::Temporal { kind, formats, date_formats } => {
+                let format = formats[0];
+                match kind {
+                    TemporalKind::Date => InferredType::Date { format },
+                    TemporalKind::DateTime => InferredType::DateTime {
+                        format,
+                        date_format: date_formats.first().copied(),
+                    },
+                    TemporalKind::DateTimeOffset => InferredType::DateTimeOffset { format },
+                }
+            }
+        };
+        InferredColumn { name: self.name, data_type, nullable: self.nullable }
+    }
+}
+
+fn classify(text: &str) -> Candidate {
+    if is_bool(text) {
+        return Candidate::Bool;
+    }
+    classify_number(text).or_else(|| classify_temporal(text)).unwrap_or(Candidate::Text)
+}
+
+fn is_bool(text: &str) -> bool {
+    BOOL_VALUES.iter().any(|value| value.eq_ignore_ascii_case(text))
+}
+
+//NOTE: integers with a leading zero, like zip codes, are kept as text.
+fn classify_number(text: &str) -> Option<Candidate> {
+    let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (unsigned, None),
+    };
+
+    let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if is_digits(integer) && fraction.is_none_or(is_digits) {
+        if integer.is_empty() && fraction.is_none_or(|fraction| fraction.is_empty()) {
+            return None;
+        }
+        if integer.len() > 1 && integer.starts_with('0') {
+            return None;
+        }
+        let int_digits = integer.trim_start_matches('0').len();
+        let scale = fraction.map(|fraction| fraction.len()).unwrap_or(0);
+        let value = if scale == 0 { text.parse::<i128>().ok() } else { Some(0) };
+        return Some(Candidate::Number {
+            int_digits,
+            scale,
+            min: value.unwrap_or(0),
+            max: value.unwrap_or(0),
+            float: value.is_none(),
+        });
+    }
+
+    let is_float = unsigned.contains(['e', 'E']) && text.parse::<f64>().is_ok_and(f64::is_finite);
+    is_float.then_some(Candidate::Number { int_digits: 0, scale: 0, min: 0, max: 0, float: true })
+}
+
+fn classify_temporal(text: &str) -> Option<Candidate> {
+    let candidates = [
+        (TemporalKind::DateTimeOffset, &DATE_TIME_OFFSET_FORMATS[..]),
+        (TemporalKind::DateTime, &DATE_TIME_FORMATS[..]),
+        (TemporalKind::Date, &DATE_FORMATS[..]),
+    ];
+    candidates.into_iter().find_map(|(kind, formats)| {
+        let formats: Vec<&'static str> =
+            formats.iter().copied().filter(|format| parses(text, kind, format)).collect();
+        (!formats.is_empty()).then_some(Candidate::Temporal {
+            kind,
+            formats,
+            date_formats: Vec::new(),
+        })
+    })
+}
+
+fn parses(text: &str, kind: TemporalKind, format: &str) -> bool {
+    match kind {
+        TemporalKind::Date => NaiveDate::parse_from_str(text, format).is_ok(),
+        TemporalKind::DateTime => NaiveDateTime::parse_from_str(text, format).is_ok(),
+        TemporalKind::DateTimeOffset => chrono::DateTime::parse_from_str(text, format).is_ok(),
+    }
+}
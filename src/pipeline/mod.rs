@@ -0,0 +1,10 @@
+pub mod error;
+pub mod export;
+pub mod import;
+pub mod infer;
+
+pub use export::{ExportFormat, ExportOptions, export};
+pub use import::{ImportMapping, ImportMode, ImportOptions, import, import_stream};
+pub use infer::{InferOptions, InferredSchema, SqlDialect, infer_schema};
+
+pub(crate) use crate::rdb_qry_handler::sqlserver::{quote_ident, quote_table};
@@ -0,0 +1,67 @@
+use super::DataRecord;
+
+pub type BulkProgress = Box<dyn FnMut(u64) + Send>;
+
+#[derive(Debug, Clone, Default)]
+pub struct ColumnMapping {
+    pairs: Vec<(usize, String)>,
+}
+
+impl ColumnMapping {
+    pub fn new(pairs: Vec<(usize, String)>) -> Self {
+        ColumnMapping { pairs }
+    }
+
+    //NOTE: maps source column i to the target column named after the i-th header.
+    pub fn by_headers(headers: &[String]) -> Self {
+        ColumnMapping { pairs: headers.iter().cloned().enumerate().collect() }
+    }
+
+    pub fn pairs(&self) -> &[(usize, String)] {
+        &self.pairs
+    }
+
+    pub fn source_index(&self, target: &str) -> Option<usize> {
+        self.pairs
+            .iter()
+            .find(|(_, column)| column.to_lowercase() == target.to_lowercase())
+            .map(|(index, _)| *index)
+    }
+}
+
+pub struct BulkLoadOptions {
+    batch_size: usize,
+    skip_rows: usize,
+    progress: Option<BulkProgress>,
+}
+
+impl BulkLoadOptions {
+    pub fn new(batch_size: usize, skip_rows: usize, progress: Option<BulkProgress>) -> Self {
+        BulkLoadOptions { batch_size: batch_size.max(1), skip_rows, progress }
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    pub fn skip_rows(&self) -> usize {
+        self.skip_rows
+    }
+
+    pub fn report_progress(&mut self, rows_loaded: u64) {
+        if let Some(progress) = self.progress.as_mut() {
+            progress(rows_loaded);
+        }
+    }
+}
+
+impl Default for BulkLoadOptions {
+    fn default() -> Self {
+        BulkLoadOptions::new(10_000, 0, None)
+    }
+}
+
+pub enum BulkRow {
+    Record(DataRecord),
+    Text(Vec<String>),
+}
@@ -0,0 +1,154 @@
+use std::{fs::read_to_string, path::Path};
+
+use super::{
+    DataSourceInform, dyn_handler::BoxedQueryHandler, error::QueryHandleError,
+    qry_handler_from_dsi, secret::SecretProvider,
+};
+
+const DATASOURCE_TABLE: &str = "datasource";
+const EXTENDS_KEY: &str = "extends";
+//NOTE: keys that cannot be used together; an override setting one drops the others from the base.
+const EXCLUSIVE_KEYS: [&[&str]; 3] = [
+    &["password", "password_file"],
+    &["token", "token_file"],
+    &["trust_server_certificate", "ca_certificate"],
+];
+//NOTE: an override switching `auth` drops the credentials of the base's method.
+const AUTH_KEY: &str = "auth";
+const CREDENTIAL_KEYS: [&str; 5] = ["username", "password", "password_file", "token", "token_file"];
+
+//NOTE: a catalog file lists named datasources as `[datasource.<name>]` tables shaped like a
+//      single datasource file. `extends = "<name>"` starts from another datasource and overrides
+//      it key by key, nested tables included. Secrets are resolved when a datasource is looked up,
+//      so a missing variable only fails the datasources that use it.
+pub struct Catalog {
+    datasources: toml::Table,
+    provider: Option<Box<dyn SecretProvider + Send + Sync>>,
+}
+
+impl Catalog {
+    pub fn new(
+        datasources: toml::Table,
+        provider: Option<Box<dyn SecretProvider + Send + Sync>>,
+    ) -> Self {
+        Catalog { datasources, provider }
+    }
+
+    pub fn from_file<P>(catalog_file: P) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        Self::load(catalog_file, None)
+    }
+
+    pub fn from_file_with_provider<P>(
+        catalog_file: P,
+        provider: Box<dyn SecretProvider + Send + Sync>,
+    ) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        Self::load(catalog_file, Some(provider))
+    }
+
+    fn load<P>(
+        catalog_file: P,
+        provider: Option<Box<dyn SecretProvider + Send + Sync>>,
+    ) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let catalog_file = catalog_file.as_ref();
+        let mut contents: toml::Table = toml::from_str(&read_to_string(catalog_file)?)?;
+        let datasources = match contents.remove(DATASOURCE_TABLE) {
+            Some(toml::Value::Table(datasources)) => datasources,
+            _ => {
+                return Err(Box::new(QueryHandleError::InvalidParameter(format!(
+                    "{} has no [{}] table",
+                    catalog_file.display(),
+                    DATASOURCE_TABLE
+                ))));
+            }
+        };
+        Ok(Catalog::new(datasources, provider))
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.datasources.keys().map(|name| name.as_str()).collect()
+    }
+
+    pub fn inform(&self, name: &str) -> Result<DataSourceInform, Box<dyn std::error::Error>> {
+        let mut datasource = toml::Value::Table(self.resolve(name, &mut Vec::new())?);
+        let provider = self.provider.as_deref().map(|provider| provider as &dyn SecretProvider);
+        super::secret::interpolate_value(&mut datasource, provider)?;
+        Ok(datasource.try_into()?)
+    }
+
+    pub async fn handler(
+        &self,
+        name: &str,
+    ) -> Result<BoxedQueryHandler, Box<dyn std::error::Error>> {
+        let mut handler = qry_handler_from_dsi(self.inform(name)?)?;
+        handler.connect().await?;
+        Ok(handler)
+    }
+
+    fn resolve(
+        &self,
+        name: &str,
+        chain: &mut Vec<String>,
+    ) -> Result<toml::Table, Box<dyn std::error::Error>> {
+        if chain.iter().any(|visited| visited == name) {
+            chain.push(name.to_string());
+            return Err(Box::new(QueryHandleError::InvalidParameter(format!(
+                "Datasources extend each other: {}",
+                chain.join(" -> ")
+            ))));
+        }
+        chain.push(name.to_string());
+
+        let mut datasource = match self.datasources.get(name) {
+            Some(toml::Value::Table(datasource)) => datasource.clone(),
+            _ => return Err(Box::new(QueryHandleError::DataSourceNotFound(name.to_string()))),
+        };
+        match datasource.remove(EXTENDS_KEY) {
+            Some(toml::Value::String(base_name)) => {
+                let mut base = self.resolve(&base_name, chain)?;
+                merge_table(&mut base, datasource);
+                Ok(base)
+            }
+            Some(_) => Err(Box::new(QueryHandleError::InvalidParameter(format!(
+                "{}: {} must name a datasource",
+                name, EXTENDS_KEY
+            )))),
+            None => Ok(datasource),
+        }
+    }
+}
+
+fn merge_table(base: &mut toml::Table, overrides: toml::Table) {
+    if let Some(auth) = overrides.get(AUTH_KEY)
+        && base.get(AUTH_KEY) != Some(auth)
+    {
+        for key in CREDENTIAL_KEYS {
+            base.remove(key);
+        }
+    }
+    for keys in EXCLUSIVE_KEYS {
+        if keys.iter().any(|key| overrides.contains_key(*key)) {
+            for key in keys {
+                base.remove(*key);
+            }
+        }
+    }
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(table)) => {
+                merge_table(base_table, table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
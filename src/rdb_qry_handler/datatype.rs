@@ -1,7 +1,8 @@
-use chrono::DateTime;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use std::fmt;
 
 //NOTE: U128 can not be supported currently
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DataType {
     I8(i8),
     I16(i16),
@@ -14,12 +15,58 @@ pub enum DataType {
     U64(u64),
     F32(f32),
     F64(f64),
+    Decimal(Decimal),
     Bool(bool),
     String(String),
     Bytes(Vec<u8>),
     DateTime(DateTime<chrono::FixedOffset>),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataTypeKind {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    Decimal,
+    Bool,
+    String,
+    Bytes,
+    DateTime,
+}
+
+//NOTE: an exact decimal number, `mantissa / 10^scale`, for decimal, numeric and money columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u8,
+}
+
+//NOTE: formats tried on text without a given format, shared with schema inference. Day and month
+//      order is ambiguous for `01/02/2024`, which is read month first.
+pub const DATE_TIME_OFFSET_FORMATS: [&str; 3] =
+    ["%Y-%m-%dT%H:%M:%S%.f%:z", "%Y-%m-%d %H:%M:%S%.f%:z", "%Y-%m-%d %H:%M:%S%.f %:z"];
+pub const DATE_TIME_FORMATS: [&str; 5] = [
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%m/%d/%Y %H:%M:%S",
+];
+pub const DATE_FORMATS: [&str; 6] =
+    ["%Y-%m-%d", "%Y/%m/%d", "%Y%m%d", "%d.%m.%Y", "%m/%d/%Y", "%d/%m/%Y"];
+pub const TIME_FORMATS: [&str; 2] = ["%H:%M:%S%.f", "%H:%M"];
+
+//NOTE: SQL Server numbers have at most 38 digits.
+const MAX_DECIMAL_SCALE: u8 = 38;
+
 impl DataType {
     pub fn value<T>(&self) -> Option<T>
     where
@@ -27,6 +74,223 @@ impl DataType {
     {
         T::from_data_type(self)
     }
+
+    pub fn kind(&self) -> DataTypeKind {
+        match self {
+            DataType::I8(_) => DataTypeKind::I8,
+            DataType::I16(_) => DataTypeKind::I16,
+            DataType::I32(_) => DataTypeKind::I32,
+            DataType::I64(_) => DataTypeKind::I64,
+            DataType::I128(_) => DataTypeKind::I128,
+            DataType::U8(_) => DataTypeKind::U8,
+            DataType::U16(_) => DataTypeKind::U16,
+            DataType::U32(_) => DataTypeKind::U32,
+            DataType::U64(_) => DataTypeKind::U64,
+            DataType::F32(_) => DataTypeKind::F32,
+            DataType::F64(_) => DataTypeKind::F64,
+            DataType::Decimal(_) => DataTypeKind::Decimal,
+            DataType::Bool(_) => DataTypeKind::Bool,
+            DataType::String(_) => DataTypeKind::String,
+            DataType::Bytes(_) => DataTypeKind::Bytes,
+            DataType::DateTime(_) => DataTypeKind::DateTime,
+        }
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataType::I8(val) => write!(f, "{}", val),
+            DataType::I16(val) => write!(f, "{}", val),
+            DataType::I32(val) => write!(f, "{}", val),
+            DataType::I64(val) => write!(f, "{}", val),
+            DataType::I128(val) => write!(f, "{}", val),
+            DataType::U8(val) => write!(f, "{}", val),
+            DataType::U16(val) => write!(f, "{}", val),
+            DataType::U32(val) => write!(f, "{}", val),
+            DataType::U64(val) => write!(f, "{}", val),
+            DataType::F32(val) => write!(f, "{}", val),
+            DataType::F64(val) => write!(f, "{}", val),
+            DataType::Decimal(val) => write!(f, "{}", val),
+            DataType::Bool(val) => write!(f, "{}", val),
+            DataType::String(val) => write!(f, "{}", val),
+            DataType::Bytes(val) => {
+                write!(f, "0x")?;
+                for byte in val {
+                    write!(f, "{:02X}", byte)?;
+                }
+                Ok(())
+            }
+            DataType::DateTime(val) => write!(f, "{}", val.to_rfc3339()),
+        }
+    }
+}
+
+impl DataTypeKind {
+    pub fn from_sql_type(type_name: &str) -> DataTypeKind {
+        match type_name.to_lowercase().as_str() {
+            "tinyint" => DataTypeKind::U8,
+            "smallint" => DataTypeKind::I16,
+            "int" | "integer" => DataTypeKind::I32,
+            "bigint" => DataTypeKind::I64,
+            "decimal" | "numeric" | "money" | "smallmoney" => DataTypeKind::Decimal,
+            "float" => DataTypeKind::F64,
+            "real" => DataTypeKind::F32,
+            "bit" => DataTypeKind::Bool,
+            "binary" | "varbinary" | "image" => DataTypeKind::Bytes,
+            "date" | "time" | "datetime" | "datetime2" | "smalldatetime" | "datetimeoffset" => {
+                DataTypeKind::DateTime
+            }
+            _ => DataTypeKind::String,
+        }
+    }
+
+    //NOTE: text without an offset is read as UTC, and bytes accept a `0x` prefixed hex string.
+    pub fn parse(&self, text: &str) -> Option<DataType> {
+        let trimmed = text.trim();
+        match self {
+            DataTypeKind::I8 => trimmed.parse().ok().map(DataType::I8),
+            DataTypeKind::I16 => trimmed.parse().ok().map(DataType::I16),
+            DataTypeKind::I32 => trimmed.parse().ok().map(DataType::I32),
+            DataTypeKind::I64 => trimmed.parse().ok().map(DataType::I64),
+            DataTypeKind::I128 => trimmed.parse().ok().map(DataType::I128),
+            DataTypeKind::U8 => trimmed.parse().ok().map(DataType::U8),
+            DataTypeKind::U16 => trimmed.parse().ok().map(DataType::U16),
+            DataTypeKind::U32 => trimmed.parse().ok().map(DataType::U32),
+            DataTypeKind::U64 => trimmed.parse().ok().map(DataType::U64),
+            DataTypeKind::F32 => trimmed.parse().ok().map(DataType::F32),
+            DataTypeKind::F64 => trimmed.parse().ok().map(DataType::F64),
+            DataTypeKind::Decimal => Decimal::parse(trimmed).map(DataType::Decimal),
+            DataTypeKind::Bool => match trimmed.to_lowercase().as_str() {
+                "true" | "t" | "yes" | "y" | "1" => Some(DataType::Bool(true)),
+                "false" | "f" | "no" | "n" | "0" => Some(DataType::Bool(false)),
+                _ => None,
+            },
+            DataTypeKind::String => Some(DataType::String(text.to_string())),
+            DataTypeKind::Bytes => match trimmed.strip_prefix("0x") {
+                Some(hex) => parse_hex(hex).map(DataType::Bytes),
+                None => Some(DataType::Bytes(text.as_bytes().to_vec())),
+            },
+            DataTypeKind::DateTime => parse_date_time(trimmed).map(DataType::DateTime),
+        }
+    }
+}
+
+impl Decimal {
+    pub fn new(mantissa: i128, scale: u8) -> Self {
+        Decimal { mantissa, scale }
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    //NOTE: plain or exponent notation such as `-12.50` or `1.5E-3`, keeping every digit given.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (number, exponent) = match text.find(['e', 'E']) {
+            Some(at) => (&text[..at], text[at + 1..].parse::<i32>().ok()?),
+            None => (text, 0),
+        };
+        let (negative, digits) = match number.as_bytes().first()? {
+            b'-' => (true, &number[1..]),
+            b'+' => (false, &number[1..]),
+            _ => (false, number),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return None;
+        }
+
+        let mut mantissa: i128 = 0;
+        for digit in integer.bytes().chain(fraction.bytes()) {
+            if !digit.is_ascii_digit() {
+                return None;
+            }
+            mantissa = mantissa.checked_mul(10)?.checked_add((digit - b'0') as i128)?;
+        }
+        let mantissa = if negative { -mantissa } else { mantissa };
+
+        let scale = fraction.len() as i32 - exponent;
+        if scale < 0 {
+            let mantissa = mantissa.checked_mul(10i128.checked_pow(scale.unsigned_abs())?)?;
+            return Some(Decimal::new(mantissa, 0));
+        }
+        let scale = u8::try_from(scale).ok().filter(|scale| *scale <= MAX_DECIMAL_SCALE)?;
+        Some(Decimal::new(mantissa, scale))
+    }
+
+    //NOTE: a smaller scale rounds half away from zero.
+    pub fn rescale(&self, scale: u8) -> Option<Self> {
+        if scale >= self.scale {
+            let factor = 10i128.checked_pow((scale - self.scale) as u32)?;
+            return Some(Decimal::new(self.mantissa.checked_mul(factor)?, scale));
+        }
+        let factor = 10i128.checked_pow((self.scale - scale) as u32)?;
+        let quotient = self.mantissa / factor;
+        let remainder = self.mantissa % factor;
+        let rounded = if remainder.unsigned_abs() * 2 >= factor.unsigned_abs() {
+            quotient + self.mantissa.signum()
+        } else {
+            quotient
+        };
+        Some(Decimal::new(rounded, scale))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, integer, fraction)
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn parse_date_time(text: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(text) {
+        return Some(date_time);
+    }
+    [&DATE_TIME_OFFSET_FORMATS[..], &DATE_TIME_FORMATS[..], &DATE_FORMATS[..], &TIME_FORMATS[..]]
+        .concat()
+        .into_iter()
+        .find_map(|format| parse_date_time_with_format(text, format))
+}
+
+//NOTE: the format may hold an offset, a date and time, a date or a time of day. Text without an
+//      offset is read as UTC, and a time of day falls on 1900-01-01, as SQL Server does when it
+//      widens time.
+pub fn parse_date_time_with_format(text: &str, format: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(date_time) = DateTime::parse_from_str(text, format) {
+        return Some(date_time);
+    }
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(text, format) {
+        return Some(date_time.and_utc().fixed_offset());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, format) {
+        return Some(date.and_time(NaiveTime::MIN).and_utc().fixed_offset());
+    }
+    let time = NaiveTime::parse_from_str(text, format).ok()?;
+    Some(NaiveDate::from_ymd_opt(1900, 1, 1)?.and_time(time).and_utc().fixed_offset())
 }
 
 pub trait FromDataType {
@@ -101,6 +365,12 @@ impl FromDataType for f64 {
     }
 }
 
+impl FromDataType for Decimal {
+    fn from_data_type(data_type: &DataType) -> Option<Self> {
+        if let DataType::Decimal(value) = data_type { Some(*value) } else { None }
+    }
+}
+
 impl FromDataType for bool {
     fn from_data_type(data_type: &DataType) -> Option<Self> {
         if let DataType::Bool(value) = data_type { Some(*value) } else { None }
@@ -121,6 +391,6 @@ impl FromDataType for Vec<u8> {
 
 impl FromDataType for DateTime<chrono::FixedOffset> {
     fn from_data_type(data_type: &DataType) -> Option<Self> {
-        if let DataType::DateTime(value) = data_type { Some(value.clone()) } else { None }
+        if let DataType::DateTime(value) = data_type { Some(*value) } else { None }
     }
 }
@@ -0,0 +1,275 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use super::{
+    DataRows, DataSourceInform, FetchMore, QueryHandler, QueryResult, RecordSink,
+    datatype::{DataType, DataTypeKind},
+    qry_handler_from_dsi,
+    schema::{ColumnInfo, ForeignKey, IndexInfo, PrimaryKey, SchemaInspector, TableInfo},
+    statement::PreparedStatement,
+};
+
+pub type BoxFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<T, Box<dyn std::error::Error>>> + Send + 'a>>;
+pub type BoxedQueryResult = Box<dyn QueryResult + Send>;
+pub type BoxedQueryHandler = Box<dyn DynQueryHandler>;
+
+//NOTE: object-safe mirror of `QueryHandler` + `SchemaInspector`, implemented for every driver, so
+//      a driver picked at runtime can be held as `BoxedQueryHandler`. The box implements both
+//      traits again and works wherever a generic handler is expected.
+pub trait DynQueryHandler: Send {
+    fn connect(&mut self) -> BoxFuture<'_, ()>;
+
+    fn query<'a>(
+        &'a mut self,
+        query: &'a str,
+        bind_variables: Option<Arc<[DataType]>>,
+        fetch_more: FetchMore,
+    ) -> BoxFuture<'a, DataRows>;
+
+    fn query_for_each<'a>(
+        &'a mut self,
+        query: &'a str,
+        bind_variables: Option<Arc<[DataType]>>,
+        sink: RecordSink,
+    ) -> BoxFuture<'a, u64>;
+
+    fn mutate<'a>(
+        &'a mut self,
+        query: &'a str,
+        bind_variables: Option<Arc<[DataType]>>,
+    ) -> BoxFuture<'a, BoxedQueryResult>;
+
+    fn prepare<'a>(
+        &'a mut self,
+        query: &'a str,
+        param_types: Arc<[DataTypeKind]>,
+    ) -> BoxFuture<'a, PreparedStatement>;
+
+    fn execute_prepared<'a>(
+        &'a mut self,
+        statement: &'a PreparedStatement,
+        bind_variables: Option<Arc<[DataType]>>,
+    ) -> BoxFuture<'a, BoxedQueryResult>;
+
+    fn unprepare(&mut self, statement: PreparedStatement) -> BoxFuture<'_, ()>;
+
+    fn close(self: Box<Self>) -> BoxFuture<'static, ()>;
+
+    fn schemas(&mut self) -> BoxFuture<'_, Vec<String>>;
+
+    fn tables<'a>(&'a mut self, schema: Option<&'a str>) -> BoxFuture<'a, Vec<TableInfo>>;
+
+    fn columns<'a>(&'a mut self, table: &'a str) -> BoxFuture<'a, Vec<ColumnInfo>>;
+
+    fn primary_key<'a>(&'a mut self, table: &'a str) -> BoxFuture<'a, Option<PrimaryKey>>;
+
+    fn foreign_keys<'a>(&'a mut self, table: &'a str) -> BoxFuture<'a, Vec<ForeignKey>>;
+
+    fn indexes<'a>(&'a mut self, table: &'a str) -> BoxFuture<'a, Vec<IndexInfo>>;
+}
+
+impl<H> DynQueryHandler for H
+where
+    H: QueryHandler + SchemaInspector + Send + 'static,
+{
+    fn connect(&mut self) -> BoxFuture<'_, ()> {
+        Box::pin(QueryHandler::connect(self))
+    }
+
+    fn query<'a>(
+        &'a mut self,
+        query: &'a str,
+        bind_variables: Option<Arc<[DataType]>>,
+        fetch_more: FetchMore,
+    ) -> BoxFuture<'a, DataRows> {
+        Box::pin(QueryHandler::query(self, query, bind_variables, fetch_more))
+    }
+
+    fn query_for_each<'a>(
+        &'a mut self,
+        query: &'a str,
+        bind_variables: Option<Arc<[DataType]>>,
+        sink: RecordSink,
+    ) -> BoxFuture<'a, u64> {
+        Box::pin(QueryHandler::query_for_each(self, query, bind_variables, sink))
+    }
+
+    fn mutate<'a>(
+        &'a mut self,
+        query: &'a str,
+        bind_variables: Option<Arc<[DataType]>>,
+    ) -> BoxFuture<'a, BoxedQueryResult> {
+        Box::pin(async move {
+            let result = QueryHandler::mutate(self, query, bind_variables).await?;
+            Ok(Box::new(AffectedRows(result.affected_rows())) as BoxedQueryResult)
+        })
+    }
+
+    fn prepare<'a>(
+        &'a mut self,
+        query: &'a str,
+        param_types: Arc<[DataTypeKind]>,
+    ) -> BoxFuture<'a, PreparedStatement> {
+        Box::pin(QueryHandler::prepare(self, query, param_types))
+    }
+
+    fn execute_prepared<'a>(
+        &'a mut self,
+        statement: &'a PreparedStatement,
+        bind_variables: Option<Arc<[DataType]>>,
+    ) -> BoxFuture<'a, BoxedQueryResult> {
+        Box::pin(async move {
+            let result = QueryHandler::execute_prepared(self, statement, bind_variables).await?;
+            Ok(Box::new(AffectedRows(result.affected_rows())) as BoxedQueryResult)
+        })
+    }
+
+    fn unprepare(&mut self, statement: PreparedStatement) -> BoxFuture<'_, ()> {
+        Box::pin(QueryHandler::unprepare(self, statement))
+    }
+
+    fn close(self: Box<Self>) -> BoxFuture<'static, ()> {
+        Box::pin(QueryHandler::close(*self))
+    }
+
+    fn schemas(&mut self) -> BoxFuture<'_, Vec<String>> {
+        Box::pin(SchemaInspector::schemas(self))
+    }
+
+    fn tables<'a>(&'a mut self, schema: Option<&'a str>) -> BoxFuture<'a, Vec<TableInfo>> {
+        Box::pin(SchemaInspector::tables(self, schema))
+    }
+
+    fn columns<'a>(&'a mut self, table: &'a str) -> BoxFuture<'a, Vec<ColumnInfo>> {
+        Box::pin(SchemaInspector::columns(self, table))
+    }
+
+    fn primary_key<'a>(&'a mut self, table: &'a str) -> BoxFuture<'a, Option<PrimaryKey>> {
+        Box::pin(SchemaInspector::primary_key(self, table))
+    }
+
+    fn foreign_keys<'a>(&'a mut self, table: &'a str) -> BoxFuture<'a, Vec<ForeignKey>> {
+        Box::pin(SchemaInspector::foreign_keys(self, table))
+    }
+
+    fn indexes<'a>(&'a mut self, table: &'a str) -> BoxFuture<'a, Vec<IndexInfo>> {
+        Box::pin(SchemaInspector::indexes(self, table))
+    }
+}
+
+struct AffectedRows(u64);
+
+impl QueryResult for AffectedRows {
+    fn affected_rows(&self) -> u64 {
+        self.0
+    }
+}
+
+impl QueryResult for BoxedQueryResult {
+    fn affected_rows(&self) -> u64 {
+        self.as_ref().affected_rows()
+    }
+}
+
+impl QueryHandler for BoxedQueryHandler {
+    type ConnectionConfig = DataSourceInform;
+
+    fn from_config(conn_config: DataSourceInform) -> Result<Self, Box<dyn std::error::Error>> {
+        qry_handler_from_dsi(conn_config)
+    }
+
+    async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.as_mut().connect().await
+    }
+
+    async fn query(
+        &mut self,
+        query: &str,
+        bind_variables: Option<Arc<[DataType]>>,
+        fetch_more: FetchMore,
+    ) -> Result<DataRows, Box<dyn std::error::Error>> {
+        self.as_mut().query(query, bind_variables, fetch_more).await
+    }
+
+    async fn query_for_each(
+        &mut self,
+        query: &str,
+        bind_variables: Option<Arc<[DataType]>>,
+        sink: RecordSink,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        self.as_mut().query_for_each(query, bind_variables, sink).await
+    }
+
+    async fn mutate(
+        &mut self,
+        query: &str,
+        bind_variables: Option<Arc<[DataType]>>,
+    ) -> Result<impl QueryResult, Box<dyn std::error::Error>> {
+        self.as_mut().mutate(query, bind_variables).await
+    }
+
+    async fn prepare(
+        &mut self,
+        query: &str,
+        param_types: Arc<[DataTypeKind]>,
+    ) -> Result<PreparedStatement, Box<dyn std::error::Error>> {
+        self.as_mut().prepare(query, param_types).await
+    }
+
+    async fn execute_prepared(
+        &mut self,
+        statement: &PreparedStatement,
+        bind_variables: Option<Arc<[DataType]>>,
+    ) -> Result<impl QueryResult, Box<dyn std::error::Error>> {
+        self.as_mut().execute_prepared(statement, bind_variables).await
+    }
+
+    async fn unprepare(
+        &mut self,
+        statement: PreparedStatement,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.as_mut().unprepare(statement).await
+    }
+
+    async fn close(self) -> Result<(), Box<dyn std::error::Error>> {
+        DynQueryHandler::close(self).await
+    }
+}
+
+impl SchemaInspector for BoxedQueryHandler {
+    async fn schemas(&mut self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        self.as_mut().schemas().await
+    }
+
+    async fn tables(
+        &mut self,
+        schema: Option<&str>,
+    ) -> Result<Vec<TableInfo>, Box<dyn std::error::Error>> {
+        self.as_mut().tables(schema).await
+    }
+
+    async fn columns(
+        &mut self,
+        table: &str,
+    ) -> Result<Vec<ColumnInfo>, Box<dyn std::error::Error>> {
+        self.as_mut().columns(table).await
+    }
+
+    async fn primary_key(
+        &mut self,
+        table: &str,
+    ) -> Result<Option<PrimaryKey>, Box<dyn std::error::Error>> {
+        self.as_mut().primary_key(table).await
+    }
+
+    async fn foreign_keys(
+        &mut self,
+        table: &str,
+    ) -> Result<Vec<ForeignKey>, Box<dyn std::error::Error>> {
+        self.as_mut().foreign_keys(table).await
+    }
+
+    async fn indexes(&mut self, table: &str) -> Result<Vec<IndexInfo>, Box<dyn std::error::Error>> {
+        self.as_mut().indexes(table).await
+    }
+}
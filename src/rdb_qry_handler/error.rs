@@ -1,10 +1,14 @@
 use std::error::Error;
 use std::fmt;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum QueryHandleError {
     NotInitialized(String),
     InvalidCall(String),
+    InvalidParameter(String),
+    SecretNotFound(String),
+    DataSourceNotFound(String),
+    InvalidSecret(String),
     Unknown(String),
 }
 
@@ -13,6 +17,12 @@ impl fmt::Display for QueryHandleError {
         match *self {
             QueryHandleError::NotInitialized(ref msg) => write!(f, "Not Initialized: {}", msg),
             QueryHandleError::InvalidCall(ref msg) => write!(f, "Invalid Call: {}", msg),
+            QueryHandleError::InvalidParameter(ref msg) => write!(f, "Invalid Parameter: {}", msg),
+            QueryHandleError::SecretNotFound(ref msg) => write!(f, "Secret Not Found: {}", msg),
+            QueryHandleError::DataSourceNotFound(ref msg) => {
+                write!(f, "DataSource Not Found: {}", msg)
+            }
+            QueryHandleError::InvalidSecret(ref msg) => write!(f, "Invalid Secret: {}", msg),
             QueryHandleError::Unknown(ref msg) => write!(f, "Unknown: {}", msg),
         }
     }
@@ -0,0 +1,301 @@
+use std::sync::{Arc, Mutex};
+
+use regex::Regex;
+
+use super::{
+    DataRows, FetchMore, QueryHandler, QueryResult,
+    datatype::{DataType, DataTypeKind},
+    error::QueryHandleError,
+    schema::{ColumnInfo, ForeignKey, IndexInfo, PrimaryKey, SchemaInspector, TableInfo},
+    statement::PreparedStatement,
+};
+
+pub enum SqlPattern {
+    Exact(String),
+    Regex(Regex),
+    Predicate(Box<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+#[derive(Debug, Clone)]
+pub enum MemoryResponse {
+    Rows(DataRows),
+    Affected(u64),
+    Error(QueryHandleError),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutedStatement {
+    sql: String,
+    bind_variables: Vec<DataType>,
+}
+
+//NOTE: answers each statement with the response of the first pattern it matches and records it.
+//      Clones share their responses and records, so keep one to assert on after the handler is
+//      moved into a pipeline or closed.
+#[derive(Clone, Default)]
+pub struct InMemoryHandler {
+    state: Arc<Mutex<InMemoryState>>,
+}
+
+#[derive(Default)]
+struct InMemoryState {
+    responses: Vec<(SqlPattern, MemoryResponse)>,
+    executed: Vec<ExecutedStatement>,
+    tables: Vec<(TableInfo, Vec<ColumnInfo>)>,
+    last_handle: i32,
+}
+
+struct MemoryAffectedRows {
+    affected_rows: u64,
+}
+
+impl QueryResult for MemoryAffectedRows {
+    fn affected_rows(&self) -> u64 {
+        self.affected_rows
+    }
+}
+
+impl SqlPattern {
+    pub fn exact(sql: &str) -> Self {
+        SqlPattern::Exact(sql.to_string())
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self, QueryHandleError> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| QueryHandleError::InvalidParameter(format!("{}: {}", pattern, e)))?;
+        Ok(SqlPattern::Regex(regex))
+    }
+
+    pub fn predicate<F>(predicate: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        SqlPattern::Predicate(Box::new(predicate))
+    }
+
+    pub fn matches(&self, sql: &str) -> bool {
+        match self {
+            SqlPattern::Exact(exact) => exact == sql,
+            SqlPattern::Regex(regex) => regex.is_match(sql),
+            SqlPattern::Predicate(predicate) => predicate(sql),
+        }
+    }
+}
+
+impl ExecutedStatement {
+    pub fn new(sql: &str, bind_variables: Vec<DataType>) -> Self {
+        ExecutedStatement { sql: sql.to_string(), bind_variables }
+    }
+
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    pub fn bind_variables(&self) -> &[DataType] {
+        &self.bind_variables
+    }
+}
+
+impl InMemoryHandler {
+    pub fn new() -> Self {
+        InMemoryHandler::default()
+    }
+
+    pub fn respond(&self, pattern: SqlPattern, response: MemoryResponse) {
+        self.state.lock().unwrap().responses.push((pattern, response));
+    }
+
+    pub fn executed(&self) -> Vec<ExecutedStatement> {
+        self.state.lock().unwrap().executed.clone()
+    }
+
+    pub fn add_table(&self, table: TableInfo, columns: Vec<ColumnInfo>) {
+        self.state.lock().unwrap().tables.push((table, columns));
+    }
+
+    fn execute(
+        &self,
+        query: &str,
+        bind_variables: Option<Arc<[DataType]>>,
+    ) -> Result<MemoryResponse, QueryHandleError> {
+        let mut state = self.state.lock().unwrap();
+        let bind_variables = bind_variables.map(|binds| binds.to_vec()).unwrap_or_default();
+        state.executed.push(ExecutedStatement::new(query, bind_variables));
+
+        let response = state
+            .responses
+            .iter()
+            .find(|(pattern, _)| pattern.matches(query))
+            .map(|(_, response)| response.clone())
+            .ok_or(QueryHandleError::InvalidCall(format!("No response for query: {}", query)))?;
+        match response {
+            MemoryResponse::Error(e) => Err(e),
+            response => Ok(response),
+        }
+    }
+
+    fn affected_rows(response: MemoryResponse) -> MemoryAffectedRows {
+        let affected_rows = match response {
+            MemoryResponse::Rows(rows) => rows.records().len() as u64,
+            MemoryResponse::Affected(affected_rows) => affected_rows,
+            MemoryResponse::Error(_) => 0,
+        };
+        MemoryAffectedRows { affected_rows }
+    }
+}
+
+impl QueryHandler for InMemoryHandler {
+    type ConnectionConfig = ();
+
+    fn from_config(_conn_config: ()) -> Result<Self, Box<dyn std::error::Error>> {
+        Result::Ok(InMemoryHandler::new())
+    }
+
+    async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        Result::Ok(())
+    }
+
+    async fn query(
+        &mut self,
+        query: &str,
+        bind_variables: Option<Arc<[DataType]>>,
+        fetch_more: FetchMore,
+    ) -> Result<DataRows, Box<dyn std::error::Error>> {
+        let rows = match self.execute(query, bind_variables)? {
+            MemoryResponse::Rows(rows) => rows,
+            _ => return Result::Ok(DataRows::new(None, Vec::new())),
+        };
+
+        //NOTE: mirrors the drivers, which offer the column names first and then each record.
+        let column_meta = rows.column_meta().map(|meta| meta.to_vec());
+        if column_meta.is_some() && !fetch_more(column_meta.as_deref(), None) {
+            return Result::Ok(DataRows::new(column_meta, Vec::new()));
+        }
+        let mut records = Vec::new();
+        for record in rows.into_records() {
+            let need_to_continue = fetch_more(None, Some(&record));
+            records.push(record);
+            if !need_to_continue {
+                break;
+            }
+        }
+
+        Result::Ok(DataRows::new(column_meta, records))
+    }
+
+    async fn mutate(
+        &mut self,
+        query: &str,
+        bind_variables: Option<Arc<[DataType]>>,
+    ) -> Result<impl QueryResult, Box<dyn std::error::Error>> {
+        let response = self.execute(query, bind_variables)?;
+        Result::Ok(InMemoryHandler::affected_rows(response))
+    }
+
+    async fn prepare(
+        &mut self,
+        query: &str,
+        param_types: Arc<[DataTypeKind]>,
+    ) -> Result<PreparedStatement, Box<dyn std::error::Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.last_handle += 1;
+        Result::Ok(PreparedStatement::new(state.last_handle, query, param_types, None))
+    }
+
+    async fn execute_prepared(
+        &mut self,
+        statement: &PreparedStatement,
+        bind_variables: Option<Arc<[DataType]>>,
+    ) -> Result<impl QueryResult, Box<dyn std::error::Error>> {
+        statement.check_binds(bind_variables.as_deref())?;
+        let response = self.execute(statement.query(), bind_variables)?;
+        Result::Ok(InMemoryHandler::affected_rows(response))
+    }
+
+    async fn unprepare(
+        &mut self,
+        statement: PreparedStatement,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        statement.release();
+        Result::Ok(())
+    }
+
+    async fn close(self) -> Result<(), Box<dyn std::error::Error>> {
+        Result::Ok(())
+    }
+}
+
+//NOTE: only the tables given to `add_table` exist; an unqualified name matches them in any schema.
+impl SchemaInspector for InMemoryHandler {
+    async fn schemas(&mut self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let state = self.state.lock().unwrap();
+        let mut schemas: Vec<String> =
+            state.tables.iter().map(|(table, _)| table.schema.clone()).collect();
+        schemas.sort();
+        schemas.dedup();
+        Result::Ok(schemas)
+    }
+
+    async fn tables(
+        &mut self,
+        schema: Option<&str>,
+    ) -> Result<Vec<TableInfo>, Box<dyn std::error::Error>> {
+        let state = self.state.lock().unwrap();
+        let tables = state
+            .tables
+            .iter()
+            .filter(|(table, _)| {
+                schema.is_none_or(|schema| table.schema.eq_ignore_ascii_case(schema))
+            })
+            .map(|(table, _)| table.clone())
+            .collect();
+        Result::Ok(tables)
+    }
+
+    async fn columns(
+        &mut self,
+        table: &str,
+    ) -> Result<Vec<ColumnInfo>, Box<dyn std::error::Error>> {
+        let (schema, name) = split_table_name(table);
+        let state = self.state.lock().unwrap();
+        let columns = state
+            .tables
+            .iter()
+            .find(|(info, _)| {
+                info.name.eq_ignore_ascii_case(&name)
+                    && schema.as_ref().is_none_or(|schema| info.schema.eq_ignore_ascii_case(schema))
+            })
+            .map(|(_, columns)| columns.clone())
+            .unwrap_or_default();
+        Result::Ok(columns)
+    }
+
+    async fn primary_key(
+        &mut self,
+        _table: &str,
+    ) -> Result<Option<PrimaryKey>, Box<dyn std::error::Error>> {
+        Result::Ok(None)
+    }
+
+    async fn foreign_keys(
+        &mut self,
+        _table: &str,
+    ) -> Result<Vec<ForeignKey>, Box<dyn std::error::Error>> {
+        Result::Ok(Vec::new())
+    }
+
+    async fn indexes(
+        &mut self,
+        _table: &str,
+    ) -> Result<Vec<IndexInfo>, Box<dyn std::error::Error>> {
+        Result::Ok(Vec::new())
+    }
+}
+
+fn split_table_name(table: &str) -> (Option<String>, String) {
+    let unquote = |part: &str| part.trim_start_matches('[').trim_end_matches(']').to_string();
+    match table.rsplit_once('.') {
+        Some((schema, name)) => (Some(unquote(schema)), unquote(name)),
+        None => (None, unquote(table)),
+    }
+}
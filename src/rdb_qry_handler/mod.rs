@@ -1,12 +1,24 @@
-use datatype::{DataType, FromDataType};
+use datatype::{DataType, DataTypeKind, FromDataType};
 use serde::Deserialize;
+use statement::PreparedStatement;
 use std::{fs::read_to_string, future::Future, path::Path, sync::Arc};
 
+pub mod bulk;
+pub mod catalog;
 pub mod datatype;
+pub mod dyn_handler;
 pub mod error;
+#[cfg(feature = "testing")]
+pub mod memory;
+pub mod schema;
+pub mod secret;
 pub mod sqlserver;
+pub mod statement;
 
-#[derive(Debug)]
+pub type FetchMore = Box<dyn Fn(Option<&[String]>, Option<&DataRecord>) -> bool + Send>;
+pub type RecordSink = Box<dyn FnMut(Option<&[String]>, Option<DataRecord>) -> bool + Send>;
+
+#[derive(Debug, Clone)]
 pub struct DataRecord {
     cells: Vec<Option<DataType>>,
 }
@@ -16,6 +28,10 @@ pub trait IntoDataRecord {
 }
 
 impl DataRecord {
+    pub fn new(cells: Vec<Option<DataType>>) -> Self {
+        DataRecord { cells }
+    }
+
     pub fn cells(&self) -> &[Option<DataType>] {
         &self.cells
     }
@@ -32,7 +48,7 @@ impl DataRecord {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DataRows {
     column_meta: Option<Vec<String>>,
     records: Vec<DataRecord>,
@@ -60,6 +76,10 @@ impl DataRows {
     pub fn records(&self) -> &[DataRecord] {
         &self.records
     }
+
+    pub fn into_records(self) -> Vec<DataRecord> {
+        self.records
+    }
 }
 
 pub trait QueryResult {
@@ -79,22 +99,82 @@ pub trait QueryHandler {
         &mut self,
         query: &str,
         bind_variables: Option<Arc<[DataType]>>,
-        fetch_more: Box<dyn Fn(Option<&[String]>, Option<&DataRecord>) -> bool + Send>,
+        fetch_more: FetchMore,
     ) -> impl Future<Output = Result<DataRows, Box<dyn std::error::Error>>> + Send;
 
+    //NOTE: hands each record to `sink` instead of collecting it. Drivers should override the
+    //      default, which buffers the whole result through `query`.
+    fn query_for_each(
+        &mut self,
+        query: &str,
+        bind_variables: Option<Arc<[DataType]>>,
+        mut sink: RecordSink,
+    ) -> impl Future<Output = Result<u64, Box<dyn std::error::Error>>> + Send
+    where
+        Self: Send,
+    {
+        async move {
+            let rows = self.query(query, bind_variables, Self::default_fetch_more()).await?;
+            let column_meta = rows.column_meta().map(|meta| meta.to_vec());
+            if column_meta.is_some() && !sink(column_meta.as_deref(), None) {
+                return Ok(0);
+            }
+
+            let mut fetched = 0;
+            for record in rows.into_records() {
+                fetched += 1;
+                if !sink(None, Some(record)) {
+                    break;
+                }
+            }
+            Ok(fetched)
+        }
+    }
+
     fn mutate(
         &mut self,
         query: &str,
         bind_variables: Option<Arc<[DataType]>>,
     ) -> impl Future<Output = Result<impl QueryResult, Box<dyn std::error::Error>>> + Send;
 
+    //NOTE: drivers without prepared statements keep the defaults, which fail with `InvalidCall`.
+    fn prepare(
+        &mut self,
+        _query: &str,
+        _param_types: Arc<[DataTypeKind]>,
+    ) -> impl Future<Output = Result<PreparedStatement, Box<dyn std::error::Error>>> + Send {
+        async { Err(prepare_unsupported()) }
+    }
+
+    //NOTE: only the affected row count comes back; a prepared SELECT cannot return its rows.
+    fn execute_prepared(
+        &mut self,
+        _statement: &PreparedStatement,
+        _bind_variables: Option<Arc<[DataType]>>,
+    ) -> impl Future<Output = Result<impl QueryResult, Box<dyn std::error::Error>>> + Send {
+        async { Err::<dyn_handler::BoxedQueryResult, _>(prepare_unsupported()) }
+    }
+
+    fn unprepare(
+        &mut self,
+        _statement: PreparedStatement,
+    ) -> impl Future<Output = Result<(), Box<dyn std::error::Error>>> + Send {
+        async { Err(prepare_unsupported()) }
+    }
+
     fn close(self) -> impl Future<Output = Result<(), Box<dyn std::error::Error>>> + Send;
 
-    fn default_fetch_more() -> Box<dyn Fn(Option<&[String]>, Option<&DataRecord>) -> bool + Send> {
+    fn default_fetch_more() -> FetchMore {
         Box::new(|_, _| true)
     }
 }
 
+fn prepare_unsupported() -> Box<dyn std::error::Error> {
+    Box::new(error::QueryHandleError::InvalidCall(
+        "Prepared statements are not supported by this handler".to_string(),
+    ))
+}
+
 #[derive(Deserialize, Debug)]
 pub struct DataSourceInform {
     driver: String,
@@ -105,45 +185,59 @@ impl DataSourceInform {
     pub fn new(driver: String, sqlserver: Option<sqlserver::SqlServerConnectionConfig>) -> Self {
         DataSourceInform { driver, sqlserver }
     }
+
+    pub fn from_file<P>(config_file: P) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        Self::load(config_file, None)
+    }
+
+    pub fn from_file_with_provider<P>(
+        config_file: P,
+        provider: &dyn secret::SecretProvider,
+    ) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        Self::load(config_file, Some(provider))
+    }
+
+    fn load<P>(
+        config_file: P,
+        provider: Option<&dyn secret::SecretProvider>,
+    ) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let mut contents: toml::Value = toml::from_str(&read_to_string(config_file)?)?;
+        secret::interpolate_value(&mut contents, provider)?;
+        Ok(contents.try_into()?)
+    }
 }
 
-//TODO: deprecated
+#[deprecated(note = "use catalog::Catalog::handler or qry_handler_from_dsi")]
 #[allow(dead_code)]
 pub fn qry_handler<P>(config_file: P) -> Option<impl QueryHandler>
 where
     P: AsRef<Path>,
 {
-    let contents = read_to_string(config_file).ok()?;
-    let connect_content = contents.as_str();
-
-    let connection_config: DataSourceInform = toml::from_str(connect_content).ok()?;
-    let driver = connection_config.driver.as_str();
-
-    match driver {
-        "sqlserver" => {
-            let conn_config = connection_config.sqlserver?;
-            let handler = sqlserver::SqlServerHandler::from_config(conn_config).ok()?;
-            Some(handler)
-        }
-        _ => {
-            println!("Not supported driver");
-            None
-        }
-    }
+    qry_handler_from_dsi(DataSourceInform::from_file(config_file).ok()?).ok()
 }
 
-pub fn qry_handler_from_dsi(inform: DataSourceInform) -> Option<impl QueryHandler> {
-    let driver = inform.driver.as_str();
-
-    match driver {
+pub fn qry_handler_from_dsi(
+    inform: DataSourceInform,
+) -> Result<dyn_handler::BoxedQueryHandler, Box<dyn std::error::Error>> {
+    match inform.driver.as_str() {
         "sqlserver" => {
-            let conn_config = inform.sqlserver?;
-            let handler = sqlserver::SqlServerHandler::from_config(conn_config).ok()?;
-            Some(handler)
-        }
-        _ => {
-            println!("Not supported driver");
-            None
+            let conn_config = inform.sqlserver.ok_or(error::QueryHandleError::InvalidParameter(
+                "driver sqlserver needs a [sqlserver] table".to_string(),
+            ))?;
+            Ok(Box::new(sqlserver::SqlServerHandler::from_config(conn_config)?))
         }
+        driver => Err(Box::new(error::QueryHandleError::InvalidParameter(format!(
+            "Not supported driver: {}",
+            driver
+        )))),
     }
 }
@@ -0,0 +1,126 @@
+use std::future::Future;
+
+use super::datatype::DataTypeKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    Table,
+    View,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableInfo {
+    pub schema: String,
+    pub name: String,
+    pub kind: TableKind,
+}
+
+impl TableInfo {
+    pub fn qualified_name(&self) -> String {
+        format!("{}.{}", self.schema, self.name)
+    }
+}
+
+//NOTE: `max_length` counts characters and is -1 for `(max)` columns; it is None for
+//      non-character types, like `precision`/`scale` for non-numeric ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub type_name: String,
+    pub max_length: Option<i32>,
+    pub precision: Option<u8>,
+    pub scale: Option<u8>,
+    pub nullable: bool,
+    pub default: Option<String>,
+    pub identity: bool,
+}
+
+impl ColumnInfo {
+    pub fn kind(&self) -> DataTypeKind {
+        DataTypeKind::from_sql_type(&self.type_name)
+    }
+
+    pub fn declared_type(&self) -> String {
+        match (self.type_name.to_lowercase().as_str(), self.max_length, self.precision, self.scale)
+        {
+            (_, Some(-1), _, _) => format!("{}(max)", self.type_name),
+            (_, Some(length), _, _) => format!("{}({})", self.type_name, length),
+            ("decimal" | "numeric", _, Some(precision), Some(scale)) => {
+                format!("{}({},{})", self.type_name, precision, scale)
+            }
+            _ => self.type_name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrimaryKey {
+    pub name: String,
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKey {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub referenced_schema: String,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexInfo {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+    pub primary_key: bool,
+    pub clustered: bool,
+}
+
+//NOTE: table names are given as `table`, `schema.table` or `[schema].[table]`; an unqualified
+//      name is looked up in the default schema of the connection.
+pub trait SchemaInspector {
+    fn schemas(
+        &mut self,
+    ) -> impl Future<Output = Result<Vec<String>, Box<dyn std::error::Error>>> + Send;
+
+    fn tables(
+        &mut self,
+        schema: Option<&str>,
+    ) -> impl Future<Output = Result<Vec<TableInfo>, Box<dyn std::error::Error>>> + Send;
+
+    fn columns(
+        &mut self,
+        table: &str,
+    ) -> impl Future<Output = Result<Vec<ColumnInfo>, Box<dyn std::error::Error>>> + Send;
+
+    fn primary_key(
+        &mut self,
+        table: &str,
+    ) -> impl Future<Output = Result<Option<PrimaryKey>, Box<dyn std::error::Error>>> + Send;
+
+    fn foreign_keys(
+        &mut self,
+        table: &str,
+    ) -> impl Future<Output = Result<Vec<ForeignKey>, Box<dyn std::error::Error>>> + Send;
+
+    fn indexes(
+        &mut self,
+        table: &str,
+    ) -> impl Future<Output = Result<Vec<IndexInfo>, Box<dyn std::error::Error>>> + Send;
+}
+
+//NOTE: groups `(key, value)` rows, already ordered by key, into one value list per key.
+pub(crate) fn group_rows<K, V>(rows: Vec<(K, V)>) -> Vec<(K, Vec<V>)>
+where
+    K: PartialEq,
+{
+    let mut grouped: Vec<(K, Vec<V>)> = Vec::new();
+    for (key, value) in rows {
+        match grouped.last_mut() {
+            Some((last, values)) if *last == key => values.push(value),
+            _ => grouped.push((key, vec![value])),
+        }
+    }
+    grouped
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{OpenOptions, read_to_string},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::Deserialize;
+
+use super::error::QueryHandleError;
+
+const NONCE_LENGTH: usize = 12;
+const SECRET_PREFIX: &str = "secret:";
+
+#[derive(Deserialize, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        Secret(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+pub trait SecretProvider {
+    fn secret(&self, name: &str) -> Result<Option<Secret>, Box<dyn std::error::Error>>;
+}
+
+pub struct EnvSecretProvider;
+
+impl SecretProvider for EnvSecretProvider {
+    fn secret(&self, name: &str) -> Result<Option<Secret>, Box<dyn std::error::Error>> {
+        Ok(std::env::var(name).ok().map(Secret::new))
+    }
+}
+
+//NOTE: secrets are kept AES-256-GCM encrypted in a TOML file of `name = "base64(nonce|data)"`
+//      entries; the key lives in a separate file that is never committed.
+pub struct KeyfileSecretStore {
+    path: PathBuf,
+    cipher: Aes256Gcm,
+}
+
+impl KeyfileSecretStore {
+    pub fn open<P, K>(store_path: P, key_path: K) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+        K: AsRef<Path>,
+    {
+        let key = STANDARD.decode(read_to_string(key_path.as_ref())?.trim())?;
+        if key.len() != 32 {
+            return Err(Box::new(QueryHandleError::InvalidSecret(format!(
+                "{} does not hold a 256 bit key",
+                key_path.as_ref().display()
+            ))));
+        }
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        Ok(KeyfileSecretStore { path: store_path.as_ref().to_path_buf(), cipher })
+    }
+
+    //NOTE: refuses to replace an existing key, which would orphan every stored secret.
+    pub fn generate_key<P>(key_path: P) -> Result<(), Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(key_path)?;
+        writeln!(file, "{}", STANDARD.encode(key))?;
+        Ok(())
+    }
+
+    pub fn names(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(self.entries()?.into_keys().collect())
+    }
+
+    pub fn set(&self, name: &str, value: &Secret) -> Result<(), Box<dyn std::error::Error>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let encrypted = self
+            .cipher
+            .encrypt(&nonce, value.expose().as_bytes())
+            .map_err(|e| QueryHandleError::InvalidSecret(e.to_string()))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(encrypted);
+
+        let mut entries = self.entries()?;
+        entries.insert(name.to_string(), STANDARD.encode(sealed));
+        std::fs::write(&self.path, toml::to_string(&entries)?)?;
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        Ok(toml::from_str(&read_to_string(&self.path)?)?)
+    }
+}
+
+impl SecretProvider for KeyfileSecretStore {
+    fn secret(&self, name: &str) -> Result<Option<Secret>, Box<dyn std::error::Error>> {
+        let entries = self.entries()?;
+        let sealed = match entries.get(name) {
+            Some(sealed) => STANDARD.decode(sealed)?,
+            None => return Ok(None),
+        };
+        if sealed.len() < NONCE_LENGTH {
+            return Err(Box::new(QueryHandleError::InvalidSecret(name.to_string())));
+        }
+
+        let (nonce, encrypted) = sealed.split_at(NONCE_LENGTH);
+        let decrypted = self.cipher.decrypt(Nonce::from_slice(nonce), encrypted).map_err(|_| {
+            QueryHandleError::InvalidSecret(format!("{} cannot be decrypted", name))
+        })?;
+        Ok(Some(Secret::new(String::from_utf8(decrypted)?)))
+    }
+}
+
+//NOTE: replaces `${NAME}` with the environment variable NAME and `${secret:NAME}` with the
+//      secret NAME of `provider`; `$${` stands for a literal `${`.
+pub fn interpolate(
+    text: &str,
+    provider: Option<&dyn SecretProvider>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut interpolated = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            interpolated.push_str(&rest[..start - 1]);
+            interpolated.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        interpolated.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or(QueryHandleError::InvalidParameter("Unclosed ${".to_string()))?;
+        let name = &rest[start + 2..start + end];
+        let value = match name.strip_prefix(SECRET_PREFIX) {
+            Some(secret_name) => match provider {
+                Some(provider) => provider.secret(secret_name)?,
+                None => None,
+            },
+            None => EnvSecretProvider.secret(name)?,
+        };
+        let value = value.ok_or(QueryHandleError::SecretNotFound(name.to_string()))?;
+        interpolated.push_str(value.expose());
+        rest = &rest[start + end + 1..];
+    }
+    interpolated.push_str(rest);
+    Ok(interpolated)
+}
+
+pub(crate) fn interpolate_value(
+    value: &mut toml::Value,
+    provider: Option<&dyn SecretProvider>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match value {
+        toml::Value::String(text) => *text = interpolate(text, provider)?,
+        toml::Value::Array(values) => {
+            for value in values {
+                interpolate_value(value, provider)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, value) in table.iter_mut() {
+                interpolate_value(value, provider)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
@@ -1,19 +1,36 @@
-use std::sync::Arc;
+use std::{
+    fmt,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    sync::{Arc, MutexGuard},
+};
 
-use chrono::{DateTime, Duration};
-use futures::stream::TryStreamExt;
+use chrono::{DateTime, Duration, NaiveDate, Timelike};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use serde::Deserialize;
 use tiberius::{
-    AuthMethod, Client, ColumnData, Config, Query, QueryItem, ResultMetadata, Row, numeric::Numeric,
+    AuthMethod, Client, ColumnData, Config, EncryptionLevel, IntoSql, Query, QueryItem,
+    ResultMetadata, Row, TokenRow, Uuid, numeric::Numeric, time,
 };
 use tokio::net::TcpStream;
 use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
 
 use crate::rdb_qry_handler::{
-    DataRecord, DataRows, DataType, IntoDataRecord, QueryHandler, QueryResult,
+    DataRecord, DataRows, DataType, DataTypeKind, FetchMore, IntoDataRecord, QueryHandler,
+    QueryResult, RecordSink,
 };
 
+use crate::tabular_porter::{TabularCursor, error::TabularPortError};
+
+use super::bulk::{BulkLoadOptions, BulkRow, ColumnMapping};
+use super::datatype::Decimal;
 use super::error::QueryHandleError;
+use super::schema::{
+    ColumnInfo, ForeignKey, IndexInfo, PrimaryKey, SchemaInspector, TableInfo, TableKind,
+    group_rows,
+};
+use super::secret::Secret;
+use super::statement::{PreparedStatement, ReleaseQueue};
 
 trait IntoMetaRecord {
     fn into_meta_rec(self) -> (Option<Vec<String>>, Option<DataRecord>);
@@ -37,6 +54,7 @@ impl IntoMetaRecord for QueryItem {
 pub struct SqlServerHandler {
     conn_config: SqlServerConnectionConfig,
     client: Option<Client<Compat<TcpStream>>>,
+    release_queue: ReleaseQueue,
 }
 
 impl QueryHandler for SqlServerHandler {
@@ -45,7 +63,12 @@ impl QueryHandler for SqlServerHandler {
     fn from_config(
         conn_config: SqlServerConnectionConfig,
     ) -> Result<SqlServerHandler, Box<dyn std::error::Error>> {
-        Result::Ok(SqlServerHandler { conn_config, client: Option::None })
+        conn_config.validate()?;
+        Result::Ok(SqlServerHandler {
+            conn_config,
+            client: Option::None,
+            release_queue: ReleaseQueue::default(),
+        })
     }
 
     async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
@@ -64,7 +87,7 @@ impl QueryHandler for SqlServerHandler {
         &mut self,
         query: &str,
         bind_variables: Option<Arc<[DataType]>>,
-        fetch_more: Box<dyn Fn(Option<&[String]>, Option<&DataRecord>) -> bool + Send>,
+        fetch_more: FetchMore,
     ) -> Result<DataRows, Box<dyn std::error::Error>> {
         let client = self
             .client
@@ -84,8 +107,7 @@ impl QueryHandler for SqlServerHandler {
         while let Some(item) = stream.try_next().await? {
             let (col_meta, record) = item.into_meta_rec();
 
-            let need_to_continue =
-                fetch_more(col_meta.as_ref().map(|value| value.as_slice()), record.as_ref());
+            let need_to_continue = fetch_more(col_meta.as_deref(), record.as_ref());
 
             if col_meta.is_some() {
                 column_meta = col_meta;
@@ -102,6 +124,39 @@ impl QueryHandler for SqlServerHandler {
         Result::Ok(DataRows { column_meta, records })
     }
 
+    async fn query_for_each(
+        &mut self,
+        query: &str,
+        bind_variables: Option<Arc<[DataType]>>,
+        mut sink: RecordSink,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let client = self
+            .client
+            .as_mut()
+            .ok_or(QueryHandleError::NotInitialized("Client is not initialized".to_string()))?;
+
+        let mut select = Query::new(query);
+        if let Some(bind_vars) = bind_variables {
+            for bind_var in bind_vars.iter() {
+                SqlServerHandler::bind_query(&mut select, bind_var);
+            }
+        }
+        let mut stream = select.query(client).await?;
+
+        let mut fetched = 0;
+        while let Some(item) = stream.try_next().await? {
+            let (col_meta, record) = item.into_meta_rec();
+            if record.is_some() {
+                fetched += 1;
+            }
+            if !sink(col_meta.as_deref(), record) {
+                break;
+            }
+        }
+
+        Result::Ok(fetched)
+    }
+
     async fn mutate(
         &mut self,
         query: &str,
@@ -123,24 +178,334 @@ impl QueryHandler for SqlServerHandler {
         Result::Ok(QueryAffectedRows { affected_rows: result.total() })
     }
 
-    async fn close(self) -> Result<(), Box<dyn std::error::Error>> {
+    async fn prepare(
+        &mut self,
+        query: &str,
+        param_types: Arc<[DataTypeKind]>,
+    ) -> Result<PreparedStatement, Box<dyn std::error::Error>> {
+        self.release_dropped_statements().await?;
+        let client = self
+            .client
+            .as_mut()
+            .ok_or(QueryHandleError::NotInitialized("Client is not initialized".to_string()))?;
+
+        let mut prepare = Query::new(
+            "DECLARE @handle int; EXEC sp_prepare @handle OUTPUT, @P1, @P2; SELECT @handle;",
+        );
+        prepare.bind(SqlServerHandler::param_declaration(&param_types));
+        prepare.bind(query.to_string());
+
+        //NOTE: sp_prepare may emit the statement's metadata first, so the handle is the last result.
+        let results = prepare.query(client).await?.into_results().await?;
+        let handle = results
+            .last()
+            .and_then(|rows| rows.first())
+            .and_then(|row| row.get::<i32, _>(0))
+            .ok_or(QueryHandleError::Unknown("sp_prepare did not return a handle".to_string()))?;
+
+        Result::Ok(PreparedStatement::new(
+            handle,
+            query,
+            param_types,
+            Some(self.release_queue.clone()),
+        ))
+    }
+
+    async fn execute_prepared(
+        &mut self,
+        statement: &PreparedStatement,
+        bind_variables: Option<Arc<[DataType]>>,
+    ) -> Result<impl QueryResult, Box<dyn std::error::Error>> {
+        statement.check_binds(bind_variables.as_deref())?;
+        self.release_dropped_statements().await?;
+        let client = self
+            .client
+            .as_mut()
+            .ok_or(QueryHandleError::NotInitialized("Client is not initialized".to_string()))?;
+
+        let param_count = statement.param_types().len();
+        let placeholders: Vec<String> = (1..=param_count + 1).map(|i| format!("@P{}", i)).collect();
+        let mut execute = Query::new(format!("EXEC sp_execute {}", placeholders.join(", ")));
+        execute.bind(statement.handle());
+        if let Some(bind_vars) = bind_variables {
+            for bind_var in bind_vars.iter() {
+                SqlServerHandler::bind_query(&mut execute, bind_var);
+            }
+        }
+
+        let result = execute.execute(client).await?;
+        Result::Ok(QueryAffectedRows { affected_rows: result.total() })
+    }
+
+    async fn unprepare(
+        &mut self,
+        statement: PreparedStatement,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let handle = statement.release();
+        let client = self
+            .client
+            .as_mut()
+            .ok_or(QueryHandleError::NotInitialized("Client is not initialized".to_string()))?;
+
+        SqlServerHandler::unprepare_handle(client, handle).await
+    }
+
+    async fn close(mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let released = self.release_dropped_statements().await;
         let client = self.client.ok_or(QueryHandleError::InvalidCall(
             "Client cannot be unwrapped. Ownership might already be moved.".to_string(),
         ))?;
 
         client.close().await?;
-        Result::Ok(())
+        Result::Ok(released?)
     }
 }
 
-#[derive(Deserialize, Debug)]
+impl SchemaInspector for SqlServerHandler {
+    async fn schemas(&mut self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        //NOTE: schema ids from 16384 up belong to the fixed database roles.
+        let records = self
+            .catalog_records(
+                "SELECT name FROM sys.schemas WHERE schema_id < 16384 \
+                 AND name NOT IN ('guest', 'sys', 'INFORMATION_SCHEMA') ORDER BY name",
+                None,
+            )
+            .await?;
+        Result::Ok(records.iter().filter_map(|record| record.cvalue::<String>(0)).collect())
+    }
+
+    async fn tables(
+        &mut self,
+        schema: Option<&str>,
+    ) -> Result<Vec<TableInfo>, Box<dyn std::error::Error>> {
+        let (filter, table) = match schema {
+            Some(schema) => ("WHERE TABLE_SCHEMA = @P1", Some(schema.to_string())),
+            None => ("", None),
+        };
+        let query = format!(
+            "SELECT TABLE_SCHEMA, TABLE_NAME, TABLE_TYPE FROM INFORMATION_SCHEMA.TABLES {} \
+             ORDER BY TABLE_SCHEMA, TABLE_NAME",
+            filter
+        );
+        let records = self.catalog_records(&query, table.as_deref()).await?;
+
+        let tables = records
+            .iter()
+            .filter_map(|record| {
+                let kind = match record.cvalue::<String>(2)?.as_str() {
+                    "VIEW" => TableKind::View,
+                    _ => TableKind::Table,
+                };
+                Some(TableInfo { schema: record.cvalue(0)?, name: record.cvalue(1)?, kind })
+            })
+            .collect();
+        Result::Ok(tables)
+    }
+
+    async fn columns(
+        &mut self,
+        table: &str,
+    ) -> Result<Vec<ColumnInfo>, Box<dyn std::error::Error>> {
+        let records = self
+            .catalog_records(
+                "SELECT c.name, TYPE_NAME(c.user_type_id), \
+                 CAST(CASE WHEN TYPE_NAME(c.system_type_id) NOT IN \
+                 ('char', 'varchar', 'nchar', 'nvarchar', 'binary', 'varbinary') THEN NULL \
+                 WHEN c.max_length = -1 THEN -1 \
+                 WHEN TYPE_NAME(c.system_type_id) IN ('nchar', 'nvarchar') THEN c.max_length / 2 \
+                 ELSE c.max_length END AS int), \
+                 CASE WHEN TYPE_NAME(c.system_type_id) IN ('decimal', 'numeric') \
+                 THEN c.precision END, \
+                 CASE WHEN TYPE_NAME(c.system_type_id) IN ('decimal', 'numeric') \
+                 THEN c.scale END, \
+                 c.is_nullable, OBJECT_DEFINITION(c.default_object_id), c.is_identity \
+                 FROM sys.columns c WHERE c.object_id = OBJECT_ID(@P1) ORDER BY c.column_id",
+                Some(table),
+            )
+            .await?;
+
+        let columns = records
+            .iter()
+            .filter_map(|record| {
+                Some(ColumnInfo {
+                    name: record.cvalue(0)?,
+                    type_name: record.cvalue(1)?,
+                    max_length: record.cvalue(2),
+                    precision: record.cvalue(3),
+                    scale: record.cvalue(4),
+                    nullable: record.cvalue(5).unwrap_or(true),
+                    default: record.cvalue(6),
+                    identity: record.cvalue(7).unwrap_or(false),
+                })
+            })
+            .collect();
+        Result::Ok(columns)
+    }
+
+    async fn primary_key(
+        &mut self,
+        table: &str,
+    ) -> Result<Option<PrimaryKey>, Box<dyn std::error::Error>> {
+        let records = self
+            .catalog_records(
+                "SELECT k.name, c.name FROM sys.key_constraints k \
+                 JOIN sys.index_columns ic ON ic.object_id = k.parent_object_id \
+                 AND ic.index_id = k.unique_index_id \
+                 JOIN sys.columns c ON c.object_id = ic.object_id AND c.column_id = ic.column_id \
+                 WHERE k.parent_object_id = OBJECT_ID(@P1) AND k.type = 'PK' \
+                 ORDER BY ic.key_ordinal",
+                Some(table),
+            )
+            .await?;
+
+        let rows = records
+            .iter()
+            .filter_map(|record| Some((record.cvalue::<String>(0)?, record.cvalue::<String>(1)?)))
+            .collect();
+        let primary_key =
+            group_rows(rows).into_iter().next().map(|(name, columns)| PrimaryKey { name, columns });
+        Result::Ok(primary_key)
+    }
+
+    async fn foreign_keys(
+        &mut self,
+        table: &str,
+    ) -> Result<Vec<ForeignKey>, Box<dyn std::error::Error>> {
+        let records = self
+            .catalog_records(
+                "SELECT fk.name, SCHEMA_NAME(rt.schema_id), rt.name, pc.name, rc.name \
+                 FROM sys.foreign_keys fk \
+                 JOIN sys.foreign_key_columns fkc ON fkc.constraint_object_id = fk.object_id \
+                 JOIN sys.columns pc ON pc.object_id = fkc.parent_object_id \
+                 AND pc.column_id = fkc.parent_column_id \
+                 JOIN sys.objects rt ON rt.object_id = fkc.referenced_object_id \
+                 JOIN sys.columns rc ON rc.object_id = fkc.referenced_object_id \
+                 AND rc.column_id = fkc.referenced_column_id \
+                 WHERE fk.parent_object_id = OBJECT_ID(@P1) \
+                 ORDER BY fk.name, fkc.constraint_column_id",
+                Some(table),
+            )
+            .await?;
+
+        let rows = records
+            .iter()
+            .filter_map(|record| {
+                let key = (
+                    record.cvalue::<String>(0)?,
+                    record.cvalue::<String>(1)?,
+                    record.cvalue::<String>(2)?,
+                );
+                Some((key, (record.cvalue::<String>(3)?, record.cvalue::<String>(4)?)))
+            })
+            .collect();
+        let foreign_keys = group_rows(rows)
+            .into_iter()
+            .map(|((name, referenced_schema, referenced_table), pairs)| {
+                let (columns, referenced_columns) = pairs.into_iter().unzip();
+                ForeignKey {
+                    name,
+                    columns,
+                    referenced_schema,
+                    referenced_table,
+                    referenced_columns,
+                }
+            })
+            .collect();
+        Result::Ok(foreign_keys)
+    }
+
+    async fn indexes(&mut self, table: &str) -> Result<Vec<IndexInfo>, Box<dyn std::error::Error>> {
+        //NOTE: heaps have an unnamed index row and included columns are not part of the key.
+        let records = self
+            .catalog_records(
+                "SELECT i.name, i.is_unique, i.is_primary_key, \
+                 CAST(CASE WHEN i.type = 1 THEN 1 ELSE 0 END AS bit), c.name \
+                 FROM sys.indexes i \
+                 JOIN sys.index_columns ic ON ic.object_id = i.object_id \
+                 AND ic.index_id = i.index_id AND ic.is_included_column = 0 \
+                 JOIN sys.columns c ON c.object_id = ic.object_id AND c.column_id = ic.column_id \
+                 WHERE i.object_id = OBJECT_ID(@P1) AND i.name IS NOT NULL \
+                 ORDER BY i.name, ic.key_ordinal",
+                Some(table),
+            )
+            .await?;
+
+        let rows = records
+            .iter()
+            .filter_map(|record| {
+                let key = (
+                    record.cvalue::<String>(0)?,
+                    record.cvalue::<bool>(1)?,
+                    record.cvalue::<bool>(2)?,
+                    record.cvalue::<bool>(3)?,
+                );
+                Some((key, record.cvalue::<String>(4)?))
+            })
+            .collect();
+        let indexes = group_rows(rows)
+            .into_iter()
+            .map(|((name, unique, primary_key, clustered), columns)| IndexInfo {
+                name,
+                columns,
+                unique,
+                primary_key,
+                clustered,
+            })
+            .collect();
+        Result::Ok(indexes)
+    }
+}
+
+#[derive(Deserialize)]
 pub struct SqlServerConnectionConfig {
     ado_string: Option<String>,
     host: String,
     port: Option<u16>,
     database: String,
-    username: String,
-    password: String,
+    #[serde(flatten)]
+    credentials: SqlServerCredentials,
+    #[serde(flatten)]
+    tls: SqlServerTlsConfig,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SqlServerAuth {
+    Sql,
+    Integrated,
+    AadToken,
+}
+
+//NOTE: secrets are given inline (`${ENV_VAR}` and `${secret:NAME}` are resolved when the
+//      datasource file is loaded) or read from `password_file`/`token_file`, e.g. a mounted
+//      docker secret. Without `auth` and `username`, the login of `ado_string` is kept.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SqlServerCredentials {
+    auth: Option<SqlServerAuth>,
+    username: Option<String>,
+    password: Option<Secret>,
+    password_file: Option<PathBuf>,
+    token: Option<Secret>,
+    token_file: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SqlServerEncryption {
+    Off,
+    On,
+    Required,
+    #[serde(alias = "login-only")]
+    LoginOnly,
+}
+
+//NOTE: each setting overrides its counterpart in `ado_string` (`encrypt`,
+//      `TrustServerCertificate`, `TrustServerCertificateCA`). Without either, encryption is off.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SqlServerTlsConfig {
+    encryption: Option<SqlServerEncryption>,
+    trust_server_certificate: Option<bool>,
+    ca_certificate: Option<PathBuf>,
 }
 
 impl SqlServerConnectionConfig {
@@ -149,13 +514,320 @@ impl SqlServerConnectionConfig {
         host: String,
         port: Option<u16>,
         database: String,
-        username: String,
-        password: String,
+        credentials: SqlServerCredentials,
+        tls: SqlServerTlsConfig,
+    ) -> Self {
+        SqlServerConnectionConfig { ado_string, host, port, database, credentials, tls }
+    }
+
+    fn validate(&self) -> Result<(), QueryHandleError> {
+        self.credentials.validate(self.ado_string.is_some())?;
+        self.tls.validate()
+    }
+
+    pub fn credentials(&self) -> &SqlServerCredentials {
+        &self.credentials
+    }
+
+    pub fn tls(&self) -> &SqlServerTlsConfig {
+        &self.tls
+    }
+}
+
+impl fmt::Debug for SqlServerConnectionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqlServerConnectionConfig")
+            .field("ado_string", &self.ado_string.as_deref().map(redact_ado_string))
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("database", &self.database)
+            .field("credentials", &self.credentials)
+            .field("tls", &self.tls)
+            .finish()
+    }
+}
+
+impl SqlServerCredentials {
+    pub fn sql(username: String, password: String) -> Self {
+        SqlServerCredentials {
+            auth: Some(SqlServerAuth::Sql),
+            username: Some(username),
+            password: Some(Secret::new(password)),
+            ..Default::default()
+        }
+    }
+
+    pub fn integrated() -> Self {
+        SqlServerCredentials { auth: Some(SqlServerAuth::Integrated), ..Default::default() }
+    }
+
+    pub fn aad_token(token: String) -> Self {
+        SqlServerCredentials {
+            auth: Some(SqlServerAuth::AadToken),
+            token: Some(Secret::new(token)),
+            ..Default::default()
+        }
+    }
+
+    pub fn auth(&self) -> Option<SqlServerAuth> {
+        self.auth
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    fn validate(&self, has_ado_string: bool) -> Result<(), QueryHandleError> {
+        let has_password = check_secret_source("password", &self.password, &self.password_file)?;
+        let has_token = check_secret_source("token", &self.token, &self.token_file)?;
+        match self.auth {
+            None if self.username.is_none() && has_ado_string => Ok(()),
+            None | Some(SqlServerAuth::Sql) if self.username.is_none() => {
+                Err(QueryHandleError::InvalidParameter(
+                    "username is required for sql authentication".to_string(),
+                ))
+            }
+            None | Some(SqlServerAuth::Sql) if !has_password => {
+                Err(QueryHandleError::InvalidParameter(
+                    "password or password_file is required for sql authentication".to_string(),
+                ))
+            }
+            None | Some(SqlServerAuth::Sql) => Ok(()),
+            #[cfg(all(feature = "integrated-auth", any(windows, unix)))]
+            Some(SqlServerAuth::Integrated) => Ok(()),
+            #[cfg(not(all(feature = "integrated-auth", any(windows, unix))))]
+            Some(SqlServerAuth::Integrated) => Err(integrated_unsupported()),
+            Some(SqlServerAuth::AadToken) if !has_token => Err(QueryHandleError::InvalidParameter(
+                "token or token_file is required for aad_token authentication".to_string(),
+            )),
+            Some(SqlServerAuth::AadToken) => Ok(()),
+        }
+    }
+
+    //NOTE: None keeps the login given by `ado_string`.
+    fn auth_method(&self) -> Result<Option<AuthMethod>, Box<dyn std::error::Error>> {
+        match (self.auth, self.username.as_deref()) {
+            (None, None) => Ok(None),
+            (None | Some(SqlServerAuth::Sql), username) => {
+                let password = read_secret(&self.password, &self.password_file)?;
+                Ok(Some(AuthMethod::sql_server(username.unwrap_or_default(), password.expose())))
+            }
+            (Some(SqlServerAuth::AadToken), _) => {
+                let token = read_secret(&self.token, &self.token_file)?;
+                Ok(Some(AuthMethod::aad_token(token.expose())))
+            }
+            #[cfg(all(feature = "integrated-auth", any(windows, unix)))]
+            (Some(SqlServerAuth::Integrated), _) => Ok(Some(AuthMethod::Integrated)),
+            #[cfg(not(all(feature = "integrated-auth", any(windows, unix))))]
+            (Some(SqlServerAuth::Integrated), _) => Err(Box::new(integrated_unsupported())),
+        }
+    }
+}
+
+fn lock_release_queue(
+    release_queue: &ReleaseQueue,
+) -> Result<MutexGuard<'_, Vec<i32>>, QueryHandleError> {
+    release_queue
+        .lock()
+        .map_err(|e| QueryHandleError::Unknown(format!("Release queue is poisoned: {}", e)))
+}
+
+//NOTE: Kerberos (GSSAPI) on Unix or SSPI on Windows comes with the `integrated-auth` feature.
+#[cfg(not(all(feature = "integrated-auth", any(windows, unix))))]
+fn integrated_unsupported() -> QueryHandleError {
+    QueryHandleError::InvalidParameter(
+        "integrated authentication is not supported by this build".to_string(),
+    )
+}
+
+pub(crate) fn quote_ident(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+//NOTE: each part of a name such as `dbo.orders` is quoted on its own; parts already in
+//      brackets are unwrapped first, and dots inside brackets do not split.
+pub(crate) fn quote_table(name: &str) -> String {
+    let mut parts = vec![String::new()];
+    let mut bracketed = false;
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        let part = parts.last_mut().expect("parts start with one");
+        match c {
+            '[' if !bracketed => bracketed = true,
+            ']' if bracketed && chars.peek() == Some(&']') => {
+                part.push(c);
+                chars.next();
+            }
+            ']' if bracketed => bracketed = false,
+            '.' if !bracketed => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        part.push(c);
+    }
+
+    let parts: Vec<String> = parts
+        .iter()
+        .map(|part| {
+            let part = part.trim();
+            match part.strip_prefix('[').and_then(|p| p.strip_suffix(']')) {
+                Some(bracketed) => quote_ident(&bracketed.replace("]]", "]")),
+                None => quote_ident(part),
+            }
+        })
+        .collect();
+    parts.join(".")
+}
+
+fn check_secret_source(
+    name: &str,
+    inline: &Option<Secret>,
+    file: &Option<PathBuf>,
+) -> Result<bool, QueryHandleError> {
+    match (inline, file) {
+        (Some(_), Some(_)) => Err(QueryHandleError::InvalidParameter(format!(
+            "{} and {}_file cannot be used together",
+            name, name
+        ))),
+        (None, None) => Ok(false),
+        _ => Ok(true),
+    }
+}
+
+//NOTE: a single trailing newline of a secret file is not part of the secret.
+fn read_secret(
+    inline: &Option<Secret>,
+    file: &Option<PathBuf>,
+) -> Result<Secret, Box<dyn std::error::Error>> {
+    match (inline, file) {
+        (Some(secret), _) => Ok(secret.clone()),
+        (None, Some(file)) => {
+            let contents = read_to_string(file).map_err(|e| {
+                QueryHandleError::InvalidParameter(format!("{}: {}", file.display(), e))
+            })?;
+            let secret = contents.strip_suffix('\n').unwrap_or(&contents);
+            let secret = secret.strip_suffix('\r').unwrap_or(secret);
+            Ok(Secret::new(secret.to_string()))
+        }
+        (None, None) => Err(Box::new(QueryHandleError::InvalidParameter(
+            "a secret or its file is required".to_string(),
+        ))),
+    }
+}
+
+impl SqlServerTlsConfig {
+    pub fn new(
+        encryption: Option<SqlServerEncryption>,
+        trust_server_certificate: Option<bool>,
+        ca_certificate: Option<PathBuf>,
     ) -> Self {
-        SqlServerConnectionConfig { ado_string, host, port, database, username, password }
+        SqlServerTlsConfig { encryption, trust_server_certificate, ca_certificate }
+    }
+
+    pub fn encryption(&self) -> Option<SqlServerEncryption> {
+        self.encryption
+    }
+
+    pub fn trust_server_certificate(&self) -> Option<bool> {
+        self.trust_server_certificate
+    }
+
+    pub fn ca_certificate(&self) -> Option<&Path> {
+        self.ca_certificate.as_deref()
+    }
+
+    fn validate(&self) -> Result<(), QueryHandleError> {
+        if self.trust_server_certificate == Some(true) && self.ca_certificate.is_some() {
+            return Err(QueryHandleError::InvalidParameter(
+                "trust_server_certificate and ca_certificate cannot be used together".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    //NOTE: drops the TLS keys of an ADO string that this config overrides, so tiberius does not
+    //      refuse to combine a trusted certificate with a CA file.
+    fn strip_overridden(&self, ado_string: &str) -> String {
+        let overrides_trust =
+            self.trust_server_certificate.is_some() || self.ca_certificate.is_some();
+        split_ado_string(ado_string)
+            .into_iter()
+            .filter(|pair| {
+                let key = ado_key(pair);
+                match key.as_str() {
+                    "encrypt" => self.encryption.is_none(),
+                    "trustservercertificate" | "trustservercertificateca" => !overrides_trust,
+                    _ => true,
+                }
+            })
+            .collect::<Vec<&str>>()
+            .join(";")
+    }
+
+    fn apply(&self, config: &mut Config, ado_string: Option<&str>) {
+        let ado_encrypts = ado_string
+            .is_some_and(|ado| split_ado_string(ado).iter().any(|pair| ado_key(pair) == "encrypt"));
+        let level = match self.encryption {
+            Some(SqlServerEncryption::Off) => Some(EncryptionLevel::NotSupported),
+            Some(SqlServerEncryption::LoginOnly) => Some(EncryptionLevel::Off),
+            Some(SqlServerEncryption::On) => Some(EncryptionLevel::On),
+            Some(SqlServerEncryption::Required) => Some(EncryptionLevel::Required),
+            None if ado_encrypts => None,
+            None => Some(EncryptionLevel::NotSupported),
+        };
+        if let Some(level) = level {
+            config.encryption(level);
+        }
+
+        if self.trust_server_certificate == Some(true) {
+            config.trust_cert();
+        }
+        if let Some(ca_certificate) = &self.ca_certificate {
+            config.trust_cert_ca(ca_certificate.display());
+        }
     }
 }
 
+//NOTE: splits `key=value` pairs on `;`, leaving quoted values such as passwords intact.
+fn split_ado_string(ado_string: &str) -> Vec<&str> {
+    let mut pairs = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in ado_string.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            (';', None) => {
+                pairs.push(&ado_string[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    pairs.push(&ado_string[start..]);
+    pairs.into_iter().filter(|pair| !pair.trim().is_empty()).collect()
+}
+
+fn redact_ado_string(ado_string: &str) -> String {
+    split_ado_string(ado_string)
+        .into_iter()
+        .map(|pair| match ado_key(pair).as_str() {
+            "password" | "pwd" => {
+                format!("{}=***", pair.split_once('=').map_or(pair, |(key, _)| key))
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(";")
+}
+
+fn ado_key(pair: &str) -> String {
+    let key = pair.split_once('=').map(|(key, _)| key).unwrap_or(pair);
+    key.trim().replace(' ', "").to_lowercase()
+}
+
 impl SqlServerHandler {
     fn create_config(
         conn_config: &SqlServerConnectionConfig,
@@ -164,11 +836,12 @@ impl SqlServerHandler {
         let host = conn_config.host.as_str();
         let port = conn_config.port.unwrap_or(1433);
         let database = conn_config.database.as_str();
-        let username = conn_config.username.as_str();
-        let password = conn_config.password.as_str();
+
+        let tls = &conn_config.tls;
+        conn_config.validate()?;
 
         let mut config = if let Some(ado_string) = ado {
-            Config::from_ado_string(ado_string.as_str())?
+            Config::from_ado_string(&tls.strip_overridden(ado_string))?
         } else {
             Config::new()
         };
@@ -176,12 +849,94 @@ impl SqlServerHandler {
         config.host(host);
         config.port(port);
         config.database(database);
-        config.authentication(AuthMethod::sql_server(username, password));
-        config.encryption(tiberius::EncryptionLevel::NotSupported);
+        if let Some(auth_method) = conn_config.credentials.auth_method()? {
+            config.authentication(auth_method);
+        }
+        tls.apply(&mut config, ado.map(|ado_string| ado_string.as_str()));
 
         Result::Ok(config)
     }
 
+    async fn catalog_records(
+        &mut self,
+        query: &str,
+        bind_variable: Option<&str>,
+    ) -> Result<Vec<DataRecord>, Box<dyn std::error::Error>> {
+        let bind_variables: Option<Arc<[DataType]>> =
+            bind_variable.map(|value| Arc::from([DataType::String(value.to_string())]));
+        let rows = self.query(query, bind_variables, Self::default_fetch_more()).await?;
+        Result::Ok(rows.into_records())
+    }
+
+    async fn unprepare_handle(
+        client: &mut Client<Compat<TcpStream>>,
+        handle: i32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut unprepare = Query::new("EXEC sp_unprepare @P1");
+        unprepare.bind(handle);
+        unprepare.execute(client).await?;
+        Result::Ok(())
+    }
+
+    //NOTE: every handle is tried; those that fail go back on the queue for the next call, and
+    //      the first failure is returned.
+    async fn release_dropped_statements(&mut self) -> Result<(), QueryHandleError> {
+        let Some(client) = self.client.as_mut() else {
+            return Result::Ok(());
+        };
+        let handles: Vec<i32> = lock_release_queue(&self.release_queue)?.drain(..).collect();
+
+        let mut unreleased = Vec::new();
+        let mut first_error = None;
+        for handle in handles {
+            if let Err(e) = SqlServerHandler::unprepare_handle(client, handle).await {
+                unreleased.push(handle);
+                first_error
+                    .get_or_insert(format!("Statement {} cannot be released: {}", handle, e));
+            }
+        }
+        if !unreleased.is_empty() {
+            lock_release_queue(&self.release_queue)?.extend(unreleased);
+        }
+
+        match first_error {
+            Some(message) => Err(QueryHandleError::Unknown(message)),
+            None => Result::Ok(()),
+        }
+    }
+
+    fn param_declaration(param_types: &[DataTypeKind]) -> Option<String> {
+        if param_types.is_empty() {
+            return None;
+        }
+
+        let declarations: Vec<String> = param_types
+            .iter()
+            .enumerate()
+            .map(|(i, kind)| format!("@P{} {}", i + 1, SqlServerHandler::sql_type_name(*kind)))
+            .collect();
+        Some(declarations.join(", "))
+    }
+
+    fn sql_type_name(kind: DataTypeKind) -> &'static str {
+        match kind {
+            DataTypeKind::U8 => "tinyint",
+            DataTypeKind::I8 | DataTypeKind::I16 => "smallint",
+            DataTypeKind::I32 | DataTypeKind::U16 => "int",
+            DataTypeKind::I64 | DataTypeKind::U32 => "bigint",
+            DataTypeKind::U64 => "numeric(20, 0)",
+            DataTypeKind::I128 => "numeric(38, 0)",
+            DataTypeKind::F32 => "real",
+            DataTypeKind::F64 => "float",
+            //NOTE: a declared parameter needs a fixed scale, so finer values are rounded.
+            DataTypeKind::Decimal => "numeric(38, 10)",
+            DataTypeKind::Bool => "bit",
+            DataTypeKind::String => "nvarchar(max)",
+            DataTypeKind::Bytes => "varbinary(max)",
+            DataTypeKind::DateTime => "datetimeoffset",
+        }
+    }
+
     fn bind_query(query: &mut Query<'_>, bind_var: &DataType) {
         match bind_var {
             DataType::U8(val) => query.bind(val.to_owned()),
@@ -191,16 +946,20 @@ impl SqlServerHandler {
             DataType::I128(val) => query.bind(Numeric::new_with_scale(*val, 0)),
             DataType::F32(val) => query.bind(val.to_owned()),
             DataType::F64(val) => query.bind(val.to_owned()),
+            DataType::Decimal(val) => {
+                query.bind(Numeric::new_with_scale(val.mantissa(), val.scale()))
+            }
             DataType::Bool(val) => query.bind(val.to_owned()),
             DataType::String(val) => query.bind(val.to_owned()),
             DataType::Bytes(val) => query.bind(val.to_owned()),
             DataType::DateTime(val) => query.bind(Some(val.to_owned())),
 
             //NOTE: following types are not supported in tiberius. So, they are converted to supported types.
+            DataType::I8(val) => query.bind(val.to_owned() as i16),
             DataType::U16(val) => query.bind(val.to_owned() as i32),
             DataType::U32(val) => query.bind(val.to_owned() as i64),
-            DataType::U64(val) => query.bind(val.to_owned() as i64),
-            _ => {}
+            //NOTE: bigint cannot hold values above i64::MAX, so u64 is bound as numeric(20, 0).
+            DataType::U64(val) => query.bind(Numeric::new_with_scale(*val as i128, 0)),
         }
     }
 
@@ -236,7 +995,7 @@ impl SqlServerHandler {
                 if v.scale() == 0 {
                     DataType::I128(i128::from(v))
                 } else {
-                    DataType::F64(f64::from(v))
+                    DataType::Decimal(Decimal::new(v.value(), v.scale()))
                 }
             }),
 
@@ -261,6 +1020,311 @@ impl SqlServerHandler {
     }
 }
 
+struct BulkColumn {
+    name: String,
+    type_name: String,
+    precision: u8,
+    scale: u8,
+}
+
+impl SqlServerHandler {
+    pub async fn bulk_load_records<S>(
+        &mut self,
+        table: &str,
+        mapping: &ColumnMapping,
+        records: S,
+        options: BulkLoadOptions,
+    ) -> Result<QueryAffectedRows, Box<dyn std::error::Error>>
+    where
+        S: Stream<Item = DataRecord> + Unpin,
+    {
+        self.bulk_load(table, mapping, records.map(|record| Ok(BulkRow::Record(record))), options)
+            .await
+    }
+
+    pub async fn bulk_load_tabular<C>(
+        &mut self,
+        table: &str,
+        mapping: &ColumnMapping,
+        porter: &mut C,
+        options: BulkLoadOptions,
+    ) -> Result<QueryAffectedRows, Box<dyn std::error::Error>>
+    where
+        C: TabularCursor,
+    {
+        let rows = stream::iter(porter.try_cursor()?.map(|record| record.map(BulkRow::Text)));
+        self.bulk_load(table, mapping, rows, options).await
+    }
+
+    async fn bulk_load<S>(
+        &mut self,
+        table: &str,
+        mapping: &ColumnMapping,
+        rows: S,
+        mut options: BulkLoadOptions,
+    ) -> Result<QueryAffectedRows, Box<dyn std::error::Error>>
+    where
+        S: Stream<Item = Result<BulkRow, TabularPortError>> + Unpin,
+    {
+        let client = self
+            .client
+            .as_mut()
+            .ok_or(QueryHandleError::NotInitialized("Client is not initialized".to_string()))?;
+
+        //NOTE: tiberius writes the name into its statements as given.
+        let quoted = quote_table(table);
+        let columns = SqlServerHandler::bulk_columns(client, &quoted).await?;
+        for (_, target) in mapping.pairs() {
+            if !columns.iter().any(|column| column.name.to_lowercase() == target.to_lowercase()) {
+                return Err(Box::new(QueryHandleError::InvalidParameter(format!(
+                    "Column {} does not exist or is not insertable in {}",
+                    target, table
+                ))));
+            }
+        }
+        let sources: Vec<Option<usize>> =
+            columns.iter().map(|column| mapping.source_index(&column.name)).collect();
+
+        let mut rows = rows.skip(options.skip_rows());
+        let mut row_number = options.skip_rows();
+        let mut affected_rows = 0;
+        while let Some(first) = rows.next().await {
+            let mut request = client.bulk_insert(&quoted).await?;
+
+            let mut next = Some(first);
+            let mut batch = 0;
+            while let Some(row) = next {
+                row_number += 1;
+                let token_row = SqlServerHandler::bulk_token_row(&columns, &sources, row?)
+                    .map_err(|e| {
+                        QueryHandleError::InvalidParameter(format!("Row {}: {}", row_number, e))
+                    })?;
+                request.send(token_row).await?;
+
+                batch += 1;
+                next = if batch < options.batch_size() { rows.next().await } else { None };
+            }
+
+            let result = request.finalize().await?;
+            affected_rows += result.total();
+            options.report_progress(affected_rows);
+        }
+
+        Result::Ok(QueryAffectedRows { affected_rows })
+    }
+
+    async fn bulk_columns(
+        client: &mut Client<Compat<TcpStream>>,
+        table: &str,
+    ) -> Result<Vec<BulkColumn>, Box<dyn std::error::Error>> {
+        //NOTE: tiberius sends every updatable column, so identity, computed and rowversion
+        //      columns are left out to match its column list.
+        let mut select = Query::new(
+            "SELECT c.name, TYPE_NAME(c.system_type_id), c.precision, c.scale FROM sys.columns c \
+             WHERE c.object_id = OBJECT_ID(@P1) AND c.is_identity = 0 AND c.is_computed = 0 \
+             AND TYPE_NAME(c.system_type_id) <> 'timestamp' ORDER BY c.column_id",
+        );
+        select.bind(table.to_string());
+        let rows = select.query(client).await?.into_first_result().await?;
+
+        let columns: Vec<BulkColumn> = rows
+            .iter()
+            .map(|row| BulkColumn {
+                name: row.get::<&str, _>(0).unwrap_or_default().to_string(),
+                type_name: row.get::<&str, _>(1).unwrap_or_default().to_lowercase(),
+                precision: row.get::<u8, _>(2).unwrap_or_default(),
+                scale: row.get::<u8, _>(3).unwrap_or_default(),
+            })
+            .collect();
+        if columns.is_empty() {
+            return Err(Box::new(QueryHandleError::InvalidParameter(format!(
+                "Table {} does not exist",
+                table
+            ))));
+        }
+
+        Result::Ok(columns)
+    }
+
+    fn bulk_token_row(
+        columns: &[BulkColumn],
+        sources: &[Option<usize>],
+        row: BulkRow,
+    ) -> Result<TokenRow<'static>, String> {
+        let mut token_row = TokenRow::with_capacity(columns.len());
+        for (column, source) in columns.iter().zip(sources) {
+            let value = match (&row, source) {
+                (BulkRow::Record(record), Some(index)) => record.cell(*index).cloned(),
+                (BulkRow::Text(cells), Some(index)) => match cells.get(*index) {
+                    Some(text) => SqlServerHandler::parse_text(text, column)?,
+                    None => None,
+                },
+                (_, None) => None,
+            };
+            token_row.push(SqlServerHandler::bulk_cell(value, column)?);
+        }
+        Ok(token_row)
+    }
+
+    fn parse_text(text: &str, column: &BulkColumn) -> Result<Option<DataType>, String> {
+        let kind = DataTypeKind::from_sql_type(&column.type_name);
+        if text.is_empty() && kind != DataTypeKind::String {
+            return Ok(None);
+        }
+
+        kind.parse(text).map(Some).ok_or(format!(
+            "cannot convert '{}' to {} for column {}",
+            text, column.type_name, column.name
+        ))
+    }
+
+    fn bulk_cell(
+        value: Option<DataType>,
+        column: &BulkColumn,
+    ) -> Result<ColumnData<'static>, String> {
+        let cell = match column.type_name.as_str() {
+            "tinyint" => ColumnData::U8(SqlServerHandler::coerce(value, column, |v| {
+                SqlServerHandler::as_i128(v).and_then(|i| u8::try_from(i).ok())
+            })?),
+            "smallint" => ColumnData::I16(SqlServerHandler::coerce(value, column, |v| {
+                SqlServerHandler::as_i128(v).and_then(|i| i16::try_from(i).ok())
+            })?),
+            "int" => ColumnData::I32(SqlServerHandler::coerce(value, column, |v| {
+                SqlServerHandler::as_i128(v).and_then(|i| i32::try_from(i).ok())
+            })?),
+            "bigint" => ColumnData::I64(SqlServerHandler::coerce(value, column, |v| {
+                SqlServerHandler::as_i128(v).and_then(|i| i64::try_from(i).ok())
+            })?),
+            "bit" => ColumnData::Bit(SqlServerHandler::coerce(value, column, |v| {
+                SqlServerHandler::as_i128(v).map(|i| i != 0)
+            })?),
+            "real" => ColumnData::F32(SqlServerHandler::coerce(value, column, |v| {
+                SqlServerHandler::as_f64(v).map(|f| f as f32)
+            })?),
+            "float" => {
+                ColumnData::F64(SqlServerHandler::coerce(value, column, SqlServerHandler::as_f64)?)
+            }
+            "decimal" | "numeric" => {
+                let scale = column.scale;
+                //NOTE: a value with more digits than the column's precision does not fit.
+                let limit = 10u128.checked_pow(column.precision as u32).unwrap_or(u128::MAX);
+                ColumnData::Numeric(SqlServerHandler::coerce(value, column, |v| {
+                    let scaled = match v {
+                        DataType::F32(_) | DataType::F64(_) => SqlServerHandler::as_f64(v)
+                            .map(|f| (f * 10f64.powi(scale as i32)).round())
+                            .filter(|f| f.is_finite() && f.abs() < limit as f64)
+                            .map(|f| f as i128),
+                        DataType::Decimal(d) => d.rescale(scale).map(|d| d.mantissa()),
+                        _ => SqlServerHandler::as_i128(v)
+                            .and_then(|i| i.checked_mul(10i128.pow(scale as u32))),
+                    };
+                    scaled
+                        .filter(|s| s.unsigned_abs() < limit)
+                        .map(|s| Numeric::new_with_scale(s, scale))
+                })?)
+            }
+            "binary" | "varbinary" | "image" => {
+                ColumnData::Binary(SqlServerHandler::coerce(value, column, |v| match v {
+                    DataType::Bytes(bytes) => Some(bytes.clone().into()),
+                    _ => None,
+                })?)
+            }
+            "uniqueidentifier" => ColumnData::Guid(SqlServerHandler::coerce(value, column, |v| {
+                Uuid::parse_str(v.to_string().trim()).ok()
+            })?),
+            "date" | "time" | "datetime" | "datetime2" | "smalldatetime" | "datetimeoffset" => {
+                let date_time = SqlServerHandler::coerce(value, column, |v| match v {
+                    DataType::DateTime(dt) => Some(*dt),
+                    _ => None,
+                })?;
+                SqlServerHandler::bulk_date_time(date_time, column)?
+            }
+            _ => ColumnData::String(value.map(|v| v.to_string().into())),
+        };
+
+        Ok(cell)
+    }
+
+    fn coerce<T>(
+        value: Option<DataType>,
+        column: &BulkColumn,
+        convert: impl Fn(&DataType) -> Option<T>,
+    ) -> Result<Option<T>, String> {
+        match value {
+            Some(v) => convert(&v)
+                .map(Some)
+                .ok_or(format!("{:?} does not fit {} column {}", v, column.type_name, column.name)),
+            None => Ok(None),
+        }
+    }
+
+    fn as_i128(value: &DataType) -> Option<i128> {
+        match value {
+            DataType::I8(v) => Some(*v as i128),
+            DataType::I16(v) => Some(*v as i128),
+            DataType::I32(v) => Some(*v as i128),
+            DataType::I64(v) => Some(*v as i128),
+            DataType::I128(v) => Some(*v),
+            DataType::U8(v) => Some(*v as i128),
+            DataType::U16(v) => Some(*v as i128),
+            DataType::U32(v) => Some(*v as i128),
+            DataType::U64(v) => Some(*v as i128),
+            DataType::Bool(v) => Some(*v as i128),
+            DataType::Decimal(v) => v
+                .rescale(0)
+                .filter(|whole| whole.rescale(v.scale()) == Some(*v))
+                .map(|whole| whole.mantissa()),
+            _ => None,
+        }
+    }
+
+    fn as_f64(value: &DataType) -> Option<f64> {
+        match value {
+            DataType::F32(v) => Some(*v as f64),
+            DataType::F64(v) => Some(*v),
+            DataType::Decimal(v) => Some(v.to_f64()),
+            other => SqlServerHandler::as_i128(other).map(|v| v as f64),
+        }
+    }
+
+    fn bulk_date_time(
+        value: Option<DateTime<chrono::FixedOffset>>,
+        column: &BulkColumn,
+    ) -> Result<ColumnData<'static>, String> {
+        let base_date = NaiveDate::from_ymd_opt(1900, 1, 1).expect("Criteria date setting error");
+        let cell = match (column.type_name.as_str(), value) {
+            ("date", Some(dt)) => dt.date_naive().into_sql(),
+            ("date", None) => ColumnData::Date(None),
+            ("time", Some(dt)) => dt.time().into_sql(),
+            ("time", None) => ColumnData::Time(None),
+            ("datetime2", Some(dt)) => dt.naive_local().into_sql(),
+            ("datetime2", None) => ColumnData::DateTime2(None),
+            ("datetimeoffset", Some(dt)) => dt.into_sql(),
+            ("datetimeoffset", None) => ColumnData::DateTimeOffset(None),
+            ("smalldatetime", Some(dt)) => {
+                let naive = dt.naive_local();
+                let days = u16::try_from((naive.date() - base_date).num_days()).map_err(|_| {
+                    format!("{} is out of range for smalldatetime column {}", dt, column.name)
+                })?;
+                let minutes = naive.time().num_seconds_from_midnight() / 60;
+                ColumnData::SmallDateTime(Some(time::SmallDateTime::new(days, minutes as u16)))
+            }
+            ("smalldatetime", None) => ColumnData::SmallDateTime(None),
+            (_, Some(dt)) => {
+                let naive = dt.naive_local();
+                let days = (naive.date() - base_date).num_days() as i32;
+                let time = naive.time();
+                let millis = time.num_seconds_from_midnight() as u64 * 1000
+                    + time.nanosecond() as u64 / 1_000_000;
+                ColumnData::DateTime(Some(time::DateTime::new(days, (millis * 3 / 10) as u32)))
+            }
+            (_, None) => ColumnData::DateTime(None),
+        };
+
+        Ok(cell)
+    }
+}
+
 impl IntoDataRecord for Row {
     fn into_data_record(self) -> DataRecord {
         let cells = self.into_iter().map(SqlServerHandler::col_to_cell).collect();
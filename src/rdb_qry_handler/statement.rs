@@ -0,0 +1,77 @@
+use std::sync::{Arc, Mutex};
+
+use super::datatype::{DataType, DataTypeKind};
+use super::error::QueryHandleError;
+
+//NOTE: handles of statements dropped without `unprepare`, released by the handler on its next call.
+pub type ReleaseQueue = Arc<Mutex<Vec<i32>>>;
+
+#[derive(Debug)]
+pub struct PreparedStatement {
+    handle: i32,
+    query: String,
+    param_types: Arc<[DataTypeKind]>,
+    release_queue: Option<ReleaseQueue>,
+}
+
+impl PreparedStatement {
+    pub fn new(
+        handle: i32,
+        query: &str,
+        param_types: Arc<[DataTypeKind]>,
+        release_queue: Option<ReleaseQueue>,
+    ) -> Self {
+        PreparedStatement { handle, query: query.to_string(), param_types, release_queue }
+    }
+
+    pub fn handle(&self) -> i32 {
+        self.handle
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn param_types(&self) -> &[DataTypeKind] {
+        &self.param_types
+    }
+
+    pub fn check_binds(&self, bind_variables: Option<&[DataType]>) -> Result<(), QueryHandleError> {
+        let binds = bind_variables.unwrap_or_default();
+        if binds.len() != self.param_types.len() {
+            return Err(QueryHandleError::InvalidParameter(format!(
+                "Statement expects {} parameters but {} were given",
+                self.param_types.len(),
+                binds.len()
+            )));
+        }
+
+        for (i, (bind, expected)) in binds.iter().zip(self.param_types.iter()).enumerate() {
+            if bind.kind() != *expected {
+                return Err(QueryHandleError::InvalidParameter(format!(
+                    "Parameter @P{} expects {:?} but {:?} was given",
+                    i + 1,
+                    expected,
+                    bind.kind()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn release(mut self) -> i32 {
+        self.release_queue = None;
+        self.handle
+    }
+}
+
+impl Drop for PreparedStatement {
+    fn drop(&mut self) {
+        if let Some(queue) = self.release_queue.take()
+            && let Ok(mut handles) = queue.lock()
+        {
+            handles.push(self.handle);
+        }
+    }
+}
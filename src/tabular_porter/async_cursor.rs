@@ -0,0 +1,139 @@
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+
+use futures::{Stream, stream};
+use tokio::{sync::mpsc, task::spawn_blocking};
+
+use super::{
+    TabularCursor, TabularNullableRecord, TabularPorter, TabularStringRecord,
+    error::TabularPortError,
+};
+
+//NOTE: records read ahead of the consumer before the reading thread waits.
+const RECORD_BUFFER: usize = 1024;
+
+pub type TabularRecordStream =
+    Pin<Box<dyn Stream<Item = Result<TabularStringRecord, TabularPortError>> + Send>>;
+pub type TabularNullableRecordStream =
+    Pin<Box<dyn Stream<Item = Result<TabularNullableRecord, TabularPortError>> + Send>>;
+
+pub trait AsyncTabularCursor {
+    fn headers(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<TabularStringRecord>, Box<dyn std::error::Error>>> + Send;
+
+    fn stream(self) -> TabularRecordStream;
+
+    //NOTE: like `stream`, with NULL cells as `None`.
+    fn nullable_stream(self) -> TabularNullableRecordStream;
+}
+
+//NOTE: runs a synchronous porter on tokio's blocking threads, so parsing neither stalls the
+//      runtime nor waits for the consumer until `RECORD_BUFFER` records are pending.
+pub struct BlockingTabularCursor<C> {
+    cursor: Option<C>,
+}
+
+impl<C> BlockingTabularCursor<C>
+where
+    C: TabularCursor + Send + 'static,
+{
+    pub fn new(cursor: C) -> Self {
+        BlockingTabularCursor { cursor: Some(cursor) }
+    }
+
+    pub async fn open<P>(src: P) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        C: TabularPorter,
+        P: AsRef<Path>,
+    {
+        let src: PathBuf = src.as_ref().to_path_buf();
+        let cursor = spawn_blocking(move || C::new(src).map_err(|e| read_failed(&*e))).await??;
+        Ok(BlockingTabularCursor::new(cursor))
+    }
+
+    pub fn into_inner(self) -> Option<C> {
+        self.cursor
+    }
+}
+
+impl<C> AsyncTabularCursor for BlockingTabularCursor<C>
+where
+    C: TabularCursor + Send + 'static,
+{
+    async fn headers(&mut self) -> Result<Option<TabularStringRecord>, Box<dyn std::error::Error>> {
+        let mut cursor = self.cursor.take().ok_or(cursor_lost())?;
+        let (cursor, headers) = spawn_blocking(move || {
+            let headers = cursor.headers().map_err(|e| read_failed(&*e));
+            (cursor, headers)
+        })
+        .await?;
+        self.cursor = Some(cursor);
+        Ok(headers?)
+    }
+
+    fn stream(self) -> TabularRecordStream {
+        spawn_stream(self.cursor, |mut cursor, sender| send_records(cursor.try_cursor(), &sender))
+    }
+
+    fn nullable_stream(self) -> TabularNullableRecordStream {
+        spawn_stream(self.cursor, |mut cursor, sender| {
+            send_records(cursor.try_nullable_cursor(), &sender)
+        })
+    }
+}
+
+fn spawn_stream<C, T, F>(
+    cursor: Option<C>,
+    read: F,
+) -> Pin<Box<dyn Stream<Item = Result<T, TabularPortError>> + Send>>
+where
+    C: Send + 'static,
+    T: Send + 'static,
+    F: FnOnce(C, mpsc::Sender<Result<T, TabularPortError>>) + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(RECORD_BUFFER);
+    match cursor {
+        Some(cursor) => {
+            spawn_blocking(move || read(cursor, sender));
+        }
+        None => {
+            let _ = sender.try_send(Err(cursor_lost()));
+        }
+    }
+    Box::pin(stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|record| (record, receiver))
+    }))
+}
+
+fn send_records<T, I>(
+    records: Result<I, Box<dyn std::error::Error>>,
+    sender: &mpsc::Sender<Result<T, TabularPortError>>,
+) where
+    I: Iterator<Item = Result<T, TabularPortError>>,
+{
+    match records {
+        Ok(records) => {
+            for record in records {
+                let failed = record.is_err();
+                if sender.blocking_send(record).is_err() || failed {
+                    break;
+                }
+            }
+        }
+        Err(e) => {
+            let _ = sender.blocking_send(Err(read_failed(&*e)));
+        }
+    }
+}
+
+fn read_failed(e: &dyn std::error::Error) -> TabularPortError {
+    TabularPortError::ReadFailed(e.to_string())
+}
+
+fn cursor_lost() -> TabularPortError {
+    TabularPortError::NotInitialized("Cursor was lost by a failed read".to_string())
+}
@@ -0,0 +1,211 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+//NOTE: the longest magic number, xz's.
+const MAGIC_LEN: u64 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+//NOTE: a sink whose trailer, if any, is written by `finish`, so errors are not lost on drop.
+pub trait FinishWrite: Write + Send {
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+//NOTE: detects the compression of the first bytes on the first read, falling back to the hint
+//      given by the file name. Reading nothing up front keeps blocking readers lazy.
+pub struct DecompressReader {
+    state: DecompressState,
+}
+
+enum DecompressState {
+    Detecting(Box<dyn Read + Send>, Option<Compression>),
+    Reading(Box<dyn Read + Send>),
+    Failed,
+}
+
+impl Compression {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            "xz" => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x1F, 0x8B, ..] => Some(Compression::Gzip),
+            [0x28, 0xB5, 0x2F, 0xFD, ..] => Some(Compression::Zstd),
+            [b'B', b'Z', b'h', b'1'..=b'9', ..] => Some(Compression::Bzip2),
+            [0xFD, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    //NOTE: `feed.csv.gz` is split into `Some(Gzip)` and `feed.csv`.
+    pub fn split_path(path: &Path) -> (Option<Self>, PathBuf) {
+        let compression = path
+            .extension()
+            .and_then(|extension| Compression::from_extension(&extension.to_string_lossy()));
+        match compression {
+            Some(compression) => (Some(compression), path.with_extension("")),
+            None => (None, path.to_path_buf()),
+        }
+    }
+
+    //NOTE: concatenated members/frames/streams are read as one, like the command line tools do.
+    pub fn decoder<R>(self, reader: R) -> io::Result<Box<dyn Read + Send>>
+    where
+        R: BufRead + Send + 'static,
+    {
+        let decoder: Box<dyn Read + Send> = match self {
+            Compression::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(reader)),
+            #[cfg(feature = "xz")]
+            Compression::Xz => Box::new(liblzma::bufread::XzDecoder::new_multi_decoder(reader)),
+            #[cfg(not(all(feature = "zstd", feature = "bzip2", feature = "xz")))]
+            disabled => return Err(disabled.unsupported()),
+        };
+        Ok(decoder)
+    }
+
+    pub fn encoder<W>(self, writer: W) -> io::Result<Box<dyn FinishWrite>>
+    where
+        W: Write + Send + 'static,
+    {
+        let encoder: Box<dyn FinishWrite> = match self {
+            Compression::Gzip => {
+                Box::new(flate2::write::GzEncoder::new(writer, flate2::Compression::default()))
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Box::new(zstd::stream::write::Encoder::new(writer, 0)?),
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => {
+                Box::new(bzip2::write::BzEncoder::new(writer, bzip2::Compression::default()))
+            }
+            #[cfg(feature = "xz")]
+            Compression::Xz => Box::new(liblzma::write::XzEncoder::new(writer, 6)),
+            #[cfg(not(all(feature = "zstd", feature = "bzip2", feature = "xz")))]
+            disabled => return Err(disabled.unsupported()),
+        };
+        Ok(encoder)
+    }
+
+    //NOTE: zstd, bzip2 and xz link C libraries, so each is built only with the feature of the
+    //      same name; files using them are still recognised and fail with this error.
+    #[cfg(not(all(feature = "zstd", feature = "bzip2", feature = "xz")))]
+    fn unsupported(self) -> io::Error {
+        let feature = match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+            Compression::Xz => "xz",
+        };
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{0} compression needs the `{0}` feature", feature),
+        )
+    }
+}
+
+impl DecompressReader {
+    pub fn new<R>(reader: R, hint: Option<Compression>) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        let reader: Box<dyn Read + Send> = Box::new(reader);
+        DecompressReader { state: DecompressState::Detecting(reader, hint) }
+    }
+
+    pub fn open<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let (hint, _) = Compression::split_path(path.as_ref());
+        Ok(DecompressReader::new(File::open(path)?, hint))
+    }
+}
+
+impl Read for DecompressReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let DecompressState::Detecting(reader, hint) = &mut self.state {
+            //NOTE: a single read may return fewer bytes than the magic number, even before EOF.
+            let mut magic = Vec::with_capacity(MAGIC_LEN as usize);
+            reader.by_ref().take(MAGIC_LEN).read_to_end(&mut magic)?;
+            let compression = Compression::from_magic(&magic).or(*hint);
+            let reader = match std::mem::replace(&mut self.state, DecompressState::Failed) {
+                DecompressState::Detecting(reader, _) => reader,
+                _ => unreachable!("state was checked above"),
+            };
+            let reader: Box<dyn Read + Send> = Box::new(io::Cursor::new(magic).chain(reader));
+            let reader = BufReader::new(reader);
+            self.state = DecompressState::Reading(match compression {
+                Some(compression) => compression.decoder(reader)?,
+                None => Box::new(reader),
+            });
+        }
+        match &mut self.state {
+            DecompressState::Reading(reader) => reader.read(buf),
+            _ => Err(io::Error::other("decompression failed to start")),
+        }
+    }
+}
+
+impl FinishWrite for File {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+impl<W> FinishWrite for flate2::write::GzEncoder<W>
+where
+    W: Write + Send,
+{
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        (*self).finish()?.flush()
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<W> FinishWrite for zstd::stream::write::Encoder<'static, W>
+where
+    W: Write + Send,
+{
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        (*self).finish()?.flush()
+    }
+}
+
+#[cfg(feature = "bzip2")]
+impl<W> FinishWrite for bzip2::write::BzEncoder<W>
+where
+    W: Write + Send,
+{
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        (*self).finish()?.flush()
+    }
+}
+
+#[cfg(feature = "xz")]
+impl<W> FinishWrite for liblzma::write::XzEncoder<W>
+where
+    W: Write + Send,
+{
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        (*self).finish()?.flush()
+    }
+}
@@ -1,11 +1,37 @@
-use super::{TabularCursor, TabularPorter, TabularStringRecord};
+use std::{
+    fs::File,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use encoding_rs::UTF_8;
+use tokio::io::AsyncRead;
+use tokio_util::io::SyncIoBridge;
+
+use crate::rdb_qry_handler::datatype::DataType;
+
+use super::{
+    TabularCursor, TabularPorter, TabularStringRecord, TabularWriter, WriteOptions,
+    compression::{Compression, DecompressReader, FinishWrite},
+    encoding::{TextEncoding, TranscodeReader, TranscodeWriter},
+    error::TabularPortError,
+    part_path,
+};
 
 pub struct CsvPorter {
-    reader: csv::Reader<std::fs::File>,
+    reader: csv::Reader<TranscodeReader>,
+}
+
+pub struct CsvWriter {
+    path: PathBuf,
+    part: usize,
+    compression: Option<Compression>,
+    writer: Option<csv::Writer<Box<dyn FinishWrite>>>,
+    options: WriteOptions,
 }
 
 pub struct CsvRecordCursor<'a> {
-    cursor: csv::StringRecordsIter<'a, std::fs::File>,
+    cursor: csv::ByteRecordsIter<'a, TranscodeReader>,
 }
 
 impl TabularPorter for CsvPorter {
@@ -13,9 +39,51 @@ impl TabularPorter for CsvPorter {
     where
         P: AsRef<std::path::Path>,
     {
-        let file = std::fs::File::open(src)?;
-        let reader = csv::Reader::from_reader(file);
-        Ok(CsvPorter { reader })
+        CsvPorter::open(src, TextEncoding::Auto)
+    }
+}
+
+//NOTE: gzip, zstd, bzip2 and xz input is decompressed on the fly, told by its first bytes or,
+//      for files, by an extension such as `.csv.gz`. The text is then transcoded to UTF-8 from
+//      the given encoding, or from the one told by its BOM or guessed from its first bytes.
+impl CsvPorter {
+    pub fn open<P>(src: P, encoding: TextEncoding) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let reader = DecompressReader::open(src)?;
+        Ok(CsvPorter::from_decompressed(reader, encoding))
+    }
+
+    pub fn from_reader<R>(reader: R) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        CsvPorter::from_reader_with_encoding(reader, TextEncoding::Auto)
+    }
+
+    pub fn from_reader_with_encoding<R>(reader: R, encoding: TextEncoding) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        CsvPorter::from_decompressed(DecompressReader::new(reader, None), encoding)
+    }
+
+    fn from_decompressed(reader: DecompressReader, encoding: TextEncoding) -> Self {
+        CsvPorter { reader: csv::Reader::from_reader(TranscodeReader::new(reader, encoding)) }
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        CsvPorter::from_reader(Cursor::new(bytes))
+    }
+
+    //NOTE: reads block on the runtime, so the porter must be read from a blocking thread,
+    //      e.g. through `BlockingTabularCursor`. Must be called within a tokio runtime.
+    pub fn from_async_reader<R>(reader: R) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        CsvPorter::from_reader(SyncIoBridge::new(reader))
     }
 }
 
@@ -23,7 +91,30 @@ impl TabularCursor for CsvPorter {
     fn cursor(
         &mut self,
     ) -> Result<impl Iterator<Item = TabularStringRecord>, Box<dyn std::error::Error>> {
-        Ok(CsvRecordCursor { cursor: self.reader.records() })
+        Ok(CsvRecordCursor { cursor: self.reader.byte_records() })
+    }
+
+    fn try_cursor(
+        &mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<TabularStringRecord, TabularPortError>>,
+        Box<dyn std::error::Error>,
+    > {
+        Ok(self.reader.byte_records().map(|record| {
+            record
+                .map(|record| {
+                    record.iter().map(|s| String::from_utf8_lossy(s).to_string()).collect()
+                })
+                .map_err(|e| TabularPortError::ReadFailed(e.to_string()))
+        }))
+    }
+
+    fn headers(&mut self) -> Result<Option<TabularStringRecord>, Box<dyn std::error::Error>> {
+        if !self.reader.has_headers() {
+            return Ok(None);
+        }
+        let headers = self.reader.byte_headers()?;
+        Ok(Some(headers.iter().map(|s| String::from_utf8_lossy(s).to_string()).collect()))
     }
 }
 
@@ -33,8 +124,95 @@ impl Iterator for CsvRecordCursor<'_> {
     fn next(&mut self) -> Option<Self::Item> {
         let record = self.cursor.next();
         match record {
-            Some(Ok(record)) => Some(record.iter().map(|s| s.to_string()).collect()),
+            Some(Ok(record)) => {
+                Some(record.iter().map(|s| String::from_utf8_lossy(s).to_string()).collect())
+            }
             _ => None,
         }
     }
 }
+
+//NOTE: a destination such as `out.csv.gz` is written compressed, each part in its own file.
+impl TabularWriter for CsvWriter {
+    fn create<P>(dest: P, options: WriteOptions) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let (compression, _) = Compression::split_path(dest.as_ref());
+        let writer = CsvWriter::open_part(dest.as_ref(), compression, &options)?;
+        Ok(CsvWriter {
+            path: dest.as_ref().to_path_buf(),
+            part: 1,
+            compression,
+            writer: Some(writer),
+            options,
+        })
+    }
+
+    fn write_header(&mut self, header: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        self.writer()?.write_record(header)?;
+        Ok(())
+    }
+
+    fn write_record(
+        &mut self,
+        record: &[Option<DataType>],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cells: Vec<String> =
+            record.iter().map(|cell| self.options.format_cell(cell.as_ref())).collect();
+        self.writer()?.write_record(cells)?;
+        Ok(())
+    }
+
+    fn next_part(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(writer) = self.writer.take() {
+            CsvWriter::close_part(writer)?;
+        }
+        self.part += 1;
+        self.writer = Some(CsvWriter::open_part(
+            &part_path(&self.path, self.part),
+            self.compression,
+            &self.options,
+        )?);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(writer) = self.writer.take() {
+            CsvWriter::close_part(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl CsvWriter {
+    fn writer(&mut self) -> Result<&mut csv::Writer<Box<dyn FinishWrite>>, TabularPortError> {
+        self.writer
+            .as_mut()
+            .ok_or(TabularPortError::NotInitialized("Writer is already finished".to_string()))
+    }
+
+    fn open_part(
+        path: &Path,
+        compression: Option<Compression>,
+        options: &WriteOptions,
+    ) -> Result<csv::Writer<Box<dyn FinishWrite>>, Box<dyn std::error::Error>> {
+        let file = File::create(path)?;
+        let mut sink: Box<dyn FinishWrite> = match compression {
+            Some(compression) => compression.encoder(file)?,
+            None => Box::new(file),
+        };
+        if options.encoding() != UTF_8 || options.bom() {
+            sink = Box::new(TranscodeWriter::new(sink, options.encoding(), options.bom())?);
+        }
+        Ok(csv::Writer::from_writer(sink))
+    }
+
+    fn close_part(
+        writer: csv::Writer<Box<dyn FinishWrite>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let sink = writer.into_inner().map_err(|e| e.into_error())?;
+        sink.finish()?;
+        Ok(())
+    }
+}
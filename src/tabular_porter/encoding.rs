@@ -0,0 +1,291 @@
+use std::io::{self, Cursor, Read, Write};
+
+use chardetng::EncodingDetector;
+use encoding_rs::{EncoderResult, Encoding, UTF_8, UTF_16BE, UTF_16LE, mem::convert_utf8_to_utf16};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+
+use super::compression::FinishWrite;
+
+//NOTE: bytes looked at to guess the encoding of input without a BOM.
+const SNIFF_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    #[default]
+    Auto,
+    Fixed(&'static Encoding),
+}
+
+//NOTE: decodes the input to UTF-8 on the first read, so blocking readers stay lazy. A BOM wins
+//      over the given or guessed encoding, and malformed bytes become U+FFFD, except when UTF-8
+//      was guessed from the sample alone: then they fail the read, as the rest of the input may
+//      be in another encoding.
+pub struct TranscodeReader {
+    state: TranscodeState,
+}
+
+//NOTE: passes UTF-8 through, holding back a character split across reads.
+struct Utf8Reader {
+    reader: Box<dyn Read + Send>,
+    buffer: Vec<u8>,
+    valid: usize,
+    offset: u64,
+}
+
+enum TranscodeState {
+    Detecting(Box<dyn Read + Send>, TextEncoding),
+    Reading(Box<dyn Read + Send>),
+    Failed,
+}
+
+//NOTE: encodes the UTF-8 written to it, holding back a character split across writes.
+//      Characters the encoding cannot represent fail the write rather than being replaced.
+pub struct TranscodeWriter {
+    writer: Box<dyn FinishWrite>,
+    encoding: &'static Encoding,
+    pending: Vec<u8>,
+}
+
+impl TextEncoding {
+    //NOTE: `auto`, or a WHATWG label such as `utf-8`, `euc-kr`, `shift_jis` or `utf-16le`.
+    //      The Windows code page names partners use are accepted too.
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.trim().to_lowercase();
+        let label = match label.as_str() {
+            "auto" => return Some(TextEncoding::Auto),
+            "cp949" | "ms949" | "uhc" => "windows-949",
+            "cp932" => "windows-31j",
+            label => label,
+        };
+        Encoding::for_label(label.as_bytes()).map(TextEncoding::Fixed)
+    }
+
+    //NOTE: UTF-16 without a BOM is told by its zero bytes; otherwise valid UTF-8 is UTF-8 and
+    //      anything else is left to chardetng, which knows the legacy CJK encodings.
+    pub fn detect(sample: &[u8], last: bool) -> &'static Encoding {
+        if let Some((encoding, _)) = Encoding::for_bom(sample) {
+            return encoding;
+        }
+        if let Some(encoding) = detect_utf16(sample) {
+            return encoding;
+        }
+        match std::str::from_utf8(sample) {
+            Ok(_) => return UTF_8,
+            Err(e) if e.error_len().is_none() && !last => return UTF_8,
+            Err(_) => {}
+        }
+        let mut detector = EncodingDetector::new();
+        detector.feed(sample, last);
+        detector.guess(None, false)
+    }
+}
+
+impl TranscodeReader {
+    pub fn new<R>(reader: R, encoding: TextEncoding) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        TranscodeReader { state: TranscodeState::Detecting(Box::new(reader), encoding) }
+    }
+
+    fn start(
+        mut reader: Box<dyn Read + Send>,
+        encoding: TextEncoding,
+    ) -> io::Result<Box<dyn Read + Send>> {
+        let encoding = match encoding {
+            TextEncoding::Fixed(encoding) => encoding,
+            TextEncoding::Auto => {
+                let mut sample = Vec::with_capacity(SNIFF_LEN);
+                (&mut reader).take(SNIFF_LEN as u64).read_to_end(&mut sample)?;
+                let encoding = TextEncoding::detect(&sample, sample.len() < SNIFF_LEN);
+                let bom = Encoding::for_bom(&sample).is_some();
+                reader = Box::new(Cursor::new(sample).chain(reader));
+                if encoding == UTF_8 && !bom {
+                    return Ok(Box::new(Utf8Reader::new(reader)));
+                }
+                encoding
+            }
+        };
+        Ok(Box::new(
+            DecodeReaderBytesBuilder::new()
+                .encoding(Some(encoding))
+                .bom_override(true)
+                .strip_bom(true)
+                .build(reader),
+        ))
+    }
+}
+
+impl Read for TranscodeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let TranscodeState::Detecting(..) = self.state {
+            let (reader, encoding) =
+                match std::mem::replace(&mut self.state, TranscodeState::Failed) {
+                    TranscodeState::Detecting(reader, encoding) => (reader, encoding),
+                    _ => unreachable!("state was checked above"),
+                };
+            self.state = TranscodeState::Reading(TranscodeReader::start(reader, encoding)?);
+        }
+        match &mut self.state {
+            TranscodeState::Reading(reader) => reader.read(buf),
+            _ => Err(io::Error::other("decoding failed to start")),
+        }
+    }
+}
+
+impl Utf8Reader {
+    fn new(reader: Box<dyn Read + Send>) -> Self {
+        Utf8Reader { reader, buffer: Vec::new(), valid: 0, offset: 0 }
+    }
+
+    fn malformed(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "input is not valid UTF-8 at byte {}; the encoding was guessed from the first {} \
+                 KiB, so give it explicitly",
+                self.offset,
+                SNIFF_LEN / 1024
+            ),
+        )
+    }
+}
+
+impl Read for Utf8Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while self.valid == 0 {
+            if let Err(e) = std::str::from_utf8(&self.buffer)
+                && e.valid_up_to() == 0
+                && e.error_len().is_some()
+            {
+                return Err(self.malformed());
+            }
+            let mut chunk = [0u8; 8 * 1024];
+            let read = self.reader.read(&mut chunk)?;
+            if read == 0 {
+                if self.buffer.is_empty() {
+                    return Ok(0);
+                }
+                return Err(self.malformed());
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+            self.valid = match std::str::from_utf8(&self.buffer) {
+                Ok(_) => self.buffer.len(),
+                Err(e) => e.valid_up_to(),
+            };
+        }
+        let len = self.valid.min(buf.len());
+        buf[..len].copy_from_slice(&self.buffer[..len]);
+        self.buffer.drain(..len);
+        self.valid -= len;
+        self.offset += len as u64;
+        Ok(len)
+    }
+}
+
+impl TranscodeWriter {
+    pub fn new(
+        mut writer: Box<dyn FinishWrite>,
+        encoding: &'static Encoding,
+        bom: bool,
+    ) -> io::Result<Self> {
+        if bom {
+            match encoding {
+                e if e == UTF_8 => writer.write_all(b"\xEF\xBB\xBF")?,
+                e if e == UTF_16LE => writer.write_all(b"\xFF\xFE")?,
+                e if e == UTF_16BE => writer.write_all(b"\xFE\xFF")?,
+                _ => {}
+            }
+        }
+        Ok(TranscodeWriter { writer, encoding, pending: Vec::new() })
+    }
+
+    fn encode(&mut self, text: &str) -> io::Result<()> {
+        if self.encoding == UTF_8 {
+            return self.writer.write_all(text.as_bytes());
+        }
+        //NOTE: encoding_rs only decodes UTF-16, so it is encoded here.
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            let mut units = vec![0u16; text.len() + 1];
+            let len = convert_utf8_to_utf16(text.as_bytes(), &mut units);
+            let little_endian = self.encoding == UTF_16LE;
+            let bytes: Vec<u8> = units[..len]
+                .iter()
+                .flat_map(
+                    |unit| if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() },
+                )
+                .collect();
+            return self.writer.write_all(&bytes);
+        }
+
+        let mut encoder = self.encoding.new_encoder();
+        let mut output = vec![0u8; text.len() * 2 + 16];
+        let mut input = text;
+        loop {
+            let (result, read, written) =
+                encoder.encode_from_utf8_without_replacement(input, &mut output, true);
+            self.writer.write_all(&output[..written])?;
+            input = &input[read..];
+            match result {
+                EncoderResult::InputEmpty => return Ok(()),
+                EncoderResult::OutputFull => output.resize(output.len() * 2, 0),
+                EncoderResult::Unmappable(c) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{:?} cannot be encoded in {}", c, self.encoding.name()),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+impl Write for TranscodeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        let pending = std::mem::take(&mut self.pending);
+        let (text, rest) = pending.split_at(valid);
+        self.encode(std::str::from_utf8(text).map_err(io::Error::other)?)?;
+        self.pending = rest.to_vec();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl FinishWrite for TranscodeWriter {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "output ends inside a UTF-8 character",
+            ));
+        }
+        self.writer.finish()
+    }
+}
+
+fn detect_utf16(sample: &[u8]) -> Option<&'static Encoding> {
+    let pairs = sample.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let even_zeros = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = sample.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    //NOTE: mostly Latin text has a zero in every other byte; UTF-8 and legacy encodings have none.
+    match (even_zeros * 4 > pairs, odd_zeros * 4 > pairs) {
+        (false, true) => Some(UTF_16LE),
+        (true, false) => Some(UTF_16BE),
+        _ => None,
+    }
+}
@@ -4,6 +4,14 @@ use std::fmt;
 #[derive(Debug, PartialEq)]
 pub enum TabularPortError {
     NotInitialized(String),
+    SheetNotFound(String, Vec<String>),
+    RangeNotFound(String, Vec<String>),
+    EntryNotFound(String, Vec<String>),
+    AmbiguousEntry(String, Vec<String>),
+    InvalidRange(String),
+    InvalidCell(String),
+    UnsupportedFormat(String),
+    ReadFailed(String),
     Unknown(String),
 }
 
@@ -11,6 +19,29 @@ impl fmt::Display for TabularPortError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             TabularPortError::NotInitialized(ref msg) => write!(f, "Not Initialized: {}", msg),
+            TabularPortError::SheetNotFound(ref sheet, ref available) => {
+                write!(f, "Sheet Not Found: {} (available sheets: {})", sheet, available.join(", "))
+            }
+            TabularPortError::RangeNotFound(ref range, ref available) => {
+                write!(f, "Range Not Found: {} (available: {})", range, available.join(", "))
+            }
+            TabularPortError::EntryNotFound(ref entry, ref available) => {
+                write!(
+                    f,
+                    "Entry Not Found: {} (available entries: {})",
+                    entry,
+                    available.join(", ")
+                )
+            }
+            TabularPortError::AmbiguousEntry(ref entry, ref matches) => {
+                write!(f, "Ambiguous Entry: {} (matches: {})", entry, matches.join(", "))
+            }
+            TabularPortError::InvalidRange(ref msg) => write!(f, "Invalid Range: {}", msg),
+            TabularPortError::InvalidCell(ref msg) => write!(f, "Invalid Cell: {}", msg),
+            TabularPortError::UnsupportedFormat(ref msg) => {
+                write!(f, "Unsupported Format: {}", msg)
+            }
+            TabularPortError::ReadFailed(ref msg) => write!(f, "Read Failed: {}", msg),
             TabularPortError::Unknown(ref msg) => write!(f, "Unknown: {}", msg),
         }
     }
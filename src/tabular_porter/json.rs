@@ -0,0 +1,542 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufRead, BufReader, Cursor, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::Deserialize;
+use serde_json::{Map, Number, Value};
+
+use crate::rdb_qry_handler::datatype::{DataType, DataTypeKind, Decimal};
+
+use super::{
+    TabularCursor, TabularNullableRecord, TabularPorter, TabularStringRecord, TabularWriter,
+    WriteOptions,
+    compression::{Compression, DecompressReader, FinishWrite},
+    encoding::{TextEncoding, TranscodeReader},
+    error::TabularPortError,
+    part_path,
+};
+
+pub type JsonRecord = Vec<Option<DataType>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonFormat {
+    Array,
+    Lines,
+}
+
+#[derive(Debug, Clone)]
+pub struct JsonOptions {
+    separator: Option<String>,
+    schema: Option<Vec<(String, DataTypeKind)>>,
+}
+
+//NOTE: reads a JSON array of objects, or JSON Lines, told by whether the input starts with `[`.
+//      Without a schema the columns are the union of the keys of every object, in the order they
+//      are first seen, so the input is read twice; it is reopened rather than held in memory.
+pub struct JsonPorter {
+    source: JsonSource,
+    options: JsonOptions,
+    columns: Vec<String>,
+}
+
+enum JsonSource {
+    File(PathBuf),
+    Bytes(Arc<[u8]>),
+}
+
+struct JsonObjects {
+    reader: BufReader<TranscodeReader>,
+    format: Option<JsonFormat>,
+    read: usize,
+    finished: bool,
+}
+
+//NOTE: `out.json` is written as an array and `out.jsonl` or `out.ndjson` as one object per line.
+//      NULL cells are written as `null`, so the null value of the options is not used.
+pub struct JsonWriter {
+    path: PathBuf,
+    part: usize,
+    format: JsonFormat,
+    compression: Option<Compression>,
+    writer: Option<Box<dyn FinishWrite>>,
+    columns: Vec<String>,
+    written: usize,
+    options: WriteOptions,
+}
+
+impl JsonFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "json" => Some(JsonFormat::Array),
+            "jsonl" | "ndjson" => Some(JsonFormat::Lines),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let (_, path) = Compression::split_path(path);
+        JsonFormat::from_extension(&path.extension()?.to_string_lossy())
+    }
+}
+
+impl JsonOptions {
+    //NOTE: nested objects become columns like `address.city` with the separator, or are kept as
+    //      JSON text without one. A schema fixes the columns and converts each value to its kind.
+    pub fn new(separator: Option<&str>, schema: Option<Vec<(String, DataTypeKind)>>) -> Self {
+        JsonOptions { separator: separator.map(|separator| separator.to_string()), schema }
+    }
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        JsonOptions::new(Some("."), None)
+    }
+}
+
+impl TabularPorter for JsonPorter {
+    fn new<P>(src: P) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        JsonPorter::open(src, JsonOptions::default())
+    }
+}
+
+impl JsonPorter {
+    pub fn open<P>(src: P, options: JsonOptions) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        JsonPorter::from_source(JsonSource::File(src.as_ref().to_path_buf()), options)
+    }
+
+    pub fn from_bytes(
+        bytes: Vec<u8>,
+        options: JsonOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        JsonPorter::from_source(JsonSource::Bytes(bytes.into()), options)
+    }
+
+    //NOTE: the input is read twice, so a stream is buffered first.
+    pub fn from_reader<R>(
+        mut reader: R,
+        options: JsonOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        R: Read,
+    {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        JsonPorter::from_bytes(bytes, options)
+    }
+
+    fn from_source(
+        source: JsonSource,
+        options: JsonOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut porter = JsonPorter { source, options, columns: Vec::new() };
+        porter.columns = match porter.options.schema.as_ref() {
+            Some(schema) => schema.iter().map(|(name, _)| name.clone()).collect(),
+            None => porter.scan_columns()?,
+        };
+        Ok(porter)
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn records(
+        &mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<JsonRecord, TabularPortError>>,
+        Box<dyn std::error::Error>,
+    > {
+        let mut objects = self.objects()?;
+        let porter = &*self;
+        Ok(std::iter::from_fn(move || {
+            let object = objects.next()?;
+            Some(object.and_then(|object| porter.to_record(object, objects.read)))
+        }))
+    }
+
+    fn objects(&self) -> Result<JsonObjects, Box<dyn std::error::Error>> {
+        let reader: DecompressReader = match &self.source {
+            JsonSource::File(path) => DecompressReader::open(path)?,
+            JsonSource::Bytes(bytes) => DecompressReader::new(Cursor::new(bytes.clone()), None),
+        };
+        Ok(JsonObjects {
+            reader: BufReader::new(TranscodeReader::new(reader, TextEncoding::Auto)),
+            format: None,
+            read: 0,
+            finished: false,
+        })
+    }
+
+    fn scan_columns(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut columns = Vec::new();
+        let mut seen = HashSet::new();
+        for object in self.objects()? {
+            for (name, _) in self.flatten(object?) {
+                if seen.insert(name.clone()) {
+                    columns.push(name);
+                }
+            }
+        }
+        Ok(columns)
+    }
+
+    fn flatten(&self, object: Map<String, Value>) -> Vec<(String, Value)> {
+        let mut fields = Vec::with_capacity(object.len());
+        flatten_into(&mut fields, None, object, self.options.separator.as_deref());
+        fields
+    }
+
+    fn to_record(
+        &self,
+        object: Map<String, Value>,
+        record_number: usize,
+    ) -> Result<JsonRecord, TabularPortError> {
+        let mut fields = self.flatten(object);
+        let kinds: Vec<Option<DataTypeKind>> = match self.options.schema.as_ref() {
+            Some(schema) => schema.iter().map(|(_, kind)| Some(*kind)).collect(),
+            None => vec![None; self.columns.len()],
+        };
+        self.columns
+            .iter()
+            .zip(kinds)
+            .map(|(column, kind)| {
+                let value = match fields.iter().position(|(name, _)| name == column) {
+                    Some(index) => fields.swap_remove(index).1,
+                    None => Value::Null,
+                };
+                to_data_type(value, kind).map_err(|value| {
+                    TabularPortError::InvalidCell(format!(
+                        "Record {}: cannot convert {} to {:?} for {}",
+                        record_number,
+                        value,
+                        kind.unwrap_or(DataTypeKind::String),
+                        column
+                    ))
+                })
+            })
+            .collect()
+    }
+}
+
+impl TabularCursor for JsonPorter {
+    fn cursor(
+        &mut self,
+    ) -> Result<impl Iterator<Item = TabularStringRecord>, Box<dyn std::error::Error>> {
+        Ok(self.try_cursor()?.map_while(Result::ok))
+    }
+
+    fn try_cursor(
+        &mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<TabularStringRecord, TabularPortError>>,
+        Box<dyn std::error::Error>,
+    > {
+        Ok(self.try_nullable_cursor()?.map(|record| {
+            record.map(|record| record.into_iter().map(Option::unwrap_or_default).collect())
+        }))
+    }
+
+    fn try_nullable_cursor(
+        &mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<TabularNullableRecord, TabularPortError>>,
+        Box<dyn std::error::Error>,
+    > {
+        Ok(self.records()?.map(|record| {
+            record.map(|record| {
+                record.into_iter().map(|cell| cell.map(|value| value.to_string())).collect()
+            })
+        }))
+    }
+
+    fn headers(&mut self) -> Result<Option<TabularStringRecord>, Box<dyn std::error::Error>> {
+        Ok(Some(self.columns.clone()))
+    }
+}
+
+impl JsonObjects {
+    fn skip_whitespace(&mut self) -> Result<Option<u8>, std::io::Error> {
+        loop {
+            let buf = self.reader.fill_buf()?;
+            match buf.iter().position(|byte| !byte.is_ascii_whitespace()) {
+                Some(position) => {
+                    let byte = buf[position];
+                    self.reader.consume(position);
+                    return Ok(Some(byte));
+                }
+                None if buf.is_empty() => return Ok(None),
+                None => {
+                    let len = buf.len();
+                    self.reader.consume(len);
+                }
+            }
+        }
+    }
+
+    fn next_object(&mut self) -> Result<Option<Map<String, Value>>, Box<dyn std::error::Error>> {
+        let mut next = self.skip_whitespace()?;
+        if self.format.is_none() {
+            self.format = Some(match next {
+                Some(b'[') => {
+                    self.reader.consume(1);
+                    next = self.skip_whitespace()?;
+                    JsonFormat::Array
+                }
+                _ => JsonFormat::Lines,
+            });
+        }
+        match (self.format, next) {
+            (Some(JsonFormat::Array), Some(b']')) => {
+                self.reader.consume(1);
+                if self.skip_whitespace()?.is_some() {
+                    return Err("unexpected content after the array".into());
+                }
+                return Ok(None);
+            }
+            (Some(JsonFormat::Array), Some(b',')) if self.read > 0 => {
+                self.reader.consume(1);
+            }
+            (Some(JsonFormat::Array), None) => {
+                return Err("the array is not closed".into());
+            }
+            (Some(JsonFormat::Array), _) if self.read > 0 => {
+                return Err("expected `,` or `]` after an element".into());
+            }
+            (_, None) => return Ok(None),
+            _ => {}
+        }
+        //NOTE: an object ends at its `}`, so the deserializer reads nothing past it.
+        let mut deserializer = serde_json::Deserializer::from_reader(&mut self.reader);
+        Ok(Some(Map::deserialize(&mut deserializer)?))
+    }
+}
+
+impl Iterator for JsonObjects {
+    type Item = Result<Map<String, Value>, TabularPortError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.next_object() {
+            Ok(Some(object)) => {
+                self.read += 1;
+                Some(Ok(object))
+            }
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(TabularPortError::ReadFailed(format!("Record {}: {}", self.read + 1, e))))
+            }
+        }
+    }
+}
+
+impl TabularWriter for JsonWriter {
+    fn create<P>(dest: P, options: WriteOptions) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let format = JsonFormat::from_path(dest.as_ref()).unwrap_or(JsonFormat::Lines);
+        JsonWriter::create_with_format(dest, format, options)
+    }
+
+    fn write_header(&mut self, header: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        self.columns = header.to_vec();
+        Ok(())
+    }
+
+    fn write_record(
+        &mut self,
+        record: &[Option<DataType>],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut object = Map::with_capacity(record.len());
+        for (i, cell) in record.iter().enumerate() {
+            let name = self.columns.get(i).cloned().unwrap_or_else(|| format!("column{}", i + 1));
+            object.insert(name, self.to_value(cell.as_ref()));
+        }
+        let separator: &[u8] = match (self.format, self.written) {
+            (JsonFormat::Array, 0) => b"\n",
+            (JsonFormat::Array, _) => b",\n",
+            (JsonFormat::Lines, _) => b"",
+        };
+        let terminator: &[u8] = match self.format {
+            JsonFormat::Array => b"",
+            JsonFormat::Lines => b"\n",
+        };
+        let writer = self.writer()?;
+        writer.write_all(separator)?;
+        serde_json::to_writer(&mut *writer, &object)?;
+        writer.write_all(terminator)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    fn next_part(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(writer) = self.writer.take() {
+            self.close_part(writer)?;
+        }
+        self.part += 1;
+        self.written = 0;
+        self.writer = Some(JsonWriter::open_part(
+            &part_path(&self.path, self.part),
+            self.compression,
+            self.format,
+        )?);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(writer) = self.writer.take() {
+            self.close_part(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl JsonWriter {
+    pub fn create_with_format<P>(
+        dest: P,
+        format: JsonFormat,
+        options: WriteOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let (compression, _) = Compression::split_path(dest.as_ref());
+        let writer = JsonWriter::open_part(dest.as_ref(), compression, format)?;
+        Ok(JsonWriter {
+            path: dest.as_ref().to_path_buf(),
+            part: 1,
+            format,
+            compression,
+            writer: Some(writer),
+            columns: Vec::new(),
+            written: 0,
+            options,
+        })
+    }
+
+    fn writer(&mut self) -> Result<&mut Box<dyn FinishWrite>, TabularPortError> {
+        self.writer
+            .as_mut()
+            .ok_or(TabularPortError::NotInitialized("Writer is already finished".to_string()))
+    }
+
+    fn open_part(
+        path: &Path,
+        compression: Option<Compression>,
+        format: JsonFormat,
+    ) -> Result<Box<dyn FinishWrite>, Box<dyn std::error::Error>> {
+        let file = File::create(path)?;
+        let mut writer: Box<dyn FinishWrite> = match compression {
+            Some(compression) => compression.encoder(file)?,
+            None => Box::new(file),
+        };
+        if format == JsonFormat::Array {
+            writer.write_all(b"[")?;
+        }
+        Ok(writer)
+    }
+
+    fn close_part(
+        &self,
+        mut writer: Box<dyn FinishWrite>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.format == JsonFormat::Array {
+            writer.write_all(b"\n]\n")?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+
+    fn to_value(&self, cell: Option<&DataType>) -> Value {
+        match cell {
+            None => Value::Null,
+            Some(DataType::I8(val)) => Value::from(*val),
+            Some(DataType::I16(val)) => Value::from(*val),
+            Some(DataType::I32(val)) => Value::from(*val),
+            Some(DataType::I64(val)) => Value::from(*val),
+            Some(DataType::U8(val)) => Value::from(*val),
+            Some(DataType::U16(val)) => Value::from(*val),
+            Some(DataType::U32(val)) => Value::from(*val),
+            Some(DataType::U64(val)) => Value::from(*val),
+            //NOTE: beyond 64 bits a number is written as text, which JSON parsers read exactly.
+            Some(DataType::I128(val)) => i64::try_from(*val)
+                .map(Value::from)
+                .or_else(|_| u64::try_from(*val).map(Value::from))
+                .unwrap_or_else(|_| Value::String(val.to_string())),
+            Some(DataType::F32(val)) => {
+                Number::from_f64(*val as f64).map_or(Value::Null, Value::Number)
+            }
+            Some(DataType::F64(val)) => Number::from_f64(*val).map_or(Value::Null, Value::Number),
+            //NOTE: so is a decimal that an f64 cannot hold exactly.
+            Some(DataType::Decimal(val)) => Number::from_f64(val.to_f64())
+                .filter(|number| {
+                    Decimal::parse(&number.to_string()).and_then(|d| d.rescale(val.scale()))
+                        == Some(*val)
+                })
+                .map_or(Value::String(val.to_string()), Value::Number),
+            Some(DataType::Bool(val)) => Value::Bool(*val),
+            Some(DataType::String(val)) => Value::String(val.clone()),
+            Some(value) => Value::String(self.options.format_cell(Some(value))),
+        }
+    }
+}
+
+fn flatten_into(
+    fields: &mut Vec<(String, Value)>,
+    prefix: Option<&str>,
+    object: Map<String, Value>,
+    separator: Option<&str>,
+) {
+    for (key, value) in object {
+        let name = match prefix {
+            Some(prefix) => format!("{}{}{}", prefix, separator.unwrap_or_default(), key),
+            None => key,
+        };
+        match (value, separator) {
+            (Value::Object(nested), Some(_)) => {
+                flatten_into(fields, Some(&name), nested, separator)
+            }
+            (value, _) => fields.push((name, value)),
+        }
+    }
+}
+
+//NOTE: without a kind, integers are I64 (U64 above its range), other numbers F64, and arrays or
+//      unflattened objects JSON text. The value is given back when it does not fit the kind.
+fn to_data_type(value: Value, kind: Option<DataTypeKind>) -> Result<Option<DataType>, Value> {
+    let data_type = match (&value, kind) {
+        (Value::Null, _) => return Ok(None),
+        (Value::Bool(val), None | Some(DataTypeKind::Bool)) => Some(DataType::Bool(*val)),
+        (Value::Number(number), None) => number
+            .as_i64()
+            .map(DataType::I64)
+            .or(number.as_u64().map(DataType::U64))
+            .or(number.as_f64().map(DataType::F64)),
+        (Value::String(val), None | Some(DataTypeKind::String)) => {
+            Some(DataType::String(val.clone()))
+        }
+        (Value::Array(_) | Value::Object(_), None | Some(DataTypeKind::String)) => {
+            Some(DataType::String(value.to_string()))
+        }
+        (Value::String(val), Some(kind)) => kind.parse(val),
+        (Value::Array(_) | Value::Object(_), Some(_)) => None,
+        (value, Some(kind)) => kind.parse(&value.to_string()),
+    };
+    data_type.map(Some).ok_or(value)
+}
@@ -1,15 +1,57 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use encoding_rs::{Encoding, UTF_8};
+
+use crate::rdb_qry_handler::datatype::DataType;
+
+use error::TabularPortError;
+
+pub mod async_cursor;
+pub mod compression;
 pub mod csv;
+pub mod encoding;
 pub mod error;
+pub mod json;
+pub mod number_format;
+pub mod spreadsheet;
 pub mod xslx;
+pub mod zip;
 
 pub type TabularStringRecord = Vec<String>;
+pub type TabularNullableRecord = Vec<Option<String>>;
 
 pub trait TabularCursor {
     fn cursor(
         &mut self,
     ) -> Result<impl Iterator<Item = TabularStringRecord>, Box<dyn std::error::Error>>;
+
+    //NOTE: like `cursor`, but a read failing part way is yielded as an error instead of ending
+    //      the records early.
+    fn try_cursor(
+        &mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<TabularStringRecord, TabularPortError>>,
+        Box<dyn std::error::Error>,
+    > {
+        Ok(self.cursor()?.map(Ok))
+    }
+
+    //NOTE: like `try_cursor`, with NULL cells as `None`. Only sources that tell NULL from empty
+    //      text, such as JSON, have any.
+    fn try_nullable_cursor(
+        &mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<TabularNullableRecord, TabularPortError>>,
+        Box<dyn std::error::Error>,
+    > {
+        Ok(self
+            .try_cursor()?
+            .map(|record| record.map(|record| record.into_iter().map(Some).collect())))
+    }
+
+    fn headers(&mut self) -> Result<Option<TabularStringRecord>, Box<dyn std::error::Error>> {
+        Ok(None)
+    }
 }
 
 pub trait TabularPorter {
@@ -18,3 +60,93 @@ pub trait TabularPorter {
         P: AsRef<Path>,
         Self: Sized;
 }
+
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    null_value: String,
+    date_format: Option<String>,
+    encoding: Option<&'static Encoding>,
+    bom: bool,
+}
+
+impl WriteOptions {
+    pub fn new(null_value: &str, date_format: Option<&str>) -> Self {
+        WriteOptions {
+            null_value: null_value.to_string(),
+            date_format: date_format.map(|format| format.to_string()),
+            encoding: None,
+            bom: false,
+        }
+    }
+
+    //NOTE: applies to text output only; CSV is written as UTF-8 without a BOM by default.
+    pub fn set_encoding(&mut self, encoding: &'static Encoding, bom: bool) {
+        self.encoding = Some(encoding);
+        self.bom = bom;
+    }
+
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding.unwrap_or(UTF_8)
+    }
+
+    pub fn bom(&self) -> bool {
+        self.bom
+    }
+
+    pub fn null_value(&self) -> &str {
+        &self.null_value
+    }
+
+    pub fn date_format(&self) -> Option<&str> {
+        self.date_format.as_deref()
+    }
+
+    pub fn format_cell(&self, cell: Option<&DataType>) -> String {
+        match (cell, self.date_format.as_ref()) {
+            (None, _) => self.null_value.clone(),
+            (Some(DataType::DateTime(dt)), Some(format)) => dt.format(format).to_string(),
+            (Some(value), _) => value.to_string(),
+        }
+    }
+}
+
+pub trait TabularWriter {
+    fn create<P>(dest: P, options: WriteOptions) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+        Self: Sized;
+
+    fn write_header(&mut self, header: &[String]) -> Result<(), Box<dyn std::error::Error>>;
+
+    fn write_record(
+        &mut self,
+        record: &[Option<DataType>],
+    ) -> Result<(), Box<dyn std::error::Error>>;
+
+    fn next_part(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+    fn finish(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+    fn max_rows_per_part(&self) -> Option<usize> {
+        None
+    }
+}
+
+//NOTE: `out.csv` becomes `out_2.csv`, `out.csv.gz` becomes `out_2.csv.gz` and `sales.2024.csv`
+//      becomes `sales.2024_2.csv`.
+pub(crate) fn part_path(path: &Path, part: usize) -> PathBuf {
+    if part <= 1 {
+        return path.to_path_buf();
+    }
+    let (compression, inner) = compression::Compression::split_path(path);
+    let extension = |path: &Path| path.extension().map(|ext| format!(".{}", ext.to_string_lossy()));
+    let stem = inner.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let part_name = format!(
+        "{}_{}{}{}",
+        stem,
+        part,
+        extension(&inner).unwrap_or_default(),
+        compression.and(extension(path)).unwrap_or_default()
+    );
+    path.with_file_name(part_name)
+}
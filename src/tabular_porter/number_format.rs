@@ -0,0 +1,320 @@
+use quick_xml::{
+    Reader,
+    escape::unescape,
+    events::{BytesStart, Event},
+};
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Seek},
+};
+use zip::{ZipArchive, result::ZipError};
+
+use super::spreadsheet::{CellPosition, SpreadsheetPorter};
+
+pub type CellNumberFormats = HashMap<CellPosition, NumberFormat>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateKind {
+    Date,
+    Time,
+    DateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberFormat {
+    Date(DateKind),
+    Decimals(usize),
+}
+
+//NOTE: calamine only tells whether a cell holds a date or a duration, so the number formats
+//      of XLSX cells are read from the styles to know whether a cell shows a date, a time or
+//      both, and how many decimals a number shows.
+pub struct XlsxNumberFormats<R> {
+    archive: ZipArchive<R>,
+    styles: Vec<Option<NumberFormat>>,
+    sheet_paths: HashMap<String, String>,
+}
+
+impl DateKind {
+    //NOTE: Excel's built-in formats by id; 27 to 36 and 50 to 58 are the East Asian ones.
+    pub fn from_builtin(id: u32) -> Option<Self> {
+        match id {
+            14..=17 | 27..=31 | 36 | 50..=54 | 57 | 58 => Some(DateKind::Date),
+            18..=21 | 32..=35 | 45..=47 | 55 | 56 => Some(DateKind::Time),
+            22 => Some(DateKind::DateTime),
+            _ => None,
+        }
+    }
+
+    //NOTE: only the first section counts. Quoted text, escaped characters and bracketed parts
+    //      such as colours or locales are skipped, and `m` next to `h` or `s` means minutes.
+    pub fn from_format_code(code: &str) -> Option<Self> {
+        let chars: Vec<char> = code.chars().collect();
+        let mut letters = Vec::new();
+        let mut index = 0;
+        while index < chars.len() {
+            let c = chars[index].to_ascii_lowercase();
+            match c {
+                ';' => break,
+                '\\' | '_' | '*' => index += 1,
+                '"' => {
+                    index += 1;
+                    while index < chars.len() && chars[index] != '"' {
+                        index += 1;
+                    }
+                }
+                '[' => {
+                    let Some(end) = chars[index..].iter().position(|c| *c == ']') else {
+                        break;
+                    };
+                    let elapsed: String = chars[index + 1..index + end].iter().collect();
+                    if !elapsed.is_empty()
+                        && elapsed
+                            .chars()
+                            .all(|c| matches!(c.to_ascii_lowercase(), 'h' | 'm' | 's'))
+                    {
+                        letters.push(elapsed.chars().next().unwrap().to_ascii_lowercase());
+                    }
+                    index += end;
+                }
+                'a' => {
+                    let rest: String = chars[index..].iter().take(5).collect();
+                    if rest.to_lowercase().starts_with("am/pm") {
+                        index += 4;
+                    } else if rest.to_lowercase().starts_with("a/p") {
+                        index += 2;
+                    }
+                }
+                'y' | 'm' | 'd' | 'h' | 's' => letters.push(c),
+                _ => {}
+            }
+            index += 1;
+        }
+
+        letters.dedup();
+        let mut date = false;
+        let mut time = false;
+        for (position, letter) in letters.iter().enumerate() {
+            match letter {
+                'y' | 'd' => date = true,
+                'h' | 's' => time = true,
+                _ => {
+                    let after_hours = position > 0 && letters[position - 1] == 'h';
+                    let before_seconds = letters.get(position + 1) == Some(&'s');
+                    if after_hours || before_seconds {
+                        time = true;
+                    } else {
+                        date = true;
+                    }
+                }
+            }
+        }
+        match (date, time) {
+            (true, true) => Some(DateKind::DateTime),
+            (true, false) => Some(DateKind::Date),
+            (false, true) => Some(DateKind::Time),
+            (false, false) => None,
+        }
+    }
+}
+
+impl NumberFormat {
+    pub fn from_builtin(id: u32) -> Option<Self> {
+        match id {
+            2 | 4 | 7 | 8 | 10 | 11 | 39 | 40 | 43 | 44 => Some(NumberFormat::Decimals(2)),
+            48 => Some(NumberFormat::Decimals(1)),
+            _ => DateKind::from_builtin(id).map(NumberFormat::Date),
+        }
+    }
+
+    //NOTE: the decimals are the digit placeholders after the first decimal point of the first
+    //      section; formats without any are left out.
+    pub fn from_format_code(code: &str) -> Option<Self> {
+        if let Some(kind) = DateKind::from_format_code(code) {
+            return Some(NumberFormat::Date(kind));
+        }
+        let mut chars = code.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                ';' => break,
+                '\\' | '_' | '*' => {
+                    chars.next();
+                }
+                '"' => {
+                    chars.by_ref().find(|c| *c == '"');
+                }
+                '[' => {
+                    chars.by_ref().find(|c| *c == ']');
+                }
+                '.' => {
+                    let decimals = chars.take_while(|c| matches!(c, '0' | '#' | '?')).count();
+                    return (decimals > 0).then_some(NumberFormat::Decimals(decimals));
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+impl<R> XlsxNumberFormats<R>
+where
+    R: Read + Seek,
+{
+    pub fn new(reader: R) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut archive = ZipArchive::new(reader)?;
+        let styles = match archive.by_name("xl/styles.xml") {
+            Ok(file) => read_styles(BufReader::new(file))?,
+            Err(ZipError::FileNotFound) => Vec::new(),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let sheet_paths = if styles.iter().any(Option::is_some) {
+            read_sheet_paths(&mut archive)?
+        } else {
+            HashMap::new()
+        };
+        Ok(XlsxNumberFormats { archive, styles, sheet_paths })
+    }
+
+    //NOTE: returns the number format of every cell of the sheet styled with a date, time or
+    //      decimal format.
+    pub fn cell_formats(
+        &mut self,
+        sheet_name: &str,
+    ) -> Result<CellNumberFormats, Box<dyn std::error::Error>> {
+        let mut cell_formats = CellNumberFormats::new();
+        let Some(path) = self.sheet_paths.get(sheet_name) else {
+            return Ok(cell_formats);
+        };
+        let mut reader = Reader::from_reader(BufReader::new(self.archive.by_name(path)?));
+        let mut buf = Vec::new();
+        let mut position: CellPosition = (0, 0);
+        let mut next_row = 0;
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"row" => {
+                    let row = attribute(&e, b"r")?.and_then(|r| r.parse::<u32>().ok());
+                    position = (row.and_then(|row| row.checked_sub(1)).unwrap_or(next_row), 0);
+                    next_row = position.0 + 1;
+                }
+                Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"c" => {
+                    if let Some(cell) =
+                        attribute(&e, b"r")?.and_then(|r| SpreadsheetPorter::parse_cell(&r))
+                    {
+                        position = cell;
+                    }
+                    let style = attribute(&e, b"s")?.and_then(|s| s.parse::<usize>().ok());
+                    if let Some(Some(format)) = style.and_then(|style| self.styles.get(style)) {
+                        cell_formats.insert(position, *format);
+                    }
+                    position.1 += 1;
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+        Ok(cell_formats)
+    }
+}
+
+fn attribute(
+    element: &BytesStart,
+    name: &[u8],
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    for attribute in element.attributes().with_checks(false) {
+        let attribute = attribute?;
+        if attribute.key.local_name().as_ref() == name {
+            let value = std::str::from_utf8(&attribute.value)?;
+            return Ok(Some(unescape(value)?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+//NOTE: maps each cell style (`cellXfs` entry) to its number format.
+fn read_styles<B>(source: B) -> Result<Vec<Option<NumberFormat>>, Box<dyn std::error::Error>>
+where
+    B: BufRead,
+{
+    let mut reader = Reader::from_reader(source);
+    let mut buf = Vec::new();
+    let mut custom = HashMap::new();
+    let mut styles = Vec::new();
+    let mut in_cell_styles = false;
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"numFmt" => {
+                let id = attribute(&e, b"numFmtId")?.and_then(|id| id.parse::<u32>().ok());
+                if let (Some(id), Some(code)) = (id, attribute(&e, b"formatCode")?) {
+                    custom.insert(id, NumberFormat::from_format_code(&code));
+                }
+            }
+            Event::Start(e) if e.local_name().as_ref() == b"cellXfs" => in_cell_styles = true,
+            Event::End(e) if e.local_name().as_ref() == b"cellXfs" => in_cell_styles = false,
+            Event::Start(e) | Event::Empty(e)
+                if in_cell_styles && e.local_name().as_ref() == b"xf" =>
+            {
+                let id = attribute(&e, b"numFmtId")?.and_then(|id| id.parse::<u32>().ok());
+                styles.push(id.and_then(|id| match custom.get(&id) {
+                    Some(format) => *format,
+                    None => NumberFormat::from_builtin(id),
+                }));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(styles)
+}
+
+fn read_sheet_paths<R>(
+    archive: &mut ZipArchive<R>,
+) -> Result<HashMap<String, String>, Box<dyn std::error::Error>>
+where
+    R: Read + Seek,
+{
+    let mut targets = HashMap::new();
+    let mut reader =
+        Reader::from_reader(BufReader::new(archive.by_name("xl/_rels/workbook.xml.rels")?));
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                if let (Some(id), Some(target)) = (attribute(&e, b"Id")?, attribute(&e, b"Target")?)
+                {
+                    let target = match target.strip_prefix('/') {
+                        Some(absolute) => absolute.to_string(),
+                        None => format!("xl/{}", target),
+                    };
+                    targets.insert(id, target);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    drop(reader);
+
+    let mut sheet_paths = HashMap::new();
+    buf.clear();
+    let mut reader = Reader::from_reader(BufReader::new(archive.by_name("xl/workbook.xml")?));
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"sheet" => {
+                if let (Some(name), Some(id)) = (attribute(&e, b"name")?, attribute(&e, b"id")?)
+                    && let Some(target) = targets.get(&id)
+                {
+                    sheet_paths.insert(name, target.clone());
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(sheet_paths)
+}
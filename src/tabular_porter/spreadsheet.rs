@@ -0,0 +1,706 @@
+use calamine::{Data, Ods, Range, Reader, Rows, Sheets, Xls, Xlsb, Xlsx};
+use chrono::Datelike;
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read, Seek, SeekFrom},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::{
+    TabularCursor, TabularPorter, TabularStringRecord,
+    error::TabularPortError,
+    number_format::{CellNumberFormats, DateKind, NumberFormat, XlsxNumberFormats},
+};
+
+pub type CellPosition = (u32, u32);
+
+//NOTE: Excel shows negative serials and years past 9999 as `####`.
+const MAX_EXCEL_YEAR: i32 = 9999;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorCellPolicy {
+    #[default]
+    Text,
+    Empty,
+    Fail,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CellFormatOptions {
+    date_time_format: Option<String>,
+    date_format: Option<String>,
+    time_format: Option<String>,
+    float_precision: Option<usize>,
+    error_cells: ErrorCellPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpreadsheetFormat {
+    Xls,
+    Xlsx,
+    Xlsb,
+    Ods,
+}
+
+pub trait SpreadsheetSource: Read + Seek + Send {}
+
+impl<T> SpreadsheetSource for T where T: Read + Seek + Send {}
+
+//NOTE: lets calamine and the XLSX number format reader share one source, each reading at
+//      its own position.
+#[derive(Clone)]
+pub struct SharedSource {
+    source: Arc<Mutex<Box<dyn SpreadsheetSource>>>,
+    position: u64,
+}
+
+pub struct SpreadsheetPorter {
+    workbook: Sheets<Box<dyn SpreadsheetSource>>,
+    number_formats: Option<XlsxNumberFormats<SharedSource>>,
+    range_data: Option<Range<Data>>,
+    cell_formats: CellNumberFormats,
+    header_row: bool,
+    table_columns: Option<TabularStringRecord>,
+    cell_format: CellFormatOptions,
+}
+
+pub struct SpreadsheetSheet {
+    range: Range<Data>,
+    cell_formats: CellNumberFormats,
+    header_row: bool,
+    cell_format: CellFormatOptions,
+}
+
+pub struct SpreadsheetSheets<'a> {
+    porter: &'a mut SpreadsheetPorter,
+    names: std::vec::IntoIter<String>,
+}
+
+pub struct SpreadsheetRecordCursor<'a> {
+    row: Rows<'a, Data>,
+    position: CellPosition,
+    cell_format: &'a CellFormatOptions,
+    cell_formats: &'a CellNumberFormats,
+}
+
+impl TabularPorter for SpreadsheetPorter {
+    fn new<P>(src: P) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let format = SpreadsheetFormat::detect(src.as_ref())?;
+        SpreadsheetPorter::open(src, format)
+    }
+}
+
+impl SpreadsheetFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "xls" | "xla" => Some(SpreadsheetFormat::Xls),
+            "xlsx" | "xlsm" | "xlam" => Some(SpreadsheetFormat::Xlsx),
+            "xlsb" => Some(SpreadsheetFormat::Xlsb),
+            "ods" => Some(SpreadsheetFormat::Ods),
+            _ => None,
+        }
+    }
+
+    pub fn detect(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let by_extension = path
+            .extension()
+            .and_then(|extension| SpreadsheetFormat::from_extension(&extension.to_string_lossy()));
+        let mut reader = BufReader::new(File::open(path)?);
+        SpreadsheetFormat::detect_reader(&mut reader, by_extension)?
+            .ok_or(Box::new(TabularPortError::UnsupportedFormat(path.display().to_string())))
+    }
+
+    //NOTE: legacy .xls files are OLE compound documents; the other formats are zip archives,
+    //      told apart by `hint` or, failing that, by trying each reader. The reader is left at
+    //      the position it was given at.
+    pub fn detect_reader<R>(
+        reader: &mut R,
+        hint: Option<SpreadsheetFormat>,
+    ) -> Result<Option<Self>, Box<dyn std::error::Error>>
+    where
+        R: Read + Seek,
+    {
+        let start = reader.stream_position()?;
+        let mut magic = Vec::with_capacity(4);
+        reader.by_ref().take(4).read_to_end(&mut magic)?;
+        reader.seek(SeekFrom::Start(start))?;
+
+        let format = match (&magic[..], hint) {
+            ([0xD0, 0xCF, 0x11, 0xE0], _) => Some(SpreadsheetFormat::Xls),
+            ([b'P', b'K', 3, 4], Some(format)) if format != SpreadsheetFormat::Xls => Some(format),
+            ([b'P', b'K', 3, 4], _) => {
+                let format = if Xlsx::new(&mut *reader).is_ok() {
+                    Some(SpreadsheetFormat::Xlsx)
+                } else if Xlsb::new(&mut *reader).is_ok() {
+                    Some(SpreadsheetFormat::Xlsb)
+                } else if Ods::new(&mut *reader).is_ok() {
+                    Some(SpreadsheetFormat::Ods)
+                } else {
+                    None
+                };
+                reader.seek(SeekFrom::Start(start))?;
+                format
+            }
+            _ => None,
+        };
+        Ok(format)
+    }
+}
+
+impl SpreadsheetPorter {
+    pub fn open<P>(src: P, format: SpreadsheetFormat) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let file = BufReader::new(File::open(src)?);
+        SpreadsheetPorter::from_reader_with_format(file, format)
+    }
+
+    pub fn from_reader<R>(mut reader: R) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        R: SpreadsheetSource + 'static,
+    {
+        let format = SpreadsheetFormat::detect_reader(&mut reader, None)?.ok_or(
+            TabularPortError::UnsupportedFormat("data is not a known spreadsheet".to_string()),
+        )?;
+        SpreadsheetPorter::from_reader_with_format(reader, format)
+    }
+
+    pub fn from_reader_with_format<R>(
+        reader: R,
+        format: SpreadsheetFormat,
+    ) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        R: SpreadsheetSource + 'static,
+    {
+        let reader: Box<dyn SpreadsheetSource> = Box::new(reader);
+        let mut number_formats = None;
+        let workbook = match format {
+            SpreadsheetFormat::Xls => Sheets::Xls(Xls::new(reader)?),
+            SpreadsheetFormat::Xlsx => {
+                let source = SharedSource::new(reader);
+                number_formats = Some(XlsxNumberFormats::new(source.clone())?);
+                let reader: Box<dyn SpreadsheetSource> = Box::new(source);
+                Sheets::Xlsx(Xlsx::new(reader)?)
+            }
+            SpreadsheetFormat::Xlsb => Sheets::Xlsb(Xlsb::new(reader)?),
+            SpreadsheetFormat::Ods => Sheets::Ods(Ods::new(reader)?),
+        };
+        Ok(SpreadsheetPorter {
+            workbook,
+            number_formats,
+            range_data: None,
+            cell_formats: CellNumberFormats::new(),
+            header_row: false,
+            table_columns: None,
+            cell_format: CellFormatOptions::default(),
+        })
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Box<dyn std::error::Error>> {
+        SpreadsheetPorter::from_reader(Cursor::new(bytes))
+    }
+
+    //NOTE: spreadsheets are read at random positions, so the whole stream is buffered first.
+    pub async fn from_async_reader<R>(mut reader: R) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        SpreadsheetPorter::from_bytes(bytes)
+    }
+
+    pub fn format(&self) -> SpreadsheetFormat {
+        match self.workbook {
+            Sheets::Xls(_) => SpreadsheetFormat::Xls,
+            Sheets::Xlsx(_) => SpreadsheetFormat::Xlsx,
+            Sheets::Xlsb(_) => SpreadsheetFormat::Xlsb,
+            Sheets::Ods(_) => SpreadsheetFormat::Ods,
+        }
+    }
+
+    pub fn sheet_names(&self) -> Vec<String> {
+        self.workbook.sheet_names()
+    }
+
+    pub fn init_range(&mut self, sheet_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (range, cell_formats) = self.sheet_range(sheet_name)?;
+        self.range_data = Some(range);
+        self.cell_formats = cell_formats;
+        self.table_columns = None;
+        Ok(())
+    }
+
+    //NOTE: only XLSX workbooks carry Excel Tables.
+    pub fn table_names(&mut self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        match &mut self.workbook {
+            Sheets::Xlsx(workbook) => {
+                workbook.load_tables()?;
+                Ok(workbook.table_names().into_iter().cloned().collect())
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    pub fn defined_names(&self) -> Vec<String> {
+        self.workbook.defined_names().iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    //NOTE: the header row of an Excel Table is always used as the headers, whatever
+    //      `set_header_row` says.
+    pub fn init_table(&mut self, table_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let table_names = self.table_names()?;
+        if !table_names.iter().any(|name| name == table_name) {
+            return Err(Box::new(TabularPortError::RangeNotFound(
+                table_name.to_string(),
+                table_names,
+            )));
+        }
+        let table = match &mut self.workbook {
+            Sheets::Xlsx(workbook) => workbook.table_by_name(table_name)?,
+            _ => unreachable!("only XLSX workbooks list tables"),
+        };
+        self.cell_formats = self.cell_formats(table.sheet_name())?;
+        self.table_columns = Some(table.columns().to_vec());
+        self.range_data = Some(table.into());
+        Ok(())
+    }
+
+    pub fn init_defined_name(
+        &mut self,
+        defined_name: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let reference = self
+            .workbook
+            .defined_names()
+            .iter()
+            .find(|(name, _)| name == defined_name)
+            .map(|(_, reference)| reference.clone())
+            .ok_or(TabularPortError::RangeNotFound(
+                defined_name.to_string(),
+                self.defined_names(),
+            ))?;
+        self.init_cell_range(&reference)
+    }
+
+    //NOTE: takes an A1 style reference such as `Sheet1!B4:H200`, `'My Sheet'!$A$1:$C$9` or
+    //      `Sheet1!C3`; rows and columns outside the used area read as empty cells.
+    pub fn init_cell_range(&mut self, reference: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (sheet_name, start, end) = SpreadsheetPorter::parse_reference(reference)
+            .ok_or(TabularPortError::InvalidRange(reference.to_string()))?;
+        let (range, cell_formats) = self.sheet_range(&sheet_name)?;
+        self.range_data = Some(range.range(start, end));
+        self.cell_formats = cell_formats;
+        self.table_columns = None;
+        Ok(())
+    }
+
+    //NOTE: resolves `name` as a sheet, an Excel Table, a defined name or an A1 reference,
+    //      in that order.
+    pub fn init_named(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if self.sheet_names().iter().any(|sheet| sheet == name) {
+            return self.init_range(name);
+        }
+        if self.table_names()?.iter().any(|table| table == name) {
+            return self.init_table(name);
+        }
+        if self.defined_names().iter().any(|defined| defined == name) {
+            return self.init_defined_name(name);
+        }
+        if name.contains('!') {
+            return self.init_cell_range(name);
+        }
+
+        let mut available = self.sheet_names();
+        available.extend(self.table_names()?);
+        available.extend(self.defined_names());
+        Err(Box::new(TabularPortError::RangeNotFound(name.to_string(), available)))
+    }
+
+    pub fn init_range_by_index(&mut self, index: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let sheet_names = self.sheet_names();
+        let sheet_name = sheet_names
+            .get(index)
+            .ok_or(TabularPortError::SheetNotFound(format!("#{}", index), sheet_names.clone()))?;
+        self.init_range(sheet_name)
+    }
+
+    //NOTE: when set, the first row of the range is returned by `headers` instead of the cursor.
+    pub fn set_header_row(&mut self, header_row: bool) {
+        self.header_row = header_row;
+    }
+
+    pub fn set_cell_format(&mut self, cell_format: CellFormatOptions) {
+        self.cell_format = cell_format;
+    }
+
+    //NOTE: each sheet is loaded when the iterator reaches it and uses the current header setting.
+    pub fn sheets(&mut self) -> SpreadsheetSheets<'_> {
+        let names = self.sheet_names().into_iter();
+        SpreadsheetSheets { porter: self, names }
+    }
+
+    fn parse_reference(reference: &str) -> Option<(String, CellPosition, CellPosition)> {
+        let (sheet_name, area) = reference.trim().trim_start_matches('=').rsplit_once('!')?;
+        let sheet_name = match sheet_name.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+            Some(quoted) => quoted.replace("''", "'"),
+            None => sheet_name.to_string(),
+        };
+        let (start, end) = match area.split_once(':') {
+            Some((start, end)) => (start, end),
+            None => (area, area),
+        };
+        let start = SpreadsheetPorter::parse_cell(start)?;
+        let end = SpreadsheetPorter::parse_cell(end)?;
+        if start.0 > end.0 || start.1 > end.1 {
+            return None;
+        }
+        Some((sheet_name, start, end))
+    }
+
+    //NOTE: converts `B4` or `$B$4` into the zero based (row, column) position.
+    pub(crate) fn parse_cell(cell: &str) -> Option<CellPosition> {
+        let cell = cell.replace('$', "");
+        let split = cell.find(|c: char| c.is_ascii_digit())?;
+        let (letters, digits) = cell.split_at(split);
+        if letters.is_empty() || letters.len() > 3 {
+            return None;
+        }
+        let mut column = 0u32;
+        for letter in letters.chars() {
+            if !letter.is_ascii_alphabetic() {
+                return None;
+            }
+            column = column * 26 + (letter.to_ascii_uppercase() as u32 - 'A' as u32 + 1);
+        }
+        let row = digits.parse::<u32>().ok().filter(|row| *row >= 1)?;
+        Some((row - 1, column - 1))
+    }
+
+    fn sheet_range(
+        &mut self,
+        sheet_name: &str,
+    ) -> Result<(Range<Data>, CellNumberFormats), Box<dyn std::error::Error>> {
+        let sheet_names = self.sheet_names();
+        if !sheet_names.iter().any(|name| name == sheet_name) {
+            return Err(Box::new(TabularPortError::SheetNotFound(
+                sheet_name.to_string(),
+                sheet_names,
+            )));
+        }
+        let range = self.workbook.worksheet_range(sheet_name)?;
+        Ok((range, self.cell_formats(sheet_name)?))
+    }
+
+    fn cell_formats(
+        &mut self,
+        sheet_name: &str,
+    ) -> Result<CellNumberFormats, Box<dyn std::error::Error>> {
+        match &mut self.number_formats {
+            Some(number_formats) => number_formats.cell_formats(sheet_name),
+            None => Ok(CellNumberFormats::new()),
+        }
+    }
+}
+
+impl TabularCursor for SpreadsheetPorter {
+    fn cursor(
+        &mut self,
+    ) -> Result<impl Iterator<Item = TabularStringRecord>, Box<dyn std::error::Error>> {
+        let range = self
+            .range_data
+            .as_ref()
+            .ok_or(TabularPortError::NotInitialized("Cannot get Range Data".to_string()))?;
+        let header_row = self.header_row && self.table_columns.is_none();
+        Ok(SpreadsheetRecordCursor::new(range, header_row, &self.cell_format, &self.cell_formats)?)
+    }
+
+    fn headers(&mut self) -> Result<Option<TabularStringRecord>, Box<dyn std::error::Error>> {
+        let range = self
+            .range_data
+            .as_ref()
+            .ok_or(TabularPortError::NotInitialized("Cannot get Range Data".to_string()))?;
+        if self.table_columns.is_some() {
+            return Ok(self.table_columns.clone());
+        }
+        Ok(SpreadsheetRecordCursor::headers(
+            range,
+            self.header_row,
+            &self.cell_format,
+            &self.cell_formats,
+        )?)
+    }
+}
+
+impl Iterator for SpreadsheetSheets<'_> {
+    type Item = Result<(String, SpreadsheetSheet), Box<dyn std::error::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let name = self.names.next()?;
+        let sheet = self.porter.sheet_range(&name).map(|(range, cell_formats)| SpreadsheetSheet {
+            range,
+            cell_formats,
+            header_row: self.porter.header_row,
+            cell_format: self.porter.cell_format.clone(),
+        });
+        Some(sheet.map(|sheet| (name, sheet)))
+    }
+}
+
+impl TabularCursor for SpreadsheetSheet {
+    fn cursor(
+        &mut self,
+    ) -> Result<impl Iterator<Item = TabularStringRecord>, Box<dyn std::error::Error>> {
+        Ok(SpreadsheetRecordCursor::new(
+            &self.range,
+            self.header_row,
+            &self.cell_format,
+            &self.cell_formats,
+        )?)
+    }
+
+    fn headers(&mut self) -> Result<Option<TabularStringRecord>, Box<dyn std::error::Error>> {
+        Ok(SpreadsheetRecordCursor::headers(
+            &self.range,
+            self.header_row,
+            &self.cell_format,
+            &self.cell_formats,
+        )?)
+    }
+}
+
+impl CellFormatOptions {
+    //NOTE: formats are strftime style and default to ISO 8601.
+    pub fn new(
+        date_time_format: Option<&str>,
+        date_format: Option<&str>,
+        time_format: Option<&str>,
+        float_precision: Option<usize>,
+        error_cells: ErrorCellPolicy,
+    ) -> Self {
+        CellFormatOptions {
+            date_time_format: date_time_format.map(|format| format.to_string()),
+            date_format: date_format.map(|format| format.to_string()),
+            time_format: time_format.map(|format| format.to_string()),
+            float_precision,
+            error_cells,
+        }
+    }
+
+    pub fn format_cell(&self, data: &Data) -> Result<String, TabularPortError> {
+        self.format_cell_as(data, None)
+    }
+
+    //NOTE: `number_format` comes from the cell's style. Without one (XLS, XLSB and ODS, whose
+    //      formats calamine does not expose) a serial below 1 is taken as a time and a whole
+    //      serial as a date, and a number shows only the decimals it needs.
+    pub fn format_cell_as(
+        &self,
+        data: &Data,
+        number_format: Option<NumberFormat>,
+    ) -> Result<String, TabularPortError> {
+        let text = match data {
+            Data::Int(i) => i.to_string(),
+            Data::Float(f) => match (self.float_precision, number_format) {
+                (Some(precision), _) => format!("{:.*}", precision, f),
+                //NOTE: pads the shortest form rather than rounding, so no digit is lost.
+                (None, Some(NumberFormat::Decimals(decimals))) => {
+                    let mut text = f.to_string();
+                    let shown = text.split_once('.').map_or(0, |(_, fraction)| fraction.len());
+                    if shown < decimals {
+                        if shown == 0 {
+                            text.push('.');
+                        }
+                        text.push_str(&"0".repeat(decimals - shown));
+                    }
+                    text
+                }
+                (None, _) => f.to_string(),
+            },
+            Data::String(s) => s.to_string(),
+            Data::Bool(b) => b.to_string(),
+            Data::DateTime(dt) if dt.is_duration() => {
+                let duration = dt.as_duration().unwrap_or_default();
+                let seconds = duration.num_seconds();
+                format!(
+                    "{}{}:{:02}:{:02}",
+                    if seconds < 0 { "-" } else { "" },
+                    seconds.abs() / 3600,
+                    seconds.abs() / 60 % 60,
+                    seconds.abs() % 60
+                )
+            }
+            Data::DateTime(dt) => {
+                let serial = dt.as_f64();
+                let date_time = dt
+                    .as_datetime()
+                    .filter(|date_time| serial >= 0.0 && date_time.year() <= MAX_EXCEL_YEAR)
+                    .ok_or(TabularPortError::InvalidCell(format!(
+                        "{} is not a valid date serial",
+                        serial
+                    )))?;
+                let date_kind = match number_format {
+                    Some(NumberFormat::Date(kind)) => kind,
+                    _ if serial < 1.0 => DateKind::Time,
+                    _ if serial.fract() == 0.0 => DateKind::Date,
+                    _ => DateKind::DateTime,
+                };
+                let (format, default) = match date_kind {
+                    DateKind::Time => (self.time_format.as_deref(), "%H:%M:%S%.f"),
+                    DateKind::Date => (self.date_format.as_deref(), "%Y-%m-%d"),
+                    DateKind::DateTime => {
+                        (self.date_time_format.as_deref(), "%Y-%m-%dT%H:%M:%S%.f")
+                    }
+                };
+                date_time.format(format.unwrap_or(default)).to_string()
+            }
+            Data::DateTimeIso(dt) => dt.to_string(),
+            Data::DurationIso(d) => d.to_string(),
+            Data::Error(e) => match self.error_cells {
+                ErrorCellPolicy::Text => e.to_string(),
+                ErrorCellPolicy::Empty => "".to_string(),
+                ErrorCellPolicy::Fail => {
+                    return Err(TabularPortError::InvalidCell(format!("Error value {}", e)));
+                }
+            },
+            Data::Empty => "".to_string(),
+        };
+        Ok(text)
+    }
+}
+
+impl<'a> SpreadsheetRecordCursor<'a> {
+    //NOTE: the whole range is already in memory, so bad cells are reported up front instead
+    //      of ending the iteration early.
+    fn new(
+        range: &'a Range<Data>,
+        header_row: bool,
+        cell_format: &'a CellFormatOptions,
+        cell_formats: &'a CellNumberFormats,
+    ) -> Result<Self, TabularPortError> {
+        let (start_row, start_column) = range.start().unwrap_or_default();
+        for (row, column, data) in range.used_cells() {
+            let position = (start_row + row as u32, start_column + column as u32);
+            format_cell_at(cell_format, data, position, cell_formats)?;
+        }
+
+        let mut cursor = SpreadsheetRecordCursor {
+            row: range.rows(),
+            position: (start_row, start_column),
+            cell_format,
+            cell_formats,
+        };
+        if header_row {
+            cursor.row.next();
+            cursor.position.0 += 1;
+        }
+        Ok(cursor)
+    }
+
+    fn headers(
+        range: &'a Range<Data>,
+        header_row: bool,
+        cell_format: &'a CellFormatOptions,
+        cell_formats: &'a CellNumberFormats,
+    ) -> Result<Option<TabularStringRecord>, TabularPortError> {
+        if !header_row {
+            return Ok(None);
+        }
+        let (start_row, start_column) = range.start().unwrap_or_default();
+        let Some(row) = range.rows().next() else {
+            return Ok(None);
+        };
+        let headers = row
+            .iter()
+            .enumerate()
+            .map(|(column, data)| {
+                let position = (start_row, start_column + column as u32);
+                format_cell_at(cell_format, data, position, cell_formats)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Some(headers))
+    }
+}
+
+impl Iterator for SpreadsheetRecordCursor<'_> {
+    type Item = TabularStringRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.row.next()?;
+        let (row_index, start_column) = self.position;
+        self.position.0 += 1;
+        let cells = row
+            .iter()
+            .enumerate()
+            .map(|(column, data)| {
+                let position = (row_index, start_column + column as u32);
+                format_cell_at(self.cell_format, data, position, self.cell_formats)
+                    .unwrap_or_default()
+            })
+            .collect();
+        Some(cells)
+    }
+}
+
+impl SharedSource {
+    pub fn new(source: Box<dyn SpreadsheetSource>) -> Self {
+        SharedSource { source: Arc::new(Mutex::new(source)), position: 0 }
+    }
+
+    fn lock(&self) -> std::io::Result<std::sync::MutexGuard<'_, Box<dyn SpreadsheetSource>>> {
+        self.source.lock().map_err(|e| std::io::Error::other(e.to_string()))
+    }
+}
+
+impl Read for SharedSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut source = self.lock()?;
+        source.seek(SeekFrom::Start(self.position))?;
+        let read = source.read(buf)?;
+        drop(source);
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for SharedSource {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::End(_) => self.lock()?.seek(pos)?,
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset).ok_or(
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek before start"),
+            )?,
+        };
+        Ok(self.position)
+    }
+}
+
+fn format_cell_at(
+    cell_format: &CellFormatOptions,
+    data: &Data,
+    position: CellPosition,
+    cell_formats: &CellNumberFormats,
+) -> Result<String, TabularPortError> {
+    cell_format.format_cell_as(data, cell_formats.get(&position).copied()).map_err(|e| match e {
+        TabularPortError::InvalidCell(msg) => {
+            let mut address = String::new();
+            push_column_name(position.1, &mut address);
+            TabularPortError::InvalidCell(format!("{}{}: {}", address, position.0 + 1, msg))
+        }
+        e => e,
+    })
+}
+
+fn push_column_name(column: u32, address: &mut String) {
+    if column >= 26 {
+        push_column_name(column / 26 - 1, address);
+    }
+    address.push((b'A' + (column % 26) as u8) as char);
+}
@@ -1,70 +1,130 @@
-use calamine::{open_workbook, Data, Range, Reader, Rows, Xlsx};
-use chrono::NaiveDateTime;
-use std::{fs::File, io::BufReader};
+use rust_xlsxwriter::{Format, Workbook};
+use std::path::PathBuf;
 
-use super::{error::TabularPortError, TabularCursor, TabularPorter, TabularStringRecord};
-pub struct XlsxPorter {
-    workbook: Xlsx<BufReader<File>>,
-    range_data: Option<Range<Data>>,
-}
+use crate::rdb_qry_handler::datatype::DataType;
+
+use super::{
+    TabularWriter, WriteOptions,
+    spreadsheet::{
+        SpreadsheetPorter, SpreadsheetRecordCursor, SpreadsheetSheet, SpreadsheetSheets,
+    },
+};
+
+//NOTE: the row limit of a single worksheet.
+const XLSX_MAX_ROWS: usize = 1_048_576;
+//NOTE: integers beyond 2^53 lose precision as Excel numbers, so they are written as text.
+const XLSX_MAX_SAFE_INTEGER: i128 = 9_007_199_254_740_992;
+
+//NOTE: kept for code written before the reader handled every spreadsheet format.
+pub type XlsxPorter = SpreadsheetPorter;
+pub type XlsxSheet = SpreadsheetSheet;
+pub type XlsxSheets<'a> = SpreadsheetSheets<'a>;
+pub type XlsxRecordCursor<'a> = SpreadsheetRecordCursor<'a>;
 
-pub struct XlsxRecordCursor<'a> {
-    row: Rows<'a, Data>,
+pub struct XlsxWriter {
+    path: PathBuf,
+    workbook: Workbook,
+    sheet: usize,
+    row: u32,
+    options: WriteOptions,
+    date_format: Format,
 }
 
-impl TabularPorter for XlsxPorter {
-    fn new<P>(src: P) -> Result<Self, Box<dyn std::error::Error>>
+impl TabularWriter for XlsxWriter {
+    fn create<P>(dest: P, options: WriteOptions) -> Result<Self, Box<dyn std::error::Error>>
     where
         P: AsRef<std::path::Path>,
     {
-        let excel: Xlsx<_> = open_workbook(src)?;
-        Ok(XlsxPorter { workbook: excel, range_data: None })
+        let mut workbook = Workbook::new();
+        workbook.add_worksheet_with_constant_memory();
+        Ok(XlsxWriter {
+            path: dest.as_ref().to_path_buf(),
+            workbook,
+            sheet: 0,
+            row: 0,
+            options,
+            date_format: Format::new().set_num_format("yyyy-mm-dd hh:mm:ss"),
+        })
     }
-}
 
-impl XlsxPorter {
-    pub fn init_range(&mut self, range_name: &str) {
-        let range = self.workbook.worksheet_range(range_name);
-        self.range_data = range.ok();
+    fn write_header(&mut self, header: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        let worksheet = self.workbook.worksheet_from_index(self.sheet)?;
+        for (col, name) in header.iter().enumerate() {
+            worksheet.write_string(self.row, col as u16, name)?;
+        }
+        self.row += 1;
+        Ok(())
     }
-}
 
-impl TabularCursor for XlsxPorter {
-    fn cursor(
+    fn write_record(
         &mut self,
-    ) -> Result<impl Iterator<Item = TabularStringRecord>, Box<dyn std::error::Error>> {
-        let range = self
-            .range_data
-            .as_ref()
-            .ok_or(TabularPortError::NotInitialized("Cannot get Range Data".to_string()))?;
-        Ok(XlsxRecordCursor { row: range.rows() })
+        record: &[Option<DataType>],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let worksheet = self.workbook.worksheet_from_index(self.sheet)?;
+        for (col, cell) in record.iter().enumerate() {
+            let col = col as u16;
+            match cell {
+                Some(DataType::F32(val)) => worksheet.write_number(self.row, col, *val)?,
+                Some(DataType::F64(val)) => worksheet.write_number(self.row, col, *val)?,
+                Some(DataType::Decimal(val)) => {
+                    worksheet.write_number(self.row, col, val.to_f64())?
+                }
+                Some(DataType::Bool(val)) => worksheet.write_boolean(self.row, col, *val)?,
+                Some(DataType::String(val)) => worksheet.write_string(self.row, col, val)?,
+                Some(DataType::DateTime(val)) if self.options.date_format().is_none() => worksheet
+                    .write_datetime_with_format(
+                        self.row,
+                        col,
+                        val.naive_local(),
+                        &self.date_format,
+                    )?,
+                Some(value) => match XlsxWriter::safe_integer(value) {
+                    Some(val) => worksheet.write_number(self.row, col, val as f64)?,
+                    None => worksheet.write_string(
+                        self.row,
+                        col,
+                        self.options.format_cell(cell.as_ref()),
+                    )?,
+                },
+                None if self.options.null_value().is_empty() => worksheet,
+                None => worksheet.write_string(self.row, col, self.options.null_value())?,
+            };
+        }
+        self.row += 1;
+        Ok(())
     }
-}
 
-impl Iterator for XlsxRecordCursor<'_> {
-    type Item = TabularStringRecord;
+    fn next_part(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.workbook.add_worksheet_with_constant_memory();
+        self.sheet += 1;
+        self.row = 0;
+        Ok(())
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let row = self.row.next();
-        match row {
-            Some(data) => Some(
-                data.iter()
-                    .map(|val| match val {
-                        Data::Int(i) => i.to_string(),
-                        Data::Float(f) => f.to_string(),
-                        Data::String(s) => s.to_string(),
-                        Data::Bool(b) => b.to_string(),
-                        Data::DateTime(dt) => {
-                            dt.as_datetime().unwrap_or(NaiveDateTime::default()).to_string()
-                        }
-                        Data::DateTimeIso(dt) => dt.to_string(),
-                        Data::DurationIso(d) => d.to_string(),
-                        Data::Error(e) => e.to_string(),
-                        Data::Empty => "".to_string(),
-                    })
-                    .collect(),
-            ),
-            _ => None,
-        }
+    fn finish(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.workbook.save(&self.path)?;
+        Ok(())
+    }
+
+    fn max_rows_per_part(&self) -> Option<usize> {
+        Some(XLSX_MAX_ROWS)
+    }
+}
+
+impl XlsxWriter {
+    fn safe_integer(value: &DataType) -> Option<i128> {
+        let val = match value {
+            DataType::I8(val) => *val as i128,
+            DataType::I16(val) => *val as i128,
+            DataType::I32(val) => *val as i128,
+            DataType::I64(val) => *val as i128,
+            DataType::I128(val) => *val,
+            DataType::U8(val) => *val as i128,
+            DataType::U16(val) => *val as i128,
+            DataType::U32(val) => *val as i128,
+            DataType::U64(val) => *val as i128,
+            _ => return None,
+        };
+        if val.abs() <= XLSX_MAX_SAFE_INTEGER { Some(val) } else { None }
     }
 }
@@ -0,0 +1,355 @@
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use glob::{MatchOptions, Pattern};
+use zip::{CompressionMethod, ZipArchive};
+
+use super::{
+    TabularCursor, TabularPorter, TabularStringRecord,
+    compression::Compression,
+    csv::CsvPorter,
+    error::TabularPortError,
+    spreadsheet::{SpreadsheetFormat, SpreadsheetPorter, SpreadsheetSource},
+};
+
+//NOTE: lists the files of a ZIP archive and opens them without extracting to disk. CSV entries
+//      are streamed from the archive; spreadsheets need random access, so they are read into
+//      memory first.
+pub struct ZipPorter {
+    source: ZipSource,
+    entries: Vec<ZipEntryInfo>,
+}
+
+enum ZipSource {
+    File(PathBuf),
+    Bytes(Arc<[u8]>),
+}
+
+struct ZipEntryInfo {
+    name: String,
+    data_start: u64,
+    compressed_size: u64,
+    compression: CompressionMethod,
+    crc32: u32,
+    encrypted: bool,
+}
+
+//NOTE: checks the CRC-32 stored in the archive once the entry has been read to the end.
+struct Crc32Reader {
+    inner: Box<dyn Read + Send>,
+    hasher: crc32fast::Hasher,
+    expected: u32,
+    name: String,
+}
+
+//NOTE: spreadsheet entries are read from their first sheet with the first row as the header,
+//      like CSV entries.
+pub enum ZipEntry {
+    Csv(CsvPorter),
+    Spreadsheet(Box<SpreadsheetPorter>),
+}
+
+//NOTE: reads the matching entries one after another as one table. Every entry must have the
+//      header of the first one.
+pub struct ZipEntries {
+    entries: Vec<(String, ZipEntry)>,
+}
+
+impl TabularPorter for ZipPorter {
+    fn new<P>(src: P) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        ZipPorter::from_source(ZipSource::File(src.as_ref().to_path_buf()))
+    }
+}
+
+impl ZipPorter {
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Box<dyn std::error::Error>> {
+        ZipPorter::from_source(ZipSource::Bytes(bytes.into()))
+    }
+
+    fn from_source(source: ZipSource) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut archive = ZipArchive::new(source.open()?)?;
+        let mut entries = Vec::new();
+        for index in 0..archive.len() {
+            let file = archive.by_index_raw(index)?;
+            if !file.is_file() {
+                continue;
+            }
+            entries.push(ZipEntryInfo {
+                name: file.name().to_string(),
+                data_start: file.data_start(),
+                compressed_size: file.compressed_size(),
+                compression: file.compression(),
+                crc32: file.crc32(),
+                encrypted: file.encrypted(),
+            });
+        }
+        Ok(ZipPorter { source, entries })
+    }
+
+    pub fn entry_names(&self) -> Vec<String> {
+        self.entries.iter().map(|entry| entry.name.clone()).collect()
+    }
+
+    //NOTE: the CSV and spreadsheet entries, compressed or not, that `open` can read.
+    pub fn tabular_entry_names(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| ZipEntryKind::from_name(&entry.name).is_some())
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    //NOTE: an exact entry name, or a glob like `orders/*.csv` where `*` stays within a folder
+    //      and `**` crosses them. Matches are in archive order.
+    pub fn find(&self, pattern: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        if self.entries.iter().any(|entry| entry.name == pattern) {
+            return Ok(vec![pattern.to_string()]);
+        }
+        let glob = Pattern::new(pattern).map_err(|e| {
+            TabularPortError::EntryNotFound(format!("{}: {}", pattern, e), self.entry_names())
+        })?;
+        let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+        let names: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|entry| glob.matches_with(&entry.name, options))
+            .map(|entry| entry.name.clone())
+            .collect();
+        if names.is_empty() {
+            return Err(Box::new(TabularPortError::EntryNotFound(
+                pattern.to_string(),
+                self.entry_names(),
+            )));
+        }
+        Ok(names)
+    }
+
+    pub fn open_csv(&self, pattern: &str) -> Result<CsvPorter, Box<dyn std::error::Error>> {
+        let entry = self.find_one(pattern)?;
+        Ok(CsvPorter::from_reader(self.entry_reader(entry)?))
+    }
+
+    pub fn open_spreadsheet(
+        &self,
+        pattern: &str,
+    ) -> Result<SpreadsheetPorter, Box<dyn std::error::Error>> {
+        let entry = self.find_one(pattern)?;
+        let mut bytes = Vec::new();
+        self.entry_reader(entry)?.read_to_end(&mut bytes)?;
+        let extension = Path::new(&entry.name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match SpreadsheetFormat::from_extension(&extension) {
+            Some(format) => SpreadsheetPorter::from_reader_with_format(Cursor::new(bytes), format),
+            None => SpreadsheetPorter::from_bytes(bytes),
+        }
+    }
+
+    pub fn open(&self, pattern: &str) -> Result<ZipEntry, Box<dyn std::error::Error>> {
+        let entry = self.find_one(pattern)?;
+        match ZipEntryKind::from_name(&entry.name) {
+            Some(ZipEntryKind::Csv) => Ok(ZipEntry::Csv(self.open_csv(&entry.name)?)),
+            Some(ZipEntryKind::Spreadsheet) => {
+                let mut porter = self.open_spreadsheet(&entry.name)?;
+                porter.init_range_by_index(0)?;
+                porter.set_header_row(true);
+                Ok(ZipEntry::Spreadsheet(Box::new(porter)))
+            }
+            None => Err(Box::new(TabularPortError::UnsupportedFormat(entry.name.clone()))),
+        }
+    }
+
+    pub fn open_all(&self, pattern: &str) -> Result<ZipEntries, Box<dyn std::error::Error>> {
+        self.open_entries(self.find(pattern)?)
+    }
+
+    //NOTE: every entry listed by `tabular_entry_names`, skipping notes, images and the like.
+    pub fn open_tabular(&self) -> Result<ZipEntries, Box<dyn std::error::Error>> {
+        let names = self.tabular_entry_names();
+        if names.is_empty() {
+            return Err(Box::new(TabularPortError::EntryNotFound(
+                "CSV or spreadsheet entry".to_string(),
+                self.entry_names(),
+            )));
+        }
+        self.open_entries(names)
+    }
+
+    fn open_entries(&self, names: Vec<String>) -> Result<ZipEntries, Box<dyn std::error::Error>> {
+        let entries = names
+            .into_iter()
+            .map(|name| self.open(&name).map(|entry| (name, entry)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ZipEntries { entries })
+    }
+
+    fn find_one(&self, pattern: &str) -> Result<&ZipEntryInfo, Box<dyn std::error::Error>> {
+        let names = self.find(pattern)?;
+        if names.len() > 1 {
+            return Err(Box::new(TabularPortError::AmbiguousEntry(pattern.to_string(), names)));
+        }
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.name == names[0])
+            .ok_or(TabularPortError::EntryNotFound(pattern.to_string(), self.entry_names()))?;
+        Ok(entry)
+    }
+
+    //NOTE: reads the entry data straight from its offset, so the reader owns its own handle on
+    //      the archive and outlives the porter. The CRC-32 is checked as `ZipFile` would.
+    fn entry_reader(
+        &self,
+        entry: &ZipEntryInfo,
+    ) -> Result<Box<dyn Read + Send>, Box<dyn std::error::Error>> {
+        if entry.encrypted {
+            return Err(Box::new(TabularPortError::UnsupportedFormat(format!(
+                "{} is encrypted",
+                entry.name
+            ))));
+        }
+        let mut source = self.source.open()?;
+        source.seek(SeekFrom::Start(entry.data_start))?;
+        let data = source.take(entry.compressed_size);
+        let inner: Box<dyn Read + Send> = match entry.compression {
+            CompressionMethod::Stored => Box::new(data),
+            CompressionMethod::Deflated => Box::new(flate2::read::DeflateDecoder::new(data)),
+            method => {
+                return Err(Box::new(TabularPortError::UnsupportedFormat(format!(
+                    "{} is compressed with {}",
+                    entry.name, method
+                ))));
+            }
+        };
+        Ok(Box::new(Crc32Reader {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+            expected: entry.crc32,
+            name: entry.name.clone(),
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ZipEntryKind {
+    Csv,
+    Spreadsheet,
+}
+
+impl ZipEntryKind {
+    //NOTE: the kind of entry is told by its extension; `orders.csv.gz` is a compressed CSV.
+    fn from_name(name: &str) -> Option<Self> {
+        let (_, name) = Compression::split_path(Path::new(name));
+        let extension =
+            name.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+        match extension.as_str() {
+            "csv" | "txt" => Some(ZipEntryKind::Csv),
+            _ if SpreadsheetFormat::from_extension(&extension).is_some() => {
+                Some(ZipEntryKind::Spreadsheet)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Read for Crc32Reader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        if read == 0 && !buf.is_empty() && self.hasher.clone().finalize() != self.expected {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} does not match its CRC-32", self.name),
+            ));
+        }
+        Ok(read)
+    }
+}
+
+impl ZipSource {
+    fn open(&self) -> Result<Box<dyn SpreadsheetSource>, Box<dyn std::error::Error>> {
+        let source: Box<dyn SpreadsheetSource> = match self {
+            ZipSource::File(path) => Box::new(BufReader::new(File::open(path)?)),
+            ZipSource::Bytes(bytes) => Box::new(Cursor::new(bytes.clone())),
+        };
+        Ok(source)
+    }
+}
+
+impl TabularCursor for ZipEntry {
+    fn cursor(
+        &mut self,
+    ) -> Result<impl Iterator<Item = TabularStringRecord>, Box<dyn std::error::Error>> {
+        Ok(self.try_cursor()?.map_while(Result::ok))
+    }
+
+    fn try_cursor(
+        &mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<TabularStringRecord, TabularPortError>>,
+        Box<dyn std::error::Error>,
+    > {
+        let cursor: Box<dyn Iterator<Item = Result<TabularStringRecord, TabularPortError>> + '_> =
+            match self {
+                ZipEntry::Csv(porter) => Box::new(porter.try_cursor()?),
+                ZipEntry::Spreadsheet(porter) => Box::new(porter.try_cursor()?),
+            };
+        Ok(cursor)
+    }
+
+    fn headers(&mut self) -> Result<Option<TabularStringRecord>, Box<dyn std::error::Error>> {
+        match self {
+            ZipEntry::Csv(porter) => porter.headers(),
+            ZipEntry::Spreadsheet(porter) => porter.headers(),
+        }
+    }
+}
+
+impl ZipEntries {
+    pub fn names(&self) -> Vec<String> {
+        self.entries.iter().map(|(name, _)| name.clone()).collect()
+    }
+}
+
+impl TabularCursor for ZipEntries {
+    fn cursor(
+        &mut self,
+    ) -> Result<impl Iterator<Item = TabularStringRecord>, Box<dyn std::error::Error>> {
+        Ok(self.try_cursor()?.map_while(Result::ok))
+    }
+
+    fn try_cursor(
+        &mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<TabularStringRecord, TabularPortError>>,
+        Box<dyn std::error::Error>,
+    > {
+        let headers = self.headers()?;
+        let mut cursors = Vec::new();
+        for (name, entry) in self.entries.iter_mut() {
+            if entry.headers()? != headers {
+                return Err(Box::new(TabularPortError::ReadFailed(format!(
+                    "{} has a different header from the first entry",
+                    name
+                ))));
+            }
+            cursors.push(entry.try_cursor()?);
+        }
+        Ok(cursors.into_iter().flatten())
+    }
+
+    fn headers(&mut self) -> Result<Option<TabularStringRecord>, Box<dyn std::error::Error>> {
+        match self.entries.first_mut() {
+            Some((_, entry)) => entry.headers(),
+            None => Ok(None),
+        }
+    }
+}
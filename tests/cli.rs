@@ -0,0 +1,107 @@
+mod shared;
+use shared::temp_path;
+use std::io::Write;
+use std::process::Command;
+
+fn tabularuq(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_tabularuq")).args(args).output().unwrap()
+}
+
+#[test]
+fn cli_preview_test() {
+    let input = temp_path("preview.csv");
+    std::fs::write(&input, "id,name\n1,alpha\n2,beta\n3,gamma\n").unwrap();
+
+    let output = tabularuq(&["preview", "-i", input.to_str().unwrap(), "-n", "2"]);
+    std::fs::remove_file(input).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "id | name\n---+------\n1  | alpha\n2  | beta\n(2 rows)\n"
+    );
+}
+
+#[test]
+fn cli_convert_round_trip_test() {
+    let input = temp_path("convert.csv");
+    let xlsx = temp_path("convert.xlsx");
+    let output = temp_path("convert_back.csv");
+    std::fs::write(&input, "id,name\n1,alpha\n2,\n").unwrap();
+
+    let to_xlsx =
+        tabularuq(&["convert", "-i", input.to_str().unwrap(), "-o", xlsx.to_str().unwrap()]);
+    let to_csv =
+        tabularuq(&["convert", "-i", xlsx.to_str().unwrap(), "-o", output.to_str().unwrap()]);
+    let converted = std::fs::read_to_string(&output).unwrap();
+    for path in [input, xlsx, output] {
+        std::fs::remove_file(path).unwrap();
+    }
+
+    assert!(to_xlsx.status.success());
+    assert!(to_csv.status.success());
+    assert_eq!(converted, "id,name\n1,alpha\n2,\n");
+}
+
+#[test]
+fn cli_error_exit_code_test() {
+    let output = tabularuq(&["query", "-c", "/nonexistent/datasource.toml", "SELECT 1"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().starts_with("error: "));
+}
+
+#[test]
+fn cli_convert_encoding_test() {
+    let input = temp_path("encoding.csv");
+    let output = temp_path("encoding_out.csv");
+    let (cp949, _, _) = encoding_rs::EUC_KR.encode("id,이름\n1,김철수\n2,이영희\n3,박민수\n");
+    std::fs::write(&input, &cp949).unwrap();
+
+    let converted = tabularuq(&[
+        "convert",
+        "-i",
+        input.to_str().unwrap(),
+        "-o",
+        output.to_str().unwrap(),
+        "--output-encoding",
+        "utf-16le",
+        "--bom",
+    ]);
+    let bytes = std::fs::read(&output).unwrap();
+    let preview = tabularuq(&["preview", "-i", output.to_str().unwrap(), "-n", "1"]);
+    let unknown = tabularuq(&["preview", "-i", input.to_str().unwrap(), "--encoding", "klingon"]);
+    for path in [input, output] {
+        std::fs::remove_file(path).unwrap();
+    }
+
+    assert!(converted.status.success());
+    assert_eq!(bytes[..2], [0xFF, 0xFE]);
+    assert!(String::from_utf8(preview.stdout).unwrap().contains("1  | 김철수\n(1 rows)"));
+    assert!(!unknown.status.success());
+}
+
+#[test]
+fn cli_zip_entry_test() {
+    let input = temp_path("bundle.zip");
+    let mut archive = zip::ZipWriter::new(std::fs::File::create(&input).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    archive.start_file("orders/2024-01.csv", options).unwrap();
+    archive.write_all(b"id,qty\n1,10\n").unwrap();
+    archive.start_file("orders/2024-02.csv", options).unwrap();
+    archive.write_all(b"id,qty\n2,20\n").unwrap();
+    archive.start_file("README.md", options).unwrap();
+    archive.write_all(b"# Orders").unwrap();
+    archive.finish().unwrap();
+
+    let input_path = input.to_str().unwrap();
+    let every = tabularuq(&["preview", "-i", input_path]);
+    let one = tabularuq(&["preview", "-i", input_path, "--entry", "orders/*-02.csv"]);
+    let sheet = tabularuq(&["preview", "-i", input_path, "--sheet", "orders/2024-01.csv"]);
+    std::fs::remove_file(input).unwrap();
+
+    assert!(every.status.success());
+    assert!(String::from_utf8(every.stdout).unwrap().ends_with("1  | 10\n2  | 20\n(2 rows)\n"));
+    assert!(String::from_utf8(one.stdout).unwrap().ends_with("2  | 20\n(1 rows)\n"));
+    assert!(!sheet.status.success());
+    assert!(String::from_utf8(sheet.stderr).unwrap().contains("use --entry"));
+}
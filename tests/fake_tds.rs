@@ -0,0 +1,619 @@
+#![allow(dead_code)]
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime};
+use tabularuq::rdb_qry_handler::{
+    datatype::{DataType, DataTypeKind, Decimal},
+    sqlserver::{
+        SqlServerConnectionConfig, SqlServerCredentials, SqlServerEncryption, SqlServerTlsConfig,
+    },
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+const PACKET_SQL_BATCH: u8 = 0x01;
+const PACKET_RPC: u8 = 0x03;
+const PACKET_TABULAR_RESULT: u8 = 0x04;
+const PACKET_BULK_LOAD: u8 = 0x07;
+const PACKET_LOGIN7: u8 = 0x10;
+const PACKET_PRELOGIN: u8 = 0x12;
+const STATUS_EOM: u8 = 0x01;
+const PACKET_SIZE: usize = 4096;
+
+const TOKEN_COLMETADATA: u8 = 0x81;
+const TOKEN_ERROR: u8 = 0xAA;
+const TOKEN_LOGINACK: u8 = 0xAD;
+const TOKEN_ROW: u8 = 0xD1;
+const TOKEN_DONE: u8 = 0xFD;
+const DONE_MORE: u16 = 0x01;
+const DONE_ERROR: u16 = 0x02;
+const DONE_COUNT: u16 = 0x10;
+const DONE_LENGTH: usize = 13;
+const COLUMN_NULLABLE_UPDATEABLE: u16 = 0x0009;
+//NOTE: `DataTypeKind::Decimal` columns are returned as decimal(38, 2).
+const DECIMAL_SCALE: u8 = 2;
+
+const TYPE_NULL: u8 = 0x1F;
+const TYPE_INTN: u8 = 0x26;
+const TYPE_DATETIMEOFFSETN: u8 = 0x2B;
+const TYPE_BITN: u8 = 0x68;
+const TYPE_DECIMALN: u8 = 0x6A;
+const TYPE_NUMERICN: u8 = 0x6C;
+const TYPE_FLOATN: u8 = 0x6D;
+const TYPE_DATETIMEN: u8 = 0x6F;
+const TYPE_BIGVARBIN: u8 = 0xA5;
+const TYPE_NVARCHAR: u8 = 0xE7;
+const COLLATION: [u8; 5] = [0x09, 0x04, 0xD0, 0x00, 0x34];
+const PLP_NULL: u64 = 0xFFFF_FFFF_FFFF_FFFF;
+
+//NOTE: speaks just enough TDS 7.4 for `SqlServerHandler`: an unencrypted prelogin, a login that
+//      always succeeds, and RPC/batch requests answered from a queue of scripted responses
+//      (an empty queue answers with an empty `DONE`). Bulk loads are decoded with the column
+//      types the client sends and kept as rows. Connect with `encryption = "off"`.
+pub struct FakeTdsServer {
+    port: u16,
+    state: Arc<Mutex<FakeState>>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    responses: VecDeque<FakeResponse>,
+    requests: Vec<FakeRequest>,
+    logins: Vec<FakeLogin>,
+    bulk_rows: Vec<Vec<Option<DataType>>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum FakeResponse {
+    Rows(Vec<(String, DataTypeKind)>, Vec<Vec<Option<DataType>>>),
+    Affected(u64),
+    Error(u32, String),
+    Batch(Vec<FakeResponse>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeRequest {
+    pub sql: String,
+    pub params: Vec<Option<DataType>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeLogin {
+    pub username: String,
+    pub database: String,
+}
+
+impl FakeTdsServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Fake TDS server bind error");
+        let port = listener.local_addr().expect("Fake TDS server address error").port();
+        let state = Arc::new(Mutex::new(FakeState::default()));
+
+        let accept_state = Arc::clone(&state);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, Arc::clone(&accept_state)));
+            }
+        });
+
+        FakeTdsServer { port, state }
+    }
+
+    pub fn config(&self, database: &str) -> SqlServerConnectionConfig {
+        let tls = SqlServerTlsConfig::new(Some(SqlServerEncryption::Off), None, None);
+        SqlServerConnectionConfig::new(
+            None,
+            "127.0.0.1".to_string(),
+            Some(self.port),
+            database.to_string(),
+            SqlServerCredentials::sql("tester".to_string(), "secret".to_string()),
+            tls,
+        )
+    }
+
+    pub fn respond(&self, response: FakeResponse) {
+        self.state.lock().unwrap().responses.push_back(response);
+    }
+
+    pub fn requests(&self) -> Vec<FakeRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn logins(&self) -> Vec<FakeLogin> {
+        self.state.lock().unwrap().logins.clone()
+    }
+
+    pub fn bulk_rows(&self) -> Vec<Vec<Option<DataType>>> {
+        self.state.lock().unwrap().bulk_rows.clone()
+    }
+}
+
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<FakeState>>) {
+    while let Some((packet_type, message)) = read_message(&mut stream).await {
+        let reply = match packet_type {
+            PACKET_PRELOGIN => prelogin_reply(),
+            PACKET_LOGIN7 => {
+                state.lock().unwrap().logins.push(parse_login(&message));
+                login_reply()
+            }
+            PACKET_RPC | PACKET_SQL_BATCH => {
+                let request = if packet_type == PACKET_RPC {
+                    parse_rpc(&message)
+                } else {
+                    parse_batch(&message)
+                };
+                let mut state = state.lock().unwrap();
+                state.requests.push(request);
+                encode_response(state.responses.pop_front().unwrap_or(FakeResponse::Affected(0)))
+            }
+            PACKET_BULK_LOAD => {
+                let rows = parse_bulk(&message);
+                let mut reply = Vec::new();
+                put_done(&mut reply, DONE_COUNT, rows.len() as u64);
+                state.lock().unwrap().bulk_rows.extend(rows);
+                reply
+            }
+            other => panic!("Fake TDS server got unsupported packet type 0x{:02x}", other),
+        };
+        if write_message(&mut stream, &reply).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn read_message(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+    let mut message = Vec::new();
+    loop {
+        let mut header = [0u8; 8];
+        stream.read_exact(&mut header).await.ok()?;
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let mut payload = vec![0u8; length - header.len()];
+        stream.read_exact(&mut payload).await.ok()?;
+        message.extend_from_slice(&payload);
+        if header[1] & STATUS_EOM != 0 {
+            return Some((header[0], message));
+        }
+    }
+}
+
+async fn write_message(stream: &mut TcpStream, message: &[u8]) -> std::io::Result<()> {
+    let chunks: Vec<&[u8]> = message.chunks(PACKET_SIZE - 8).collect();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let status = if index + 1 == chunks.len() { STATUS_EOM } else { 0 };
+        let length = (chunk.len() + 8) as u16;
+        let mut packet = vec![PACKET_TABULAR_RESULT, status];
+        packet.extend_from_slice(&length.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, (index + 1) as u8, 0]);
+        packet.extend_from_slice(chunk);
+        stream.write_all(&packet).await?;
+    }
+    stream.flush().await
+}
+
+fn prelogin_reply() -> Vec<u8> {
+    //NOTE: VERSION, ENCRYPTION (not supported), INSTOPT, THREADID (empty), MARS (off).
+    let options: [(u8, Vec<u8>); 5] = [
+        (0x00, vec![0x10, 0x00, 0x00, 0x00, 0x00, 0x00]),
+        (0x01, vec![0x02]),
+        (0x02, vec![0x00]),
+        (0x03, vec![]),
+        (0x04, vec![0x00]),
+    ];
+    let mut offset = options.len() * 5 + 1;
+    let mut table = Vec::new();
+    let mut data = Vec::new();
+    for (token, value) in options {
+        table.push(token);
+        table.extend_from_slice(&(offset as u16).to_be_bytes());
+        table.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        offset += value.len();
+        data.extend_from_slice(&value);
+    }
+    table.push(0xFF);
+    table.extend_from_slice(&data);
+    table
+}
+
+fn parse_login(message: &[u8]) -> FakeLogin {
+    let field = |at: usize| {
+        let offset = u16::from_le_bytes([message[at], message[at + 1]]) as usize;
+        let chars = u16::from_le_bytes([message[at + 2], message[at + 3]]) as usize;
+        utf16(&message[offset..offset + chars * 2])
+    };
+    FakeLogin { username: field(40), database: field(68) }
+}
+
+fn login_reply() -> Vec<u8> {
+    let prog_name = "FakeTDS";
+    let mut ack = vec![0x01];
+    ack.extend_from_slice(&0x7400_0004u32.to_be_bytes());
+    put_b_varchar(&mut ack, prog_name);
+    ack.extend_from_slice(&[0x10, 0x00, 0x00, 0x00]);
+
+    let mut reply = vec![TOKEN_LOGINACK];
+    reply.extend_from_slice(&(ack.len() as u16).to_le_bytes());
+    reply.extend_from_slice(&ack);
+    put_done(&mut reply, 0, 0);
+    reply
+}
+
+fn parse_batch(message: &[u8]) -> FakeRequest {
+    let headers = read_u32(message, 0) as usize;
+    FakeRequest { sql: utf16(&message[headers..]), params: Vec::new() }
+}
+
+//NOTE: `sp_executesql` sends @stmt, the @params declaration and then the values. Other
+//      procedures only send values, which are recorded with an empty `sql`.
+fn parse_rpc(message: &[u8]) -> FakeRequest {
+    let mut cursor = read_u32(message, 0) as usize;
+    let proc_id = if read_u16(message, cursor) == 0xFFFF {
+        cursor += 4;
+        read_u16(message, cursor - 2)
+    } else {
+        let chars = read_u16(message, cursor) as usize;
+        cursor += 2 + chars * 2;
+        0
+    };
+    cursor += 2;
+
+    let mut values = Vec::new();
+    while cursor < message.len() {
+        let name_chars = message[cursor] as usize;
+        cursor += 1 + name_chars * 2 + 1;
+        values.push(read_value(message, &mut cursor));
+    }
+
+    if proc_id == 10 {
+        let sql = match values.first() {
+            Some(Some(DataType::String(sql))) => sql.clone(),
+            other => panic!("Fake TDS server got sp_executesql without @stmt: {:?}", other),
+        };
+        FakeRequest { sql, params: values.into_iter().skip(2).collect() }
+    } else {
+        FakeRequest { sql: String::new(), params: values }
+    }
+}
+
+struct TypeInfo {
+    type_id: u8,
+    max_length: usize,
+    scale: u8,
+}
+
+fn read_value(message: &[u8], cursor: &mut usize) -> Option<DataType> {
+    let type_info = read_type_info(message, cursor);
+    read_typed_value(message, cursor, &type_info)
+}
+
+fn read_type_info(message: &[u8], cursor: &mut usize) -> TypeInfo {
+    let type_id = message[*cursor];
+    *cursor += 1;
+    let (max_length, scale) = match type_id {
+        TYPE_NULL => (0, 0),
+        TYPE_INTN | TYPE_BITN | TYPE_FLOATN | TYPE_DATETIMEN => {
+            *cursor += 1;
+            (message[*cursor - 1] as usize, 0)
+        }
+        TYPE_DATETIMEOFFSETN => {
+            *cursor += 1;
+            (0, message[*cursor - 1])
+        }
+        TYPE_NVARCHAR | TYPE_BIGVARBIN => {
+            let max_length = read_u16(message, *cursor) as usize;
+            *cursor += 2;
+            if type_id == TYPE_NVARCHAR {
+                *cursor += COLLATION.len();
+            }
+            (max_length, 0)
+        }
+        TYPE_NUMERICN | TYPE_DECIMALN => {
+            *cursor += 3;
+            (message[*cursor - 3] as usize, message[*cursor - 1])
+        }
+        other => panic!("Fake TDS server got unsupported parameter type 0x{:02x}", other),
+    };
+    TypeInfo { type_id, max_length, scale }
+}
+
+fn read_typed_value(message: &[u8], cursor: &mut usize, type_info: &TypeInfo) -> Option<DataType> {
+    let type_id = type_info.type_id;
+    match type_id {
+        TYPE_NULL => None,
+        TYPE_INTN | TYPE_BITN | TYPE_FLOATN | TYPE_DATETIMEN => {
+            let length = message[*cursor] as usize;
+            *cursor += 1;
+            let bytes = &message[*cursor..*cursor + length];
+            *cursor += length;
+            match (type_id, length) {
+                (_, 0) => None,
+                (TYPE_INTN, 1) => Some(DataType::U8(bytes[0])),
+                (TYPE_INTN, 2) => Some(DataType::I16(i16::from_le_bytes(bytes.try_into().ok()?))),
+                (TYPE_INTN, 4) => Some(DataType::I32(i32::from_le_bytes(bytes.try_into().ok()?))),
+                (TYPE_INTN, 8) => Some(DataType::I64(i64::from_le_bytes(bytes.try_into().ok()?))),
+                (TYPE_BITN, 1) => Some(DataType::Bool(bytes[0] != 0)),
+                (TYPE_FLOATN, 4) => Some(DataType::F32(f32::from_le_bytes(bytes.try_into().ok()?))),
+                (TYPE_FLOATN, 8) => Some(DataType::F64(f64::from_le_bytes(bytes.try_into().ok()?))),
+                (TYPE_DATETIMEN, 8) => {
+                    let days = i32::from_le_bytes(bytes[..4].try_into().ok()?);
+                    let ticks = u32::from_le_bytes(bytes[4..].try_into().ok()?);
+                    let date_time = NaiveDate::from_ymd_opt(1900, 1, 1)?.and_time(NaiveTime::MIN)
+                        + chrono::Duration::days(days as i64)
+                        + chrono::Duration::milliseconds(ticks as i64 * 10 / 3);
+                    Some(DataType::DateTime(date_time.and_utc().fixed_offset()))
+                }
+                _ => panic!("Fake TDS server got 0x{:02x} with length {}", type_id, length),
+            }
+        }
+        TYPE_NVARCHAR | TYPE_BIGVARBIN => {
+            let bytes = if type_info.max_length == 0xFFFF {
+                read_plp(message, cursor)?
+            } else {
+                let length = read_u16(message, *cursor);
+                *cursor += 2;
+                if length == 0xFFFF {
+                    return None;
+                }
+                let bytes = message[*cursor..*cursor + length as usize].to_vec();
+                *cursor += length as usize;
+                bytes
+            };
+            match type_id {
+                TYPE_NVARCHAR => Some(DataType::String(utf16(&bytes))),
+                _ => Some(DataType::Bytes(bytes)),
+            }
+        }
+        TYPE_NUMERICN | TYPE_DECIMALN => {
+            let length = message[*cursor] as usize;
+            *cursor += 1;
+            if length == 0 {
+                return None;
+            }
+            let positive = message[*cursor] == 1;
+            let mut magnitude = [0u8; 16];
+            magnitude[..length - 1].copy_from_slice(&message[*cursor + 1..*cursor + length]);
+            *cursor += length;
+            let value = u128::from_le_bytes(magnitude) as i128;
+            let value = if positive { value } else { -value };
+            match type_info.scale {
+                0 => Some(DataType::I128(value)),
+                scale => Some(DataType::Decimal(Decimal::new(value, scale))),
+            }
+        }
+        TYPE_DATETIMEOFFSETN => {
+            let length = message[*cursor] as usize;
+            *cursor += 1;
+            if length == 0 {
+                return None;
+            }
+            let time_length = length - 5;
+            let mut increments = [0u8; 8];
+            increments[..time_length].copy_from_slice(&message[*cursor..*cursor + time_length]);
+            let mut days = [0u8; 4];
+            days[..3].copy_from_slice(&message[*cursor + time_length..*cursor + time_length + 3]);
+            let offset =
+                i16::from_le_bytes([message[*cursor + length - 2], message[*cursor + length - 1]]);
+            *cursor += length;
+
+            let date = NaiveDate::from_ymd_opt(1, 1, 1)?
+                + chrono::Duration::days(u32::from_le_bytes(days) as i64);
+            let time = NaiveTime::MIN
+                + chrono::Duration::nanoseconds(u64::from_le_bytes(increments) as i64 * 100);
+            let offset = FixedOffset::east_opt(offset as i32 * 60)?;
+            Some(DataType::DateTime(DateTime::from_naive_utc_and_offset(
+                date.and_time(time),
+                offset,
+            )))
+        }
+        other => panic!("Fake TDS server got unsupported parameter type 0x{:02x}", other),
+    }
+}
+
+//NOTE: a bulk load is the column metadata followed by one ROW token per row.
+fn parse_bulk(message: &[u8]) -> Vec<Vec<Option<DataType>>> {
+    assert_eq!(message[0], TOKEN_COLMETADATA, "Fake TDS server got a bulk load without metadata");
+    let mut cursor = 3;
+    let mut columns = Vec::new();
+    for _ in 0..read_u16(message, 1) {
+        cursor += 4 + 2;
+        columns.push(read_type_info(message, &mut cursor));
+        cursor += 1 + message[cursor] as usize * 2;
+    }
+
+    let mut rows = Vec::new();
+    while message[cursor] == TOKEN_ROW {
+        cursor += 1;
+        rows.push(
+            columns.iter().map(|column| read_typed_value(message, &mut cursor, column)).collect(),
+        );
+    }
+    rows
+}
+
+fn read_plp(message: &[u8], cursor: &mut usize) -> Option<Vec<u8>> {
+    let total = u64::from_le_bytes(message[*cursor..*cursor + 8].try_into().ok()?);
+    *cursor += 8;
+    if total == PLP_NULL {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    loop {
+        let chunk = read_u32(message, *cursor) as usize;
+        *cursor += 4;
+        if chunk == 0 {
+            return Some(bytes);
+        }
+        bytes.extend_from_slice(&message[*cursor..*cursor + chunk]);
+        *cursor += chunk;
+    }
+}
+
+fn encode_response(response: FakeResponse) -> Vec<u8> {
+    let mut reply = Vec::new();
+    match response {
+        //NOTE: every result but the last ends with a `DONE` flagged as followed by more.
+        FakeResponse::Batch(responses) => {
+            let last = responses.len().saturating_sub(1);
+            for (index, response) in responses.into_iter().enumerate() {
+                let mut result = encode_response(response);
+                if index < last {
+                    let status = result.len() - DONE_LENGTH + 1;
+                    result[status] |= DONE_MORE as u8;
+                }
+                reply.extend_from_slice(&result);
+            }
+        }
+        FakeResponse::Rows(columns, rows) => {
+            reply.push(TOKEN_COLMETADATA);
+            reply.extend_from_slice(&(columns.len() as u16).to_le_bytes());
+            for (name, kind) in &columns {
+                reply.extend_from_slice(&0u32.to_le_bytes());
+                reply.extend_from_slice(&COLUMN_NULLABLE_UPDATEABLE.to_le_bytes());
+                put_type_info(&mut reply, kind);
+                put_b_varchar(&mut reply, name);
+            }
+            for row in &rows {
+                reply.push(TOKEN_ROW);
+                for ((_, kind), value) in columns.iter().zip(row) {
+                    put_column_value(&mut reply, kind, value.as_ref());
+                }
+            }
+            put_done(&mut reply, DONE_COUNT, rows.len() as u64);
+        }
+        FakeResponse::Affected(rows) => put_done(&mut reply, DONE_COUNT, rows),
+        FakeResponse::Error(code, message) => {
+            let mut error = Vec::new();
+            error.extend_from_slice(&code.to_le_bytes());
+            error.extend_from_slice(&[0x01, 0x10]);
+            error.extend_from_slice(&(message.encode_utf16().count() as u16).to_le_bytes());
+            put_utf16(&mut error, &message);
+            put_b_varchar(&mut error, "FakeTDS");
+            put_b_varchar(&mut error, "");
+            error.extend_from_slice(&1u32.to_le_bytes());
+
+            reply.push(TOKEN_ERROR);
+            reply.extend_from_slice(&(error.len() as u16).to_le_bytes());
+            reply.extend_from_slice(&error);
+            put_done(&mut reply, DONE_ERROR, 0);
+        }
+    }
+    reply
+}
+
+fn put_type_info(dst: &mut Vec<u8>, kind: &DataTypeKind) {
+    match kind {
+        DataTypeKind::U8 => dst.extend_from_slice(&[TYPE_INTN, 1]),
+        DataTypeKind::I16 => dst.extend_from_slice(&[TYPE_INTN, 2]),
+        DataTypeKind::I32 => dst.extend_from_slice(&[TYPE_INTN, 4]),
+        DataTypeKind::I64 => dst.extend_from_slice(&[TYPE_INTN, 8]),
+        DataTypeKind::F32 => dst.extend_from_slice(&[TYPE_FLOATN, 4]),
+        DataTypeKind::F64 => dst.extend_from_slice(&[TYPE_FLOATN, 8]),
+        DataTypeKind::Bool => dst.extend_from_slice(&[TYPE_BITN, 1]),
+        DataTypeKind::I128 => dst.extend_from_slice(&[TYPE_DECIMALN, 17, 38, 0]),
+        DataTypeKind::Decimal => dst.extend_from_slice(&[TYPE_DECIMALN, 17, 38, DECIMAL_SCALE]),
+        DataTypeKind::DateTime => dst.extend_from_slice(&[TYPE_DATETIMEN, 8]),
+        DataTypeKind::String => {
+            dst.push(TYPE_NVARCHAR);
+            dst.extend_from_slice(&8000u16.to_le_bytes());
+            dst.extend_from_slice(&COLLATION);
+        }
+        DataTypeKind::Bytes => {
+            dst.push(TYPE_BIGVARBIN);
+            dst.extend_from_slice(&8000u16.to_le_bytes());
+        }
+        other => panic!("Fake TDS server cannot return {:?} columns", other),
+    }
+}
+
+fn put_column_value(dst: &mut Vec<u8>, kind: &DataTypeKind, value: Option<&DataType>) {
+    let value = match value {
+        Some(value) => value,
+        None if matches!(kind, DataTypeKind::String | DataTypeKind::Bytes) => {
+            dst.extend_from_slice(&0xFFFFu16.to_le_bytes());
+            return;
+        }
+        None => {
+            dst.push(0);
+            return;
+        }
+    };
+    match value {
+        DataType::U8(value) => dst.extend_from_slice(&[1, *value]),
+        DataType::I16(value) => put_sized(dst, &value.to_le_bytes()),
+        DataType::I32(value) => put_sized(dst, &value.to_le_bytes()),
+        DataType::I64(value) => put_sized(dst, &value.to_le_bytes()),
+        DataType::F32(value) => put_sized(dst, &value.to_le_bytes()),
+        DataType::F64(value) => put_sized(dst, &value.to_le_bytes()),
+        DataType::Bool(value) => dst.extend_from_slice(&[1, *value as u8]),
+        DataType::I128(value) => {
+            dst.extend_from_slice(&[17, (*value >= 0) as u8]);
+            dst.extend_from_slice(&value.unsigned_abs().to_le_bytes());
+        }
+        DataType::Decimal(value) => {
+            let value = value.rescale(DECIMAL_SCALE).unwrap().mantissa();
+            dst.extend_from_slice(&[17, (value >= 0) as u8]);
+            dst.extend_from_slice(&value.unsigned_abs().to_le_bytes());
+        }
+        DataType::DateTime(value) => {
+            let epoch = NaiveDate::from_ymd_opt(1900, 1, 1).unwrap().and_time(NaiveTime::MIN);
+            let elapsed = value.naive_utc() - epoch;
+            let days = elapsed.num_days();
+            let millis = (elapsed - chrono::Duration::days(days)).num_milliseconds();
+            let mut datetime = Vec::new();
+            datetime.extend_from_slice(&(days as i32).to_le_bytes());
+            datetime.extend_from_slice(&((millis * 3 / 10) as u32).to_le_bytes());
+            put_sized(dst, &datetime);
+        }
+        DataType::String(value) => {
+            dst.extend_from_slice(&((value.encode_utf16().count() * 2) as u16).to_le_bytes());
+            put_utf16(dst, value);
+        }
+        DataType::Bytes(value) => {
+            dst.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            dst.extend_from_slice(value);
+        }
+        other => panic!("Fake TDS server cannot return {:?}", other),
+    }
+}
+
+fn put_sized(dst: &mut Vec<u8>, bytes: &[u8]) {
+    dst.push(bytes.len() as u8);
+    dst.extend_from_slice(bytes);
+}
+
+fn put_done(dst: &mut Vec<u8>, status: u16, rows: u64) {
+    dst.push(TOKEN_DONE);
+    dst.extend_from_slice(&status.to_le_bytes());
+    dst.extend_from_slice(&0u16.to_le_bytes());
+    dst.extend_from_slice(&rows.to_le_bytes());
+}
+
+fn put_b_varchar(dst: &mut Vec<u8>, value: &str) {
+    dst.push(value.encode_utf16().count() as u8);
+    put_utf16(dst, value);
+}
+
+fn put_utf16(dst: &mut Vec<u8>, value: &str) {
+    for unit in value.encode_utf16() {
+        dst.extend_from_slice(&unit.to_le_bytes());
+    }
+}
+
+fn utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> =
+        bytes.chunks_exact(2).map(|unit| u16::from_le_bytes([unit[0], unit[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn read_u16(message: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([message[at], message[at + 1]])
+}
+
+fn read_u32(message: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([message[at], message[at + 1], message[at + 2], message[at + 3]])
+}
@@ -0,0 +1,534 @@
+mod fake_tds;
+mod shared;
+use fake_tds::{FakeResponse, FakeTdsServer};
+use shared::*;
+use std::sync::{Arc, Mutex};
+use tabularuq::pipeline::{
+    ExportOptions, ImportMapping, ImportMode, ImportOptions, InferOptions, SqlDialect,
+    error::PipelineError, export, import, import_stream, infer::InferredType, infer_schema,
+};
+use tabularuq::rdb_qry_handler::{
+    DataRecord, DataRows, QueryHandler,
+    bulk::ColumnMapping,
+    datatype::{DataType, DataTypeKind, Decimal},
+    schema::ColumnInfo,
+    sqlserver::SqlServerHandler,
+};
+use tabularuq::tabular_porter::{
+    TabularCursor, TabularPorter, async_cursor::BlockingTabularCursor, csv::CsvPorter,
+    json::JsonPorter, xslx::XlsxPorter,
+};
+
+type Executed = Arc<Mutex<Vec<(String, Vec<DataType>)>>>;
+
+fn column_infos(columns: &[(&str, &str)]) -> Vec<ColumnInfo> {
+    columns
+        .iter()
+        .map(|(name, type_name)| ColumnInfo {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            max_length: None,
+            precision: None,
+            scale: None,
+            nullable: true,
+            default: None,
+            identity: false,
+        })
+        .collect()
+}
+
+fn recording_mock(columns: &[(&str, &str)]) -> (MockQueryHandlerMock, Executed) {
+    let executed = Arc::new(Mutex::new(Vec::new()));
+    let mut handler = MockQueryHandlerMock::new();
+    let columns = column_infos(columns);
+    handler.expect_columns().returning(move |_| Ok(columns.clone()));
+
+    let recorder = executed.clone();
+    handler.expect_mutate().returning(move |query, binds| {
+        let binds: Vec<DataType> = binds.map(|b| b.to_vec()).unwrap_or_default();
+        let rows = query.matches('(').count().saturating_sub(1) as u64;
+        recorder.lock().unwrap().push((query.to_string(), binds));
+        Box::pin(async move {
+            let mut result = MockQueryResultMock::new();
+            result.expect_affected_rows().return_const(rows);
+            Ok(result)
+        })
+    });
+    (handler, executed)
+}
+
+#[tokio::test]
+async fn import_by_header_test() {
+    let path =
+        write_temp("import_by_header.csv", "name,id,joined\na,1,2024-01-02\nb,,\nc,3,2024-01-04\n");
+    let (mut handler, executed) =
+        recording_mock(&[("id", "int"), ("name", "nvarchar"), ("joined", "date")]);
+
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let options = ImportOptions::new(ImportMode::Append, ImportMapping::ByHeader, 2);
+    let imported = import(&mut handler, &mut porter, "dbo.members", options).await.unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(imported, 3);
+    let executed = executed.lock().unwrap();
+    assert_eq!(executed.len(), 2);
+    assert_eq!(
+        executed[0].0,
+        "INSERT INTO [dbo].[members] ([name], [id], [joined]) VALUES (@P1, @P2, @P3), (@P4, NULL, NULL)"
+    );
+    assert_eq!(executed[0].1[1], DataType::I32(1));
+    assert_eq!(executed[0].1[3], DataType::String("b".to_string()));
+    assert_eq!(executed[1].1.len(), 3);
+}
+
+#[tokio::test]
+async fn import_stream_test() {
+    let path = write_temp("import_stream.csv", "name,id\na,1\nb,2\nc,x\n");
+    let (mut handler, executed) = recording_mock(&[("id", "int"), ("name", "nvarchar")]);
+
+    let cursor = BlockingTabularCursor::<CsvPorter>::open(&path).await.unwrap();
+    let options = ImportOptions::new(ImportMode::Append, ImportMapping::ByHeader, 2);
+    let err = import_stream(&mut handler, cursor, "dbo.members", options).await.unwrap_err();
+    std::fs::remove_file(path).unwrap();
+
+    assert!(matches!(*err.downcast::<PipelineError>().unwrap(), PipelineError::InvalidValue(_)));
+    let executed = executed.lock().unwrap();
+    assert_eq!(executed.len(), 1);
+    assert_eq!(
+        executed[0].0,
+        "INSERT INTO [dbo].[members] ([name], [id]) VALUES (@P1, @P2), (@P3, @P4)"
+    );
+    assert_eq!(executed[0].1[3], DataType::I32(2));
+}
+
+#[tokio::test]
+async fn import_json_lines_test() {
+    let path = write_temp(
+        "import_json.jsonl",
+        "{\"id\": 1, \"member\": {\"name\": \"a\"}}\n{\"id\": 2, \"member\": {\"name\": null}}\n",
+    );
+    let (mut handler, executed) = recording_mock(&[("id", "int"), ("member.name", "nvarchar")]);
+
+    let cursor = BlockingTabularCursor::<JsonPorter>::open(&path).await.unwrap();
+    let imported =
+        import_stream(&mut handler, cursor, "dbo.members", ImportOptions::default()).await.unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(imported, 2);
+    let executed = executed.lock().unwrap();
+    assert_eq!(
+        executed[0].0,
+        "INSERT INTO [dbo].[members] ([id], [member.name]) VALUES (@P1, @P2), (@P3, NULL)"
+    );
+    assert_eq!(
+        executed[0].1,
+        vec![DataType::I32(1), DataType::String("a".to_string()), DataType::I32(2)]
+    );
+}
+
+#[tokio::test]
+async fn import_create_if_missing_test() {
+    let path = write_temp("import_create.csv", "id,name,joined\n1,a,2024-01-02\n2,,2024-01-03\n");
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let schema = infer_schema(&mut porter, InferOptions::default()).unwrap();
+
+    let (mut handler, executed) = recording_mock(&[]);
+    let created = column_infos(&[("id", "tinyint"), ("name", "varchar"), ("joined", "date")]);
+    let mut lookups = 0;
+    handler.checkpoint();
+    handler.expect_columns().returning(move |_| {
+        lookups += 1;
+        Ok(if lookups == 1 { Vec::new() } else { created.clone() })
+    });
+    let recorder = executed.clone();
+    handler.expect_mutate().returning(move |query, binds| {
+        let binds: Vec<DataType> = binds.map(|b| b.to_vec()).unwrap_or_default();
+        recorder.lock().unwrap().push((query.to_string(), binds));
+        Box::pin(async move {
+            let mut result = MockQueryResultMock::new();
+            result.expect_affected_rows().return_const(2u64);
+            Ok(result)
+        })
+    });
+
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let mut options = ImportOptions::new(ImportMode::CreateIfMissing, ImportMapping::ByHeader, 100);
+    options.set_schema(schema);
+    import(&mut handler, &mut porter, "dbo.new members", options).await.unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let executed = executed.lock().unwrap();
+    assert_eq!(
+        executed[0].0,
+        "CREATE TABLE [dbo].[new members] ([id] tinyint NOT NULL, [name] varchar(1) NULL, \
+         [joined] date NOT NULL)"
+    );
+    assert_eq!(
+        executed[1].0,
+        "INSERT INTO [dbo].[new members] ([id], [name], [joined]) VALUES (@P1, @P2, @P3), (@P4, @P5, @P6)"
+    );
+}
+
+#[tokio::test]
+async fn import_truncate_with_explicit_mapping_test() {
+    let path = write_temp("import_truncate.csv", "a,b\nx,10\n");
+    let (mut handler, executed) = recording_mock(&[("code", "varchar"), ("amount", "bigint")]);
+
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let mapping = ColumnMapping::new(vec![(1, "amount".to_string())]);
+    let options = ImportOptions::new(ImportMode::Truncate, ImportMapping::Explicit(mapping), 100);
+    import(&mut handler, &mut porter, "ledger", options).await.unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let executed = executed.lock().unwrap();
+    assert_eq!(executed[0].0, "TRUNCATE TABLE [ledger]");
+    assert_eq!(executed[1].0, "INSERT INTO [ledger] ([amount]) VALUES (@P1)");
+    assert_eq!(executed[1].1, vec![DataType::I64(10)]);
+}
+
+#[tokio::test]
+async fn import_truncate_with_invalid_mapping_test() {
+    let path = write_temp("import_truncate_invalid.csv", "a,b\nx,10\n");
+    let (mut handler, executed) = recording_mock(&[("code", "varchar"), ("amount", "bigint")]);
+
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let mapping = ColumnMapping::new(vec![(1, "missing".to_string())]);
+    let options = ImportOptions::new(ImportMode::Truncate, ImportMapping::Explicit(mapping), 100);
+    let err = import(&mut handler, &mut porter, "ledger", options).await.unwrap_err();
+    std::fs::remove_file(path).unwrap();
+
+    assert!(matches!(*err.downcast::<PipelineError>().unwrap(), PipelineError::InvalidMapping(_)));
+    assert!(executed.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn import_invalid_value_test() {
+    let path = write_temp("import_invalid.csv", "id\nabc\n");
+    let (mut handler, _) = recording_mock(&[("id", "int")]);
+
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let err = import(&mut handler, &mut porter, "t", ImportOptions::default()).await.unwrap_err();
+    std::fs::remove_file(path).unwrap();
+
+    assert!(matches!(*err.downcast::<PipelineError>().unwrap(), PipelineError::InvalidValue(_)));
+}
+
+#[tokio::test]
+async fn import_missing_table_test() {
+    let path = write_temp("import_missing.csv", "id\n1\n");
+    let (mut handler, _) = recording_mock(&[]);
+
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let err = import(&mut handler, &mut porter, "t", ImportOptions::default()).await.unwrap_err();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(
+        *err.downcast::<PipelineError>().unwrap(),
+        PipelineError::TableNotFound("t".to_string())
+    );
+}
+
+fn member_rows() -> DataRows {
+    let records = (1..=3)
+        .map(|i| {
+            DataRecord::new(vec![
+                Some(DataType::I32(i)),
+                if i == 2 { None } else { Some(DataType::String(format!("name{}", i))) },
+            ])
+        })
+        .collect();
+    DataRows::new(Some(vec!["id".to_string(), "name".to_string()]), records)
+}
+
+#[tokio::test]
+async fn export_csv_split_test() {
+    let mut handler = MockQueryHandlerMock::new();
+    handler.expect_query().returning(|_, _, _| Ok(member_rows()));
+
+    let dir = temp_path("export_csv");
+    std::fs::create_dir_all(&dir).unwrap();
+    let destination = dir.join("members.2024.csv");
+    let options = ExportOptions::new(true, "NULL", None, Some(2));
+    let exported = export(&mut handler, "SELECT * FROM members", None, &destination, None, options)
+        .await
+        .unwrap();
+
+    assert_eq!(exported, 3);
+    assert_eq!(std::fs::read_to_string(&destination).unwrap(), "id,name\n1,name1\n2,NULL\n");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("members.2024_2.csv")).unwrap(),
+        "id,name\n3,name3\n"
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn export_xlsx_test() {
+    let mut handler = MockQueryHandlerMock::new();
+    handler.expect_query().returning(|_, _, _| Ok(member_rows()));
+
+    let destination = temp_path("export.xlsx");
+    export(
+        &mut handler,
+        "SELECT * FROM members",
+        None,
+        &destination,
+        None,
+        ExportOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    let mut porter = XlsxPorter::new(&destination).unwrap();
+    porter.init_range("Sheet1").unwrap();
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    std::fs::remove_file(destination).unwrap();
+
+    assert_eq!(rows.len(), 4);
+    assert_eq!(rows[0], vec!["id", "name"]);
+    assert_eq!(rows[1], vec!["1", "name1"]);
+    assert_eq!(rows[2], vec!["2", ""]);
+}
+
+#[tokio::test]
+async fn export_compressed_csv_test() {
+    let mut handler = MockQueryHandlerMock::new();
+    handler.expect_query().returning(|_, _, _| Ok(member_rows()));
+
+    let destination = temp_path("export.csv.gz");
+    let exported = export(
+        &mut handler,
+        "SELECT * FROM members",
+        None,
+        &destination,
+        None,
+        ExportOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    let mut porter = CsvPorter::new(&destination).unwrap();
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    std::fs::remove_file(destination).unwrap();
+
+    assert_eq!(exported, 3);
+    assert_eq!(rows, vec![vec!["1", "name1"], vec!["2", ""], vec!["3", "name3"]]);
+
+    let err = export(&mut handler, "SELECT 1", None, "out.xlsx.gz", None, ExportOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        *err.downcast::<PipelineError>().unwrap(),
+        PipelineError::InvalidDestination(_)
+    ));
+}
+
+#[tokio::test]
+async fn export_json_test() {
+    let mut handler = MockQueryHandlerMock::new();
+    handler.expect_query().returning(|_, _, _| Ok(member_rows()));
+
+    let destination = temp_path("export.json");
+    let exported = export(
+        &mut handler,
+        "SELECT * FROM members",
+        None,
+        &destination,
+        None,
+        ExportOptions::default(),
+    )
+    .await
+    .unwrap();
+    let written = std::fs::read_to_string(&destination).unwrap();
+    std::fs::remove_file(destination).unwrap();
+
+    assert_eq!(exported, 3);
+    assert_eq!(
+        written,
+        "[\n{\"id\":1,\"name\":\"name1\"},\n{\"id\":2,\"name\":null},\n{\"id\":3,\"name\":\"name3\"}\n]\n"
+    );
+}
+
+#[tokio::test]
+async fn export_result_sets_test() {
+    let server = FakeTdsServer::start().await;
+    server.respond(FakeResponse::Batch(vec![
+        FakeResponse::Rows(
+            vec![("id".to_string(), DataTypeKind::I32)],
+            vec![vec![Some(DataType::I32(1))], vec![Some(DataType::I32(2))]],
+        ),
+        FakeResponse::Rows(
+            vec![
+                ("code".to_string(), DataTypeKind::String),
+                ("qty".to_string(), DataTypeKind::I32),
+            ],
+            vec![vec![Some(DataType::String("A".to_string())), Some(DataType::I32(3))]],
+        ),
+    ]));
+    let mut handler = SqlServerHandler::from_config(server.config("sales")).unwrap();
+    handler.connect().await.unwrap();
+
+    let dir = temp_path("export_result_sets");
+    std::fs::create_dir_all(&dir).unwrap();
+    let destination = dir.join("sales.csv");
+    let exported = export(
+        &mut handler,
+        "SELECT id FROM orders; SELECT code, qty FROM items",
+        None,
+        &destination,
+        None,
+        ExportOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(exported, 3);
+    assert_eq!(std::fs::read_to_string(&destination).unwrap(), "id\n1\n2\n");
+    assert_eq!(std::fs::read_to_string(dir.join("sales_2.csv")).unwrap(), "code,qty\nA,3\n");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn export_unknown_format_test() {
+    let mut handler = MockQueryHandlerMock::new();
+    let err = export(&mut handler, "SELECT 1", None, "out.unknown", None, ExportOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        *err.downcast::<PipelineError>().unwrap(),
+        PipelineError::InvalidDestination(_)
+    ));
+}
+
+#[test]
+fn infer_schema_test() {
+    let path = write_temp(
+        "infer_schema.csv",
+        "id,code,amount,active,joined,seen_at,note,big\n\
+         1,007,12.5,yes,2024-01-02,2024-01-02 10:00:00,héllo,3000000000\n\
+         2,010,-3.25,no,2024-02-29,2024-01-03 11:30:00.5,,-1\n\
+         300,123,100,Y,2024-12-31,2024-01-04 00:00:00,world,7\n",
+    );
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let schema = infer_schema(&mut porter, InferOptions::default()).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(schema.rows_scanned, 3);
+    let types: Vec<&InferredType> = schema.columns.iter().map(|c| &c.data_type).collect();
+    assert_eq!(
+        types,
+        vec![
+            &InferredType::SmallInt,
+            &InferredType::Text { max_length: 3, unicode: false },
+            &InferredType::Decimal { precision: 5, scale: 2 },
+            &InferredType::Bool,
+            &InferredType::Date { format: "%Y-%m-%d" },
+            &InferredType::DateTime { format: "%Y-%m-%d %H:%M:%S%.f", date_format: None },
+            &InferredType::Text { max_length: 5, unicode: true },
+            &InferredType::BigInt,
+        ]
+    );
+    assert!(!schema.columns[0].nullable);
+    assert!(schema.columns[6].nullable);
+
+    let ddl = schema.to_ddl("dbo.my staging", SqlDialect::SqlServer);
+    assert!(ddl.starts_with("CREATE TABLE [dbo].[my staging] (\n    [id] smallint NOT NULL,\n"));
+    assert!(ddl.contains("    [note] nvarchar(5) NULL,\n"));
+    assert!(ddl.ends_with("    [big] bigint NOT NULL\n);\n"));
+    let ddl = schema.to_ddl("dbo.[order.lines]", SqlDialect::SqlServer);
+    assert!(ddl.starts_with("CREATE TABLE [dbo].[order.lines] (\n"));
+    let ddl = schema.to_ddl("[a]]b].x", SqlDialect::SqlServer);
+    assert!(ddl.starts_with("CREATE TABLE [a]]b].[x] (\n"));
+}
+
+#[tokio::test]
+async fn infer_then_import_test() {
+    let path = write_temp(
+        "infer_import.csv",
+        "id,placed,amount,shipped\n1,25/12/2024,1.50,31.12.2024\n2,01/02/2024,20.25,01.02.2024\n",
+    );
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let schema = infer_schema(&mut porter, InferOptions::default()).unwrap();
+    assert_eq!(schema.columns[1].data_type, InferredType::Date { format: "%d/%m/%Y" });
+    assert_eq!(schema.columns[3].data_type, InferredType::Date { format: "%d.%m.%Y" });
+
+    //NOTE: the catalog reports type names without their length or precision.
+    let sql_types: Vec<(String, String)> = schema
+        .columns
+        .iter()
+        .map(|column| {
+            let sql_type = column.data_type.sql_type(SqlDialect::SqlServer);
+            (column.name.clone(), sql_type.split('(').next().unwrap().to_string())
+        })
+        .collect();
+    let sql_types: Vec<(&str, &str)> =
+        sql_types.iter().map(|(name, sql_type)| (name.as_str(), sql_type.as_str())).collect();
+    let (mut handler, executed) = recording_mock(&sql_types);
+
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let mut options = ImportOptions::default();
+    options.set_date_formats(schema.date_formats());
+    let imported = import(&mut handler, &mut porter, "dbo.orders", options).await.unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(imported, 2);
+    let date = |text: &str| DataType::DateTime(chrono::DateTime::parse_from_rfc3339(text).unwrap());
+    let executed = executed.lock().unwrap();
+    assert_eq!(
+        executed[0].1,
+        vec![
+            DataType::U8(1),
+            date("2024-12-25T00:00:00Z"),
+            DataType::Decimal(Decimal::new(150, 2)),
+            date("2024-12-31T00:00:00Z"),
+            DataType::U8(2),
+            date("2024-02-01T00:00:00Z"),
+            DataType::Decimal(Decimal::new(2025, 2)),
+            date("2024-02-01T00:00:00Z"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn infer_then_import_mixed_dates_test() {
+    let path = write_temp(
+        "infer_import_mixed.csv",
+        "placed\n2024-01-02\n2024-01-03 10:00:00\n2024-01-04\n",
+    );
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let schema = infer_schema(&mut porter, InferOptions::default()).unwrap();
+    assert_eq!(
+        schema.columns[0].data_type,
+        InferredType::DateTime { format: "%Y-%m-%d %H:%M:%S%.f", date_format: Some("%Y-%m-%d") }
+    );
+
+    let (mut handler, executed) = recording_mock(&[("placed", "datetime2")]);
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let mut options = ImportOptions::default();
+    options.set_date_formats(schema.date_formats());
+    let imported = import(&mut handler, &mut porter, "dbo.orders", options).await.unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(imported, 3);
+    let date = |text: &str| DataType::DateTime(chrono::DateTime::parse_from_rfc3339(text).unwrap());
+    let executed = executed.lock().unwrap();
+    assert_eq!(
+        executed[0].1,
+        vec![
+            date("2024-01-02T00:00:00Z"),
+            date("2024-01-03T10:00:00Z"),
+            date("2024-01-04T00:00:00Z")
+        ]
+    );
+}
+
+#[test]
+fn infer_schema_sample_test() {
+    let path = write_temp("infer_sample.csv", "value\n1\n2\nnot a number\n");
+    let mut porter = CsvPorter::new(&path).unwrap();
+    let schema = infer_schema(&mut porter, InferOptions::new(Some(2))).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(schema.rows_scanned, 2);
+    assert_eq!(schema.columns[0].data_type, InferredType::TinyInt);
+}
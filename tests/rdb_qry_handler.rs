@@ -1,6 +1,27 @@
+mod fake_tds;
 mod shared;
+use chrono::DateTime;
+use fake_tds::{FakeLogin, FakeRequest, FakeResponse, FakeTdsServer};
 use shared::*;
-use tabularuq::rdb_qry_handler::{error::QueryHandleError, DataRows, QueryHandler};
+use std::{path::Path, sync::Arc};
+use tabularuq::rdb_qry_handler::{
+    DataRecord, DataRows, DataSourceInform, QueryHandler, QueryResult,
+    bulk::{BulkLoadOptions, ColumnMapping},
+    catalog::Catalog,
+    datatype::{DataType, DataTypeKind, Decimal},
+    dyn_handler::BoxedQueryHandler,
+    error::QueryHandleError,
+    memory::{ExecutedStatement, InMemoryHandler, MemoryResponse, SqlPattern},
+    qry_handler_from_dsi,
+    schema::{ColumnInfo, IndexInfo, PrimaryKey, SchemaInspector, TableInfo, TableKind},
+    secret::{KeyfileSecretStore, Secret, SecretProvider, interpolate},
+    sqlserver::{
+        SqlServerAuth, SqlServerConnectionConfig, SqlServerCredentials, SqlServerEncryption,
+        SqlServerHandler, SqlServerTlsConfig,
+    },
+    statement::{PreparedStatement, ReleaseQueue},
+};
+use tabularuq::tabular_porter::{TabularCursor, csv::CsvPorter};
 
 #[tokio::test]
 async fn mock_connect_test() {
@@ -82,3 +103,1050 @@ async fn mock_close_test() {
         QueryHandleError::Unknown("Unknown".to_string())
     );
 }
+
+#[tokio::test]
+async fn mock_prepare_test() {
+    let mut handler_ok_mock = MockQueryHandlerMock::new();
+    handler_ok_mock
+        .expect_prepare()
+        .returning(|query, param_types| Ok(PreparedStatement::new(1, query, param_types, None)));
+    handler_ok_mock
+        .expect_execute_prepared()
+        .returning(|_, _| Box::pin(async { Ok(MockQueryResultMock::new()) }));
+    handler_ok_mock.expect_unprepare().returning(|_| Ok(()));
+
+    let statement = handler_ok_mock
+        .prepare("INSERT INTO table VALUES (@P1)", Arc::from([DataTypeKind::I32]))
+        .await
+        .unwrap();
+    assert_eq!(statement.handle(), 1);
+    let ok_result =
+        handler_ok_mock.execute_prepared(&statement, Some(Arc::from([DataType::I32(1)]))).await;
+    assert!(ok_result.is_ok());
+    assert!(handler_ok_mock.unprepare(statement).await.is_ok());
+}
+
+#[test]
+fn prepared_statement_check_binds_test() {
+    let statement = PreparedStatement::new(
+        1,
+        "INSERT INTO table VALUES (@P1, @P2)",
+        Arc::from([DataTypeKind::I32, DataTypeKind::String]),
+        None,
+    );
+
+    assert!(
+        statement.check_binds(Some(&[DataType::I32(1), DataType::String("a".to_string())])).is_ok()
+    );
+    assert!(matches!(
+        statement.check_binds(Some(&[DataType::I32(1)])),
+        Err(QueryHandleError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        statement.check_binds(Some(&[DataType::I64(1), DataType::String("a".to_string())])),
+        Err(QueryHandleError::InvalidParameter(_))
+    ));
+    assert!(matches!(statement.check_binds(None), Err(QueryHandleError::InvalidParameter(_))));
+}
+
+#[test]
+fn prepared_statement_release_on_drop_test() {
+    let queue = ReleaseQueue::default();
+    let statement = PreparedStatement::new(
+        7,
+        "SELECT @P1",
+        Arc::from([DataTypeKind::I32]),
+        Some(queue.clone()),
+    );
+    drop(statement);
+    assert_eq!(*queue.lock().unwrap(), vec![7]);
+}
+
+#[test]
+fn data_type_kind_parse_test() {
+    assert_eq!(DataTypeKind::I32.parse(" 42 "), Some(DataType::I32(42)));
+    assert_eq!(DataTypeKind::U8.parse("256"), None);
+    assert_eq!(DataTypeKind::Bool.parse("Yes"), Some(DataType::Bool(true)));
+    assert_eq!(DataTypeKind::Bytes.parse("0x0aFF"), Some(DataType::Bytes(vec![0x0a, 0xff])));
+    assert_eq!(DataTypeKind::String.parse(" a "), Some(DataType::String(" a ".to_string())));
+
+    let date_time = DataTypeKind::DateTime.parse("2024-03-01 12:30:00").unwrap();
+    assert_eq!(date_time.to_string(), "2024-03-01T12:30:00+00:00");
+    let date_time = DataTypeKind::DateTime.parse("2024-03-01T12:30:00+09:00").unwrap();
+    assert_eq!(date_time.to_string(), "2024-03-01T12:30:00+09:00");
+    assert_eq!(
+        DataTypeKind::DateTime.parse("2024/03/01").unwrap().to_string(),
+        "2024-03-01T00:00:00+00:00"
+    );
+    assert_eq!(
+        DataTypeKind::DateTime.parse("13:45:30.5").unwrap().to_string(),
+        "1900-01-01T13:45:30.500+00:00"
+    );
+    assert_eq!(DataTypeKind::DateTime.parse("not a date"), None);
+
+    assert_eq!(DataTypeKind::Decimal.parse("-0.05"), Some(DataType::Decimal(Decimal::new(-5, 2))));
+    assert_eq!(DataTypeKind::Decimal.parse("1.5E2"), Some(DataType::Decimal(Decimal::new(150, 0))));
+    let exact = DataTypeKind::Decimal.parse("12345678901234567890.12").unwrap();
+    assert_eq!(exact.to_string(), "12345678901234567890.12");
+    assert_eq!(Decimal::new(12345, 3).rescale(2), Some(Decimal::new(1235, 2)));
+    assert_eq!(DataTypeKind::Decimal.parse("1.2.3"), None);
+    assert_eq!(DataTypeKind::from_sql_type("money"), DataTypeKind::Decimal);
+}
+
+#[test]
+fn column_mapping_test() {
+    let mapping = ColumnMapping::by_headers(&["id".to_string(), "Name".to_string()]);
+    assert_eq!(mapping.source_index("ID"), Some(0));
+    assert_eq!(mapping.source_index("name"), Some(1));
+    assert_eq!(mapping.source_index("missing"), None);
+
+    let mapping = ColumnMapping::new(vec![(2, "amount".to_string())]);
+    assert_eq!(mapping.source_index("amount"), Some(2));
+}
+
+#[test]
+fn column_info_declared_type_test() {
+    let column = |type_name: &str, max_length, precision, scale| ColumnInfo {
+        name: "c".to_string(),
+        type_name: type_name.to_string(),
+        max_length,
+        precision,
+        scale,
+        nullable: true,
+        default: None,
+        identity: false,
+    };
+
+    assert_eq!(column("nvarchar", Some(50), None, None).declared_type(), "nvarchar(50)");
+    assert_eq!(column("varbinary", Some(-1), None, None).declared_type(), "varbinary(max)");
+    assert_eq!(column("decimal", None, Some(18), Some(2)).declared_type(), "decimal(18,2)");
+    assert_eq!(column("int", None, None, None).declared_type(), "int");
+    assert_eq!(column("datetime2", None, None, None).kind(), DataTypeKind::DateTime);
+}
+
+fn fake_rows(columns: &[(&str, DataTypeKind)], rows: Vec<Vec<Option<DataType>>>) -> FakeResponse {
+    let columns = columns.iter().map(|(name, kind)| (name.to_string(), *kind)).collect();
+    FakeResponse::Rows(columns, rows)
+}
+
+fn text(value: &str) -> Option<DataType> {
+    Some(DataType::String(value.to_string()))
+}
+
+#[tokio::test]
+async fn sqlserver_fake_server_schema_inspector_test() {
+    let server = FakeTdsServer::start().await;
+    let mut handler = SqlServerHandler::from_config(server.config("sales")).unwrap();
+    handler.connect().await.unwrap();
+
+    let name_columns = [
+        ("TABLE_SCHEMA", DataTypeKind::String),
+        ("TABLE_NAME", DataTypeKind::String),
+        ("TABLE_TYPE", DataTypeKind::String),
+    ];
+    server.respond(fake_rows(
+        &name_columns,
+        vec![
+            vec![text("dbo"), text("orders"), text("BASE TABLE")],
+            vec![text("dbo"), text("order_totals"), text("VIEW")],
+        ],
+    ));
+    let tables = handler.tables(Some("dbo")).await.unwrap();
+    assert_eq!(
+        tables,
+        vec![
+            TableInfo { schema: "dbo".into(), name: "orders".into(), kind: TableKind::Table },
+            TableInfo { schema: "dbo".into(), name: "order_totals".into(), kind: TableKind::View },
+        ]
+    );
+
+    server.respond(fake_rows(
+        &[
+            ("name", DataTypeKind::String),
+            ("type", DataTypeKind::String),
+            ("max_length", DataTypeKind::I32),
+            ("precision", DataTypeKind::U8),
+            ("scale", DataTypeKind::U8),
+            ("is_nullable", DataTypeKind::Bool),
+            ("default", DataTypeKind::String),
+            ("is_identity", DataTypeKind::Bool),
+        ],
+        vec![
+            vec![
+                text("id"),
+                text("int"),
+                None,
+                None,
+                None,
+                Some(DataType::Bool(false)),
+                None,
+                Some(DataType::Bool(true)),
+            ],
+            vec![
+                text("amount"),
+                text("decimal"),
+                None,
+                Some(DataType::U8(18)),
+                Some(DataType::U8(2)),
+                Some(DataType::Bool(true)),
+                text("((0))"),
+                Some(DataType::Bool(false)),
+            ],
+        ],
+    ));
+    let columns = handler.columns("dbo.orders").await.unwrap();
+    assert_eq!(columns[0].declared_type(), "int");
+    assert!(!columns[0].nullable && columns[0].identity);
+    assert_eq!(columns[1].declared_type(), "decimal(18,2)");
+    assert_eq!(columns[1].default.as_deref(), Some("((0))"));
+
+    let key_columns = [("name", DataTypeKind::String), ("column", DataTypeKind::String)];
+    server.respond(fake_rows(
+        &key_columns,
+        vec![vec![text("PK_orders"), text("id")], vec![text("PK_orders"), text("line")]],
+    ));
+    let primary_key = handler.primary_key("dbo.orders").await.unwrap();
+    assert_eq!(
+        primary_key,
+        Some(PrimaryKey { name: "PK_orders".into(), columns: vec!["id".into(), "line".into()] })
+    );
+
+    server.respond(fake_rows(
+        &[
+            ("name", DataTypeKind::String),
+            ("schema", DataTypeKind::String),
+            ("table", DataTypeKind::String),
+            ("column", DataTypeKind::String),
+            ("referenced", DataTypeKind::String),
+        ],
+        vec![
+            vec![
+                text("FK_customer"),
+                text("crm"),
+                text("customers"),
+                text("customer_id"),
+                text("id"),
+            ],
+            vec![text("FK_item"), text("dbo"), text("items"), text("item_id"), text("id")],
+            vec![text("FK_item"), text("dbo"), text("items"), text("item_rev"), text("rev")],
+        ],
+    ));
+    let foreign_keys = handler.foreign_keys("dbo.orders").await.unwrap();
+    assert_eq!(foreign_keys.len(), 2);
+    assert_eq!(foreign_keys[0].referenced_schema, "crm");
+    assert_eq!(foreign_keys[1].columns, vec!["item_id".to_string(), "item_rev".to_string()]);
+    assert_eq!(foreign_keys[1].referenced_columns, vec!["id".to_string(), "rev".to_string()]);
+
+    server.respond(fake_rows(
+        &[
+            ("name", DataTypeKind::String),
+            ("is_unique", DataTypeKind::Bool),
+            ("is_primary_key", DataTypeKind::Bool),
+            ("clustered", DataTypeKind::Bool),
+            ("column", DataTypeKind::String),
+        ],
+        vec![
+            vec![
+                text("IX_placed"),
+                Some(DataType::Bool(false)),
+                Some(DataType::Bool(false)),
+                Some(DataType::Bool(false)),
+                text("placed_at"),
+            ],
+            vec![
+                text("PK_orders"),
+                Some(DataType::Bool(true)),
+                Some(DataType::Bool(true)),
+                Some(DataType::Bool(true)),
+                text("id"),
+            ],
+            vec![
+                text("PK_orders"),
+                Some(DataType::Bool(true)),
+                Some(DataType::Bool(true)),
+                Some(DataType::Bool(true)),
+                text("line"),
+            ],
+        ],
+    ));
+    let indexes = handler.indexes("dbo.orders").await.unwrap();
+    assert_eq!(
+        indexes[1],
+        IndexInfo {
+            name: "PK_orders".into(),
+            columns: vec!["id".into(), "line".into()],
+            unique: true,
+            primary_key: true,
+            clustered: true,
+        }
+    );
+    assert!(!indexes[0].unique);
+
+    let requests = server.requests();
+    assert!(requests[0].sql.contains("FROM INFORMATION_SCHEMA.TABLES WHERE TABLE_SCHEMA = @P1"));
+    assert_eq!(requests[0].params, vec![text("dbo")]);
+    let catalogs =
+        ["sys.columns c", "sys.key_constraints k", "sys.foreign_keys fk", "sys.indexes i"];
+    for (request, catalog) in requests[1..].iter().zip(catalogs) {
+        assert!(request.sql.contains(&format!("FROM {} ", catalog)), "{}", request.sql);
+        assert_eq!(request.params, vec![text("dbo.orders")]);
+    }
+
+    handler.close().await.unwrap();
+}
+
+#[test]
+fn sqlserver_tls_config_test() {
+    let config: SqlServerConnectionConfig = toml::from_str(
+        r#"
+        host = "db.internal"
+        database = "sales"
+        username = "loader"
+        password = "secret"
+        encryption = "login-only"
+        ca_certificate = "/etc/ssl/corp-ca.pem"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.tls().encryption(), Some(SqlServerEncryption::LoginOnly));
+    assert_eq!(config.tls().trust_server_certificate(), None);
+    assert_eq!(config.tls().ca_certificate(), Some(Path::new("/etc/ssl/corp-ca.pem")));
+    assert!(SqlServerHandler::from_config(config).is_ok());
+
+    let tls = SqlServerTlsConfig::new(
+        Some(SqlServerEncryption::Required),
+        Some(true),
+        Some("/etc/ssl/corp-ca.pem".into()),
+    );
+    let config = SqlServerConnectionConfig::new(
+        Some("encrypt=false".to_string()),
+        "db.internal".to_string(),
+        None,
+        "sales".to_string(),
+        SqlServerCredentials::sql("loader".to_string(), "secret".to_string()),
+        tls,
+    );
+    let err = SqlServerHandler::from_config(config).err().unwrap();
+    assert!(matches!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::InvalidParameter(_)
+    ));
+}
+
+struct StubSecretProvider(Vec<(&'static str, &'static str)>);
+
+impl SecretProvider for StubSecretProvider {
+    fn secret(&self, name: &str) -> Result<Option<Secret>, Box<dyn std::error::Error>> {
+        let secret = self.0.iter().find(|(key, _)| *key == name);
+        Ok(secret.map(|(_, value)| Secret::new(value.to_string())))
+    }
+}
+
+#[test]
+fn secret_interpolation_test() {
+    let provider = StubSecretProvider(vec![("db_host", "db.internal")]);
+    let config = write_temp(
+        "secret_datasource.toml",
+        r#"
+        driver = "sqlserver"
+        [sqlserver]
+        ado_string = "Application Name=$${app};Password=hunter2"
+        host = "${secret:db_host}"
+        database = "${CARGO_PKG_NAME}"
+        username = "loader"
+        password = "pre-${secret:db_host}"
+        "#,
+    );
+
+    let debug =
+        format!("{:?}", DataSourceInform::from_file_with_provider(&config, &provider).unwrap());
+    assert!(debug.contains("host: \"db.internal\""));
+    assert!(debug.contains("database: \"tabularuq\""));
+    assert!(debug.contains("Application Name=${app};Password=***"));
+    assert!(!debug.contains("hunter2"));
+    assert!(!debug.contains("pre-db.internal"));
+
+    let err = DataSourceInform::from_file(&config).unwrap_err();
+    assert_eq!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::SecretNotFound("secret:db_host".to_string())
+    );
+    let err = interpolate("${TABULARUQ_TEST_UNSET_VARIABLE}", Some(&provider)).err().unwrap();
+    assert_eq!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::SecretNotFound("TABULARUQ_TEST_UNSET_VARIABLE".to_string())
+    );
+    std::fs::remove_file(config).unwrap();
+}
+
+#[test]
+fn keyfile_secret_store_test() {
+    let key = temp_path("secret_store.key");
+    let store_path = temp_path("secret_store.toml");
+    KeyfileSecretStore::generate_key(&key).unwrap();
+    assert!(KeyfileSecretStore::generate_key(&key).is_err());
+
+    let store = KeyfileSecretStore::open(&store_path, &key).unwrap();
+    store.set("sales_password", &Secret::new("hunter2".to_string())).unwrap();
+    assert_eq!(store.names().unwrap(), vec!["sales_password".to_string()]);
+    assert!(!std::fs::read_to_string(&store_path).unwrap().contains("hunter2"));
+    assert_eq!(store.secret("sales_password").unwrap().unwrap().expose(), "hunter2");
+    assert!(store.secret("missing").unwrap().is_none());
+    assert_eq!(
+        interpolate("${secret:sales_password}", Some(&store)).unwrap(),
+        "hunter2".to_string()
+    );
+
+    std::fs::remove_file(&key).unwrap();
+    KeyfileSecretStore::generate_key(&key).unwrap();
+    let other = KeyfileSecretStore::open(&store_path, &key).unwrap();
+    let err = other.secret("sales_password").err().unwrap();
+    assert!(matches!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::InvalidSecret(_)
+    ));
+    std::fs::remove_file(key).unwrap();
+    std::fs::remove_file(store_path).unwrap();
+}
+
+#[test]
+fn sqlserver_password_file_test() {
+    let password_file = temp_path("secret_password");
+    std::fs::write(&password_file, "hunter2\n").unwrap();
+    let config: SqlServerConnectionConfig = toml::from_str(&format!(
+        r#"
+        host = "db.internal"
+        database = "sales"
+        username = "loader"
+        password_file = "{}"
+        "#,
+        password_file.display()
+    ))
+    .unwrap();
+    assert!(SqlServerHandler::from_config(config).is_ok());
+
+    let config: SqlServerConnectionConfig = toml::from_str(&format!(
+        r#"
+        host = "db.internal"
+        database = "sales"
+        username = "loader"
+        password = "hunter2"
+        password_file = "{}"
+        "#,
+        password_file.display()
+    ))
+    .unwrap();
+    assert!(!format!("{:?}", config).contains("hunter2"));
+    let err = SqlServerHandler::from_config(config).err().unwrap();
+    assert!(matches!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::InvalidParameter(_)
+    ));
+    std::fs::remove_file(password_file).unwrap();
+}
+
+#[test]
+fn catalog_profile_test() {
+    let path = temp_path("secret_catalog.toml");
+    std::fs::write(
+        &path,
+        r#"
+        [datasource.prod]
+        driver = "sqlserver"
+        [datasource.prod.sqlserver]
+        host = "prod.internal"
+        database = "sales"
+        username = "loader"
+        password = "hunter2"
+        encryption = "required"
+
+        [datasource.staging]
+        extends = "prod"
+        [datasource.staging.sqlserver]
+        host = "staging.internal"
+        password_file = "/run/secrets/staging"
+
+        [datasource.reporting]
+        extends = "prod"
+        [datasource.reporting.sqlserver]
+        auth = "aad_token"
+        token = "eyJ0"
+
+        [datasource.loop_a]
+        extends = "loop_b"
+        [datasource.loop_b]
+        extends = "loop_a"
+        "#,
+    )
+    .unwrap();
+
+    let catalog = Catalog::from_file(&path).unwrap();
+    assert_eq!(catalog.names(), vec!["loop_a", "loop_b", "prod", "reporting", "staging"]);
+    let staging = format!("{:?}", catalog.inform("staging").unwrap());
+    assert!(staging.contains("host: \"staging.internal\""));
+    assert!(staging.contains("database: \"sales\""));
+    assert!(staging.contains("encryption: Some(Required)"));
+    assert!(staging.contains("password: None"));
+    assert!(staging.contains("password_file: Some(\"/run/secrets/staging\")"));
+    let reporting = format!("{:?}", catalog.inform("reporting").unwrap());
+    assert!(reporting.contains("auth: Some(AadToken)"));
+    assert!(reporting.contains("username: None"));
+    assert!(reporting.contains("password: None"));
+    assert!(reporting.contains("host: \"prod.internal\""));
+    for name in ["staging", "reporting"] {
+        assert!(qry_handler_from_dsi(catalog.inform(name).unwrap()).is_ok());
+    }
+
+    let err = catalog.inform("qa").err().unwrap();
+    assert_eq!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::DataSourceNotFound("qa".to_string())
+    );
+    let err = catalog.inform("loop_a").err().unwrap();
+    assert_eq!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::InvalidParameter(
+            "Datasources extend each other: loop_a -> loop_b -> loop_a".to_string()
+        )
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn boxed_query_handler_test() {
+    let mut mock = MockQueryHandlerMock::new();
+    mock.expect_query().returning(|query, _, _| {
+        assert_eq!(query, "SELECT 1");
+        Ok(DataRows::new(Some(vec!["one".to_string()]), Vec::new()))
+    });
+    mock.expect_mutate().returning(|_, _| {
+        Box::pin(async {
+            let mut result = MockQueryResultMock::new();
+            result.expect_affected_rows().return_const(3u64);
+            Ok(result)
+        })
+    });
+    mock.expect_schemas().returning(|| Ok(vec!["dbo".to_string()]));
+    mock.expect_close().returning(|| Ok(()));
+
+    let mut handler: BoxedQueryHandler = Box::new(mock);
+    let rows = handler.query("SELECT 1", None, Box::new(|_, _| true)).await.unwrap();
+    assert_eq!(rows.column_meta(), Some(&["one".to_string()][..]));
+    assert_eq!(handler.mutate("DELETE FROM orders", None).await.unwrap().affected_rows(), 3);
+    assert_eq!(handler.schemas().await.unwrap(), vec!["dbo".to_string()]);
+    assert!(handler.close().await.is_ok());
+
+    let err =
+        qry_handler_from_dsi(DataSourceInform::new("oracle".to_string(), None)).err().unwrap();
+    assert_eq!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::InvalidParameter("Not supported driver: oracle".to_string())
+    );
+    let err =
+        qry_handler_from_dsi(DataSourceInform::new("sqlserver".to_string(), None)).err().unwrap();
+    assert!(matches!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::InvalidParameter(_)
+    ));
+}
+
+#[test]
+fn sqlserver_auth_test() {
+    let token_file = temp_path("secret_token");
+    std::fs::write(&token_file, "eyJ0eXAi\n").unwrap();
+    let config: SqlServerConnectionConfig = toml::from_str(&format!(
+        r#"
+        host = "sales.database.windows.net"
+        database = "sales"
+        auth = "aad_token"
+        token_file = "{}"
+        "#,
+        token_file.display()
+    ))
+    .unwrap();
+    assert_eq!(config.credentials().auth(), Some(SqlServerAuth::AadToken));
+    assert_eq!(config.credentials().username(), None);
+    assert!(SqlServerHandler::from_config(config).is_ok());
+    std::fs::remove_file(token_file).unwrap();
+
+    let config: SqlServerConnectionConfig = toml::from_str(
+        r#"
+        ado_string = "User Id=loader;Password=hunter2"
+        host = "db.internal"
+        database = "sales"
+        "#,
+    )
+    .unwrap();
+    assert!(SqlServerHandler::from_config(config).is_ok());
+
+    #[allow(unused_mut)]
+    let mut invalid = vec![SqlServerConnectionConfig::new(
+        None,
+        "db.internal".to_string(),
+        None,
+        "sales".to_string(),
+        SqlServerCredentials::default(),
+        SqlServerTlsConfig::default(),
+    )];
+    #[cfg(not(feature = "integrated-auth"))]
+    invalid.push(SqlServerConnectionConfig::new(
+        None,
+        "db.internal".to_string(),
+        None,
+        "sales".to_string(),
+        SqlServerCredentials::integrated(),
+        SqlServerTlsConfig::default(),
+    ));
+    for config in invalid {
+        let err = SqlServerHandler::from_config(config).err().unwrap();
+        assert!(matches!(
+            *err.downcast::<QueryHandleError>().unwrap(),
+            QueryHandleError::InvalidParameter(_)
+        ));
+    }
+}
+
+#[tokio::test]
+async fn sqlserver_fake_server_query_test() {
+    let server = FakeTdsServer::start().await;
+    let mut handler = SqlServerHandler::from_config(server.config("sales")).unwrap();
+    handler.connect().await.unwrap();
+    assert_eq!(
+        server.logins(),
+        vec![FakeLogin { username: "tester".to_string(), database: "sales".to_string() }]
+    );
+
+    let created = DateTime::parse_from_rfc3339("2024-03-01T09:30:15Z").unwrap();
+    server.respond(FakeResponse::Rows(
+        vec![
+            ("id".to_string(), DataTypeKind::I32),
+            ("name".to_string(), DataTypeKind::String),
+            ("amount".to_string(), DataTypeKind::I128),
+            ("active".to_string(), DataTypeKind::Bool),
+            ("created".to_string(), DataTypeKind::DateTime),
+        ],
+        vec![
+            vec![
+                Some(DataType::I32(1)),
+                Some(DataType::String("alpha".to_string())),
+                Some(DataType::I128(-1200)),
+                Some(DataType::Bool(true)),
+                Some(DataType::DateTime(created)),
+            ],
+            vec![Some(DataType::I32(2)), None, None, Some(DataType::Bool(false)), None],
+        ],
+    ));
+
+    let since = DateTime::parse_from_rfc3339("2024-01-01T00:00:00+09:00").unwrap();
+    let bind_variables: Arc<[DataType]> = Arc::from([
+        DataType::String("alpha".to_string()),
+        DataType::I64(7),
+        DataType::U16(8),
+        DataType::F64(1.5),
+        DataType::Bool(true),
+        DataType::I128(99),
+        DataType::Bytes(vec![1, 2, 3]),
+        DataType::DateTime(since),
+    ]);
+    let rows = handler
+        .query(
+            "SELECT id, name, amount, active, created FROM sales WHERE name = @P1",
+            Some(bind_variables),
+            Box::new(|_, _| true),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        server.requests(),
+        vec![FakeRequest {
+            sql: "SELECT id, name, amount, active, created FROM sales WHERE name = @P1".to_string(),
+            params: vec![
+                Some(DataType::String("alpha".to_string())),
+                Some(DataType::I64(7)),
+                Some(DataType::I32(8)),
+                Some(DataType::F64(1.5)),
+                Some(DataType::Bool(true)),
+                Some(DataType::I128(99)),
+                Some(DataType::Bytes(vec![1, 2, 3])),
+                Some(DataType::DateTime(since)),
+            ],
+        }]
+    );
+    let columns: Vec<&str> = rows.column_meta().unwrap().iter().map(|c| c.as_str()).collect();
+    assert_eq!(columns, vec!["id", "name", "amount", "active", "created"]);
+    assert_eq!(rows.records().len(), 2);
+    assert_eq!(
+        rows.records()[0].cells(),
+        &[
+            Some(DataType::I32(1)),
+            Some(DataType::String("alpha".to_string())),
+            Some(DataType::I128(-1200)),
+            Some(DataType::Bool(true)),
+            Some(DataType::DateTime(created)),
+        ]
+    );
+    assert_eq!(
+        rows.records()[1].cells(),
+        &[Some(DataType::I32(2)), None, None, Some(DataType::Bool(false)), None]
+    );
+
+    handler.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn sqlserver_fake_server_mutate_test() {
+    let server = FakeTdsServer::start().await;
+    let mut handler = SqlServerHandler::from_config(server.config("sales")).unwrap();
+    handler.connect().await.unwrap();
+
+    server.respond(FakeResponse::Affected(3));
+    let affected = handler
+        .mutate(
+            "UPDATE sales SET name = @P1",
+            Some(Arc::from([DataType::String("x".repeat(5000))])),
+        )
+        .await
+        .unwrap()
+        .affected_rows();
+    assert_eq!(affected, 3);
+    assert_eq!(server.requests()[0].params, vec![Some(DataType::String("x".repeat(5000)))]);
+
+    server.respond(FakeResponse::Error(208, "Invalid object name 'missing'.".to_string()));
+    let err = handler
+        .mutate("DELETE FROM missing", None)
+        .await
+        .map(|result| result.affected_rows())
+        .unwrap_err();
+    assert!(err.to_string().contains("Invalid object name 'missing'."));
+
+    server.respond(FakeResponse::Error(208, "Invalid object name 'missing'.".to_string()));
+    let err =
+        handler.query("SELECT * FROM missing", None, Box::new(|_, _| true)).await.unwrap_err();
+    assert!(err.to_string().contains("Invalid object name 'missing'."));
+
+    handler.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn sqlserver_fake_server_prepare_test() {
+    let server = FakeTdsServer::start().await;
+    let mut handler = SqlServerHandler::from_config(server.config("sales")).unwrap();
+    handler.connect().await.unwrap();
+
+    server.respond(FakeResponse::Rows(
+        vec![("".to_string(), DataTypeKind::I32)],
+        vec![vec![Some(DataType::I32(5))]],
+    ));
+    let statement = handler
+        .prepare("INSERT INTO sales VALUES (@P1)", Arc::from([DataTypeKind::I32]))
+        .await
+        .unwrap();
+    assert_eq!(statement.handle(), 5);
+
+    server.respond(FakeResponse::Affected(1));
+    let affected = handler
+        .execute_prepared(&statement, Some(Arc::from([DataType::I32(10)])))
+        .await
+        .unwrap()
+        .affected_rows();
+    assert_eq!(affected, 1);
+    handler.unprepare(statement).await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(
+        requests[0].params[1],
+        Some(DataType::String("INSERT INTO sales VALUES (@P1)".to_string()))
+    );
+    assert_eq!(requests[1].sql, "EXEC sp_execute @P1, @P2");
+    assert_eq!(requests[1].params, vec![Some(DataType::I32(5)), Some(DataType::I32(10))]);
+    assert_eq!(requests[2].sql, "EXEC sp_unprepare @P1");
+    assert_eq!(requests[2].params, vec![Some(DataType::I32(5))]);
+
+    handler.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn sqlserver_fake_server_release_dropped_test() {
+    let server = FakeTdsServer::start().await;
+    let mut handler = SqlServerHandler::from_config(server.config("sales")).unwrap();
+    handler.connect().await.unwrap();
+
+    let mut statements = Vec::new();
+    for handle in [5, 6] {
+        server.respond(FakeResponse::Rows(
+            vec![("".to_string(), DataTypeKind::I32)],
+            vec![vec![Some(DataType::I32(handle))]],
+        ));
+        statements.push(handler.prepare("DELETE FROM sales", Arc::from([])).await.unwrap());
+    }
+    drop(statements);
+
+    server.respond(FakeResponse::Error(8179, "Could not find prepared statement".to_string()));
+    let err = handler.prepare("DELETE FROM sales", Arc::from([])).await.err().unwrap();
+    assert!(err.to_string().contains("Statement 5 cannot be released"));
+    server.respond(FakeResponse::Affected(0));
+    server.respond(FakeResponse::Rows(
+        vec![("".to_string(), DataTypeKind::I32)],
+        vec![vec![Some(DataType::I32(7))]],
+    ));
+    let statement = handler.prepare("DELETE FROM sales", Arc::from([])).await.unwrap();
+    handler.unprepare(statement).await.unwrap();
+
+    let released: Vec<Option<DataType>> = server
+        .requests()
+        .iter()
+        .filter(|request| request.sql == "EXEC sp_unprepare @P1")
+        .map(|request| request.params[0].clone())
+        .collect();
+    let handles = [5, 6, 5, 7].map(|handle| Some(DataType::I32(handle)));
+    assert_eq!(released, handles);
+    handler.close().await.unwrap();
+}
+
+#[test]
+fn default_prepare_test() {
+    struct PlainHandler;
+    struct NoRows;
+
+    impl QueryResult for NoRows {
+        fn affected_rows(&self) -> u64 {
+            0
+        }
+    }
+
+    impl QueryHandler for PlainHandler {
+        type ConnectionConfig = ();
+
+        fn from_config(_: ()) -> Result<Self, Box<dyn std::error::Error>> {
+            Ok(PlainHandler)
+        }
+
+        async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+
+        async fn query(
+            &mut self,
+            _: &str,
+            _: Option<Arc<[DataType]>>,
+            _: tabularuq::rdb_qry_handler::FetchMore,
+        ) -> Result<DataRows, Box<dyn std::error::Error>> {
+            Ok(DataRows::new(None, Vec::new()))
+        }
+
+        async fn mutate(
+            &mut self,
+            _: &str,
+            _: Option<Arc<[DataType]>>,
+        ) -> Result<impl QueryResult, Box<dyn std::error::Error>> {
+            Ok(NoRows)
+        }
+
+        async fn close(self) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+    }
+
+    let mut handler = PlainHandler::from_config(()).unwrap();
+    let err =
+        futures::executor::block_on(handler.prepare("SELECT 1", Arc::from([]))).err().unwrap();
+    assert!(matches!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::InvalidCall(_)
+    ));
+}
+
+#[tokio::test]
+async fn sqlserver_fake_server_bulk_load_test() {
+    let server = FakeTdsServer::start().await;
+    let mut handler = SqlServerHandler::from_config(server.config("sales")).unwrap();
+    handler.connect().await.unwrap();
+
+    let catalog = |columns: &[(&str, &str, u8, u8)]| {
+        FakeResponse::Rows(
+            vec![
+                ("name".to_string(), DataTypeKind::String),
+                ("type".to_string(), DataTypeKind::String),
+                ("precision".to_string(), DataTypeKind::U8),
+                ("scale".to_string(), DataTypeKind::U8),
+            ],
+            columns
+                .iter()
+                .map(|(name, type_name, precision, scale)| {
+                    vec![
+                        Some(DataType::String(name.to_string())),
+                        Some(DataType::String(type_name.to_string())),
+                        Some(DataType::U8(*precision)),
+                        Some(DataType::U8(*scale)),
+                    ]
+                })
+                .collect(),
+        )
+    };
+    server.respond(catalog(&[
+        ("id", "int", 10, 0),
+        ("amount", "decimal", 10, 2),
+        ("due", "datetime", 23, 3),
+        ("note", "nvarchar", 0, 0),
+    ]));
+    server.respond(FakeResponse::Rows(
+        vec![
+            ("id".to_string(), DataTypeKind::I32),
+            ("amount".to_string(), DataTypeKind::Decimal),
+            ("due".to_string(), DataTypeKind::DateTime),
+            ("note".to_string(), DataTypeKind::String),
+        ],
+        Vec::new(),
+    ));
+
+    let mut porter = CsvPorter::from_bytes(
+        b"id,amount,due,note\n1,12.345,2024-03-01 09:30,alpha\n2,,2024-03-02,\n".to_vec(),
+    );
+    let mapping = ColumnMapping::by_headers(&porter.headers().unwrap().unwrap());
+    let affected = handler
+        .bulk_load_tabular("dbo.order lines", &mapping, &mut porter, BulkLoadOptions::default())
+        .await
+        .unwrap()
+        .affected_rows();
+    assert_eq!(affected, 2);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].params, vec![Some(DataType::String("[dbo].[order lines]".to_string()))]);
+    assert_eq!(requests[1].sql, "SELECT TOP 0 * FROM [dbo].[order lines]");
+    assert!(requests[2].sql.starts_with("INSERT BULK [dbo].[order lines] ("));
+    let due = |text: &str| Some(DataType::DateTime(DateTime::parse_from_rfc3339(text).unwrap()));
+    assert_eq!(
+        server.bulk_rows(),
+        vec![
+            vec![
+                Some(DataType::I32(1)),
+                Some(DataType::Decimal(Decimal::new(1235, 2))),
+                due("2024-03-01T09:30:00Z"),
+                Some(DataType::String("alpha".to_string())),
+            ],
+            vec![
+                Some(DataType::I32(2)),
+                None,
+                due("2024-03-02T00:00:00Z"),
+                Some(DataType::String(String::new())),
+            ],
+        ]
+    );
+
+    server.respond(catalog(&[("due", "smalldatetime", 16, 0)]));
+    server
+        .respond(FakeResponse::Rows(vec![("due".to_string(), DataTypeKind::DateTime)], Vec::new()));
+    let records = futures::stream::iter([DataRecord::new(vec![due("2200-01-01T00:00:00Z")])]);
+    let err = handler
+        .bulk_load_records(
+            "sales",
+            &ColumnMapping::new(vec![(0, "due".to_string())]),
+            records,
+            BulkLoadOptions::default(),
+        )
+        .await
+        .map(|result| result.affected_rows())
+        .unwrap_err();
+    assert!(err.to_string().contains("Row 1: 2200-01-01 00:00:00 +00:00 is out of range"));
+
+    for value in [DataType::F64(f64::NAN), DataType::F64(f64::INFINITY), DataType::F64(1000.0)] {
+        server.respond(catalog(&[("amount", "decimal", 5, 2)]));
+        server.respond(FakeResponse::Rows(
+            vec![("amount".to_string(), DataTypeKind::Decimal)],
+            Vec::new(),
+        ));
+        let records = futures::stream::iter([DataRecord::new(vec![Some(value)])]);
+        let err = handler
+            .bulk_load_records(
+                "sales",
+                &ColumnMapping::new(vec![(0, "amount".to_string())]),
+                records,
+                BulkLoadOptions::default(),
+            )
+            .await
+            .map(|result| result.affected_rows())
+            .unwrap_err();
+        assert!(err.to_string().contains("does not fit decimal column amount"), "{}", err);
+    }
+}
+
+#[tokio::test]
+async fn in_memory_handler_test() {
+    let handler = InMemoryHandler::new();
+    handler.respond(
+        SqlPattern::exact("SELECT id, name FROM users"),
+        MemoryResponse::Rows(DataRows::new(
+            Some(vec!["id".to_string(), "name".to_string()]),
+            vec![
+                DataRecord::new(vec![Some(DataType::I32(1)), Some(DataType::String("a".into()))]),
+                DataRecord::new(vec![Some(DataType::I32(2)), None]),
+            ],
+        )),
+    );
+    handler.respond(SqlPattern::regex(r"^UPDATE users ").unwrap(), MemoryResponse::Affected(2));
+    handler.respond(
+        SqlPattern::predicate(|sql| sql.contains("orders")),
+        MemoryResponse::Error(QueryHandleError::Unknown("orders is locked".to_string())),
+    );
+    assert!(SqlPattern::regex("(").is_err());
+
+    let mut recorder = handler.clone();
+    recorder.connect().await.unwrap();
+    let rows = recorder
+        .query("SELECT id, name FROM users", None, InMemoryHandler::default_fetch_more())
+        .await
+        .unwrap();
+    assert_eq!(rows.column_meta(), Some(&["id".to_string(), "name".to_string()][..]));
+    assert_eq!(rows.records().len(), 2);
+    let first = recorder
+        .query("SELECT id, name FROM users", None, Box::new(|_, record| record.is_none()))
+        .await
+        .unwrap();
+    assert_eq!(first.records().len(), 1);
+
+    let binds: Arc<[DataType]> = Arc::from([DataType::String("b".into()), DataType::I32(1)]);
+    let affected = recorder
+        .mutate("UPDATE users SET name = @P1 WHERE id = @P2", Some(binds.clone()))
+        .await
+        .unwrap()
+        .affected_rows();
+    assert_eq!(affected, 2);
+
+    let err = recorder
+        .mutate("DELETE FROM orders", None)
+        .await
+        .map(|result| result.affected_rows())
+        .unwrap_err();
+    assert_eq!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::Unknown("orders is locked".to_string())
+    );
+    let err =
+        recorder.query("SELECT 1", None, InMemoryHandler::default_fetch_more()).await.unwrap_err();
+    assert!(matches!(
+        *err.downcast::<QueryHandleError>().unwrap(),
+        QueryHandleError::InvalidCall(_)
+    ));
+
+    let statement = recorder
+        .prepare(
+            "UPDATE users SET name = @P1 WHERE id = @P2",
+            Arc::from([DataTypeKind::String, DataTypeKind::I32]),
+        )
+        .await
+        .unwrap();
+    let affected =
+        recorder.execute_prepared(&statement, Some(binds.clone())).await.unwrap().affected_rows();
+    assert_eq!(affected, 2);
+    recorder.unprepare(statement).await.unwrap();
+    recorder.close().await.unwrap();
+
+    let executed = handler.executed();
+    assert_eq!(executed.len(), 6);
+    assert_eq!(executed[0], ExecutedStatement::new("SELECT id, name FROM users", Vec::new()));
+    assert_eq!(executed[2].sql(), "UPDATE users SET name = @P1 WHERE id = @P2");
+    assert_eq!(executed[2].bind_variables(), &binds[..]);
+    assert_eq!(executed[5].bind_variables(), &binds[..]);
+}
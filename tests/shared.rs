@@ -1,6 +1,11 @@
+#![allow(dead_code)]
+
 use mockall::mock;
-use std::{future::Future, sync::Arc};
-use tabularuq::rdb_qry_handler::datatype::DataType;
+use rust_xlsxwriter::Workbook;
+use std::{future::Future, path::PathBuf, sync::Arc};
+use tabularuq::rdb_qry_handler::datatype::{DataType, DataTypeKind};
+use tabularuq::rdb_qry_handler::schema::*;
+use tabularuq::rdb_qry_handler::statement::PreparedStatement;
 use tabularuq::rdb_qry_handler::*;
 
 mock! {
@@ -28,7 +33,7 @@ mock! {
             &mut self,
             query: &str,
             bind_variables: Option<Arc<[DataType]>>,
-            fetch_more: Box<dyn Fn(Option<&[String]>, Option<&DataRecord>) -> bool + Send>,
+            fetch_more: FetchMore,
         ) -> Result<DataRows, Box<dyn std::error::Error>>;
 
         fn mutate(
@@ -37,6 +42,77 @@ mock! {
             bind_variables: Option<Arc<[DataType]>>,
         ) -> impl Future<Output = Result<MockQueryResultMock, Box<dyn std::error::Error>>> + Send;
 
+        async fn prepare(
+            &mut self,
+            query: &str,
+            param_types: Arc<[DataTypeKind]>,
+        ) -> Result<PreparedStatement, Box<dyn std::error::Error>>;
+
+        fn execute_prepared(
+            &mut self,
+            statement: &PreparedStatement,
+            bind_variables: Option<Arc<[DataType]>>,
+        ) -> impl Future<Output = Result<MockQueryResultMock, Box<dyn std::error::Error>>> + Send;
+
+        async fn unprepare(
+            &mut self,
+            statement: PreparedStatement,
+        ) -> Result<(), Box<dyn std::error::Error>>;
+
         async fn close(self) -> Result<(), Box<dyn std::error::Error>>;
     }
+
+    impl SchemaInspector for QueryHandlerMock {
+        async fn schemas(&mut self) -> Result<Vec<String>, Box<dyn std::error::Error>>;
+
+        async fn tables<'a>(
+            &mut self,
+            schema: Option<&'a str>,
+        ) -> Result<Vec<TableInfo>, Box<dyn std::error::Error>>;
+
+        async fn columns(&mut self, table: &str) -> Result<Vec<ColumnInfo>, Box<dyn std::error::Error>>;
+
+        async fn primary_key(
+            &mut self,
+            table: &str,
+        ) -> Result<Option<PrimaryKey>, Box<dyn std::error::Error>>;
+
+        async fn foreign_keys(
+            &mut self,
+            table: &str,
+        ) -> Result<Vec<ForeignKey>, Box<dyn std::error::Error>>;
+
+        async fn indexes(&mut self, table: &str) -> Result<Vec<IndexInfo>, Box<dyn std::error::Error>>;
+    }
+}
+
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("tabularuq_{}_{}", std::process::id(), name))
+}
+
+pub fn write_temp(name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+    let path = temp_path(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+pub fn write_workbook(name: &str, write: impl FnOnce(&mut Workbook)) -> PathBuf {
+    let path = temp_path(&format!("{}.xlsx", name));
+    let mut workbook = Workbook::new();
+    write(&mut workbook);
+    workbook.save(&path).unwrap();
+    path
+}
+
+pub fn write_sheets(name: &str, sheets: &[(&str, &[&[&str]])]) -> PathBuf {
+    write_workbook(name, |workbook| {
+        for (sheet_name, rows) in sheets {
+            let worksheet = workbook.add_worksheet().set_name(*sheet_name).unwrap();
+            for (row, cells) in rows.iter().enumerate() {
+                for (col, cell) in cells.iter().enumerate() {
+                    worksheet.write_string(row as u32, col as u16, *cell).unwrap();
+                }
+            }
+        }
+    })
 }
@@ -0,0 +1,777 @@
+mod shared;
+use calamine::{CellErrorType, Data};
+use futures::StreamExt;
+use rust_xlsxwriter::{Format, Table};
+use shared::*;
+use std::io::{Read, Write};
+use tabularuq::rdb_qry_handler::datatype::{DataType, DataTypeKind};
+use tabularuq::tabular_porter::{
+    TabularCursor, TabularPorter, TabularWriter, WriteOptions,
+    async_cursor::{AsyncTabularCursor, BlockingTabularCursor},
+    compression::{Compression, DecompressReader},
+    csv::{CsvPorter, CsvWriter},
+    encoding::TextEncoding,
+    error::TabularPortError,
+    json::{JsonFormat, JsonOptions, JsonPorter, JsonWriter},
+    number_format::{DateKind, NumberFormat},
+    spreadsheet::{CellFormatOptions, ErrorCellPolicy, SpreadsheetFormat, SpreadsheetPorter},
+    xslx::XlsxPorter,
+    zip::{ZipEntry, ZipPorter},
+};
+
+#[test]
+fn xlsx_sheet_discovery_test() {
+    let path = write_sheets(
+        "sheet_discovery",
+        &[("Orders", &[&["id"], &["1"]]), ("Items", &[&["sku"], &["a"], &["b"]])],
+    );
+    let mut porter = XlsxPorter::new(&path).unwrap();
+    assert_eq!(porter.sheet_names(), vec!["Orders".to_string(), "Items".to_string()]);
+
+    porter.set_header_row(true);
+    porter.init_range_by_index(1).unwrap();
+    assert_eq!(porter.headers().unwrap(), Some(vec!["sku".to_string()]));
+    assert_eq!(porter.cursor().unwrap().count(), 2);
+
+    let err = porter.init_range("Order").unwrap_err();
+    assert_eq!(
+        *err.downcast::<TabularPortError>().unwrap(),
+        TabularPortError::SheetNotFound(
+            "Order".to_string(),
+            vec!["Orders".to_string(), "Items".to_string()]
+        )
+    );
+    assert!(porter.init_range_by_index(2).is_err());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn xlsx_sheets_iteration_test() {
+    let path = write_sheets(
+        "sheets_iteration",
+        &[("First", &[&["a", "b"], &["1", "2"]]), ("Second", &[&["c"], &["3"], &["4"]])],
+    );
+    let mut porter = XlsxPorter::new(&path).unwrap();
+    porter.set_header_row(true);
+
+    let mut seen = Vec::new();
+    for sheet in porter.sheets() {
+        let (name, mut sheet) = sheet.unwrap();
+        let headers = sheet.headers().unwrap().unwrap();
+        let rows: Vec<Vec<String>> = sheet.cursor().unwrap().collect();
+        seen.push((name, headers, rows.len()));
+    }
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(
+        seen,
+        vec![
+            ("First".to_string(), vec!["a".to_string(), "b".to_string()], 1),
+            ("Second".to_string(), vec!["c".to_string()], 2),
+        ]
+    );
+}
+
+fn write_finance_workbook(name: &str) -> std::path::PathBuf {
+    write_workbook(name, |workbook| {
+        let worksheet = workbook.add_worksheet().set_name("Q1 Report").unwrap();
+        worksheet.write_string(0, 0, "Quarterly report").unwrap();
+        let rows = [["account", "amount"], ["cash", "10"], ["bank", "20"], ["fees", "-3"]];
+        for (row, cells) in rows.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                worksheet.write_string(row as u32 + 3, col as u16 + 1, *cell).unwrap();
+            }
+        }
+        let table = Table::new().set_name("Ledger");
+        worksheet.add_table(3, 1, 6, 2, &table).unwrap();
+        workbook.define_name("Totals", "='Q1 Report'!$B$5:$C$6").unwrap();
+    })
+}
+
+#[test]
+fn xlsx_table_and_defined_name_test() {
+    let path = write_finance_workbook("finance_ranges");
+    let mut porter = XlsxPorter::new(&path).unwrap();
+
+    porter.init_table("Ledger").unwrap();
+    assert_eq!(porter.headers().unwrap(), Some(vec!["account".to_string(), "amount".to_string()]));
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0], vec!["cash".to_string(), "10".to_string()]);
+
+    porter.init_defined_name("Totals").unwrap();
+    assert_eq!(porter.headers().unwrap(), None);
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows, vec![vec!["cash", "10"], vec!["bank", "20"]]);
+
+    porter.set_header_row(true);
+    porter.init_named("Ledger").unwrap();
+    assert_eq!(porter.cursor().unwrap().count(), 3);
+
+    let err = porter.init_table("Ledgr").unwrap_err();
+    assert_eq!(
+        *err.downcast::<TabularPortError>().unwrap(),
+        TabularPortError::RangeNotFound("Ledgr".to_string(), vec!["Ledger".to_string()])
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn xlsx_cell_range_test() {
+    let path = write_finance_workbook("cell_range");
+    let mut porter = XlsxPorter::new(&path).unwrap();
+    porter.set_header_row(true);
+
+    porter.init_cell_range("'Q1 Report'!B4:D5").unwrap();
+    assert_eq!(
+        porter.headers().unwrap(),
+        Some(vec!["account".to_string(), "amount".to_string(), "".to_string()])
+    );
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows, vec![vec!["cash", "10", ""]]);
+
+    porter.init_named("'Q1 Report'!$C$7").unwrap();
+    assert_eq!(porter.headers().unwrap(), Some(vec!["-3".to_string()]));
+
+    for reference in ["B4:C5", "'Q1 Report'!C5:B4", "'Q1 Report'!4B"] {
+        let err = porter.init_cell_range(reference).unwrap_err();
+        assert_eq!(
+            *err.downcast::<TabularPortError>().unwrap(),
+            TabularPortError::InvalidRange(reference.to_string())
+        );
+    }
+    assert!(porter.init_cell_range("Missing!A1:B2").is_err());
+    std::fs::remove_file(path).unwrap();
+}
+
+fn write_ods(name: &str, extension: &str) -> std::path::PathBuf {
+    let path = temp_path(&format!("{}.{}", name, extension));
+    let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" office:version="1.2">
+<office:body><office:spreadsheet>
+<table:table table:name="Legacy">
+<table:table-row><table:table-cell office:value-type="string"><text:p>code</text:p></table:table-cell><table:table-cell office:value-type="string"><text:p>qty</text:p></table:table-cell></table:table-row>
+<table:table-row><table:table-cell office:value-type="string"><text:p>A1</text:p></table:table-cell><table:table-cell office:value-type="float" office:value="4"><text:p>4</text:p></table:table-cell></table:table-row>
+</table:table>
+</office:spreadsheet></office:body>
+</office:document-content>"#;
+    let manifest = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+<manifest:file-entry manifest:full-path="/" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+<manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>"#;
+
+    let mut archive = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+    let stored =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for (entry, data) in [
+        ("mimetype", "application/vnd.oasis.opendocument.spreadsheet"),
+        ("content.xml", content),
+        ("META-INF/manifest.xml", manifest),
+    ] {
+        archive.start_file(entry, stored).unwrap();
+        archive.write_all(data.as_bytes()).unwrap();
+    }
+    archive.finish().unwrap();
+    path
+}
+
+#[test]
+fn spreadsheet_ods_test() {
+    let path = write_ods("legacy", "ods");
+    let mut porter = SpreadsheetPorter::new(&path).unwrap();
+    assert_eq!(porter.format(), SpreadsheetFormat::Ods);
+    assert_eq!(porter.sheet_names(), vec!["Legacy".to_string()]);
+    assert_eq!(porter.table_names().unwrap(), Vec::<String>::new());
+
+    porter.set_header_row(true);
+    porter.init_range_by_index(0).unwrap();
+    assert_eq!(porter.headers().unwrap(), Some(vec!["code".to_string(), "qty".to_string()]));
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows, vec![vec!["A1", "4"]]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn spreadsheet_format_detection_test() {
+    let ods = write_ods("detect", "bin");
+    assert_eq!(SpreadsheetFormat::detect(&ods).unwrap(), SpreadsheetFormat::Ods);
+    std::fs::remove_file(ods).unwrap();
+
+    let xlsx = write_sheets("detect", &[("Sheet1", &[&["a"]])]);
+    let renamed = xlsx.with_extension("XLSX");
+    std::fs::rename(&xlsx, &renamed).unwrap();
+    assert_eq!(SpreadsheetFormat::detect(&renamed).unwrap(), SpreadsheetFormat::Xlsx);
+    std::fs::remove_file(renamed).unwrap();
+
+    let ole = temp_path("ole.dat");
+    std::fs::write(&ole, [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]).unwrap();
+    assert_eq!(SpreadsheetFormat::detect(&ole).unwrap(), SpreadsheetFormat::Xls);
+    std::fs::write(&ole, "id,name\n").unwrap();
+    let err = SpreadsheetFormat::detect(&ole).unwrap_err();
+    std::fs::remove_file(&ole).unwrap();
+    assert_eq!(
+        *err.downcast::<TabularPortError>().unwrap(),
+        TabularPortError::UnsupportedFormat(ole.display().to_string())
+    );
+    assert_eq!(SpreadsheetFormat::from_extension("XLSB"), Some(SpreadsheetFormat::Xlsb));
+}
+
+#[test]
+fn spreadsheet_cell_format_test() {
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+    let date_time = Format::new().set_num_format("yyyy-mm-dd hh:mm");
+    let time = Format::new().set_num_format("hh:mm:ss");
+    let one_decimal = Format::new().set_num_format("#,##0.0");
+    let path = write_workbook("cell_format", |workbook| {
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_number_with_format(0, 0, 45292.0, &date).unwrap();
+        worksheet.write_number_with_format(0, 1, 45292.75, &date_time).unwrap();
+        worksheet.write_number_with_format(0, 2, 0.5, &time).unwrap();
+        worksheet.write_number(0, 3, 1.0).unwrap();
+        worksheet.write_number(0, 4, 2.125).unwrap();
+        worksheet.write_number_with_format(1, 0, 45292.5, &date).unwrap();
+        worksheet.write_number_with_format(1, 1, 45292.0, &date_time).unwrap();
+        worksheet.write_number_with_format(1, 2, 1.25, &time).unwrap();
+        worksheet.write_number_with_format(1, 3, 1.0, &one_decimal).unwrap();
+        worksheet.write_number_with_format(1, 4, 2.125, &one_decimal).unwrap();
+    });
+    let mut porter = SpreadsheetPorter::new(&path).unwrap();
+    porter.init_range_by_index(0).unwrap();
+
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows[0], vec!["2024-01-01", "2024-01-01T18:00:00", "12:00:00", "1", "2.125"]);
+    assert_eq!(rows[1], vec!["2024-01-01", "2024-01-01T00:00:00", "06:00:00", "1.0", "2.125"]);
+
+    porter.set_cell_format(CellFormatOptions::new(
+        Some("%d/%m/%Y %H:%M"),
+        Some("%d/%m/%Y"),
+        Some("%H:%M"),
+        Some(2),
+        ErrorCellPolicy::Empty,
+    ));
+    let row = porter.cursor().unwrap().next().unwrap();
+    assert_eq!(row, vec!["01/01/2024", "01/01/2024 18:00", "12:00", "1.00", "2.12"]);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(DateKind::from_format_code("yyyy-mm-dd"), Some(DateKind::Date));
+    assert_eq!(DateKind::from_format_code("[$-409]m/d/yy h:mm AM/PM"), Some(DateKind::DateTime));
+    assert_eq!(DateKind::from_format_code("[Red]hh:mm"), Some(DateKind::Time));
+    assert_eq!(DateKind::from_format_code("mm:ss.0"), Some(DateKind::Time));
+    assert_eq!(DateKind::from_format_code("0.00\" days\";General"), None);
+    assert_eq!(DateKind::from_builtin(14), Some(DateKind::Date));
+    assert_eq!(NumberFormat::from_format_code("0.000"), Some(NumberFormat::Decimals(3)));
+    assert_eq!(
+        NumberFormat::from_format_code("[Blue]\"v.\"0.0#;(0.00)"),
+        Some(NumberFormat::Decimals(2))
+    );
+    assert_eq!(NumberFormat::from_format_code("0"), None);
+    assert_eq!(NumberFormat::from_builtin(4), Some(NumberFormat::Decimals(2)));
+    assert_eq!(NumberFormat::from_builtin(22), Some(NumberFormat::Date(DateKind::DateTime)));
+}
+
+#[test]
+fn spreadsheet_error_cell_policy_test() {
+    let error = Data::Error(CellErrorType::Div0);
+    let options = |policy| CellFormatOptions::new(None, None, None, None, policy);
+
+    assert_eq!(options(ErrorCellPolicy::Text).format_cell(&error).unwrap(), "#DIV/0!");
+    assert_eq!(options(ErrorCellPolicy::Empty).format_cell(&error).unwrap(), "");
+    assert_eq!(
+        options(ErrorCellPolicy::Fail).format_cell(&error).unwrap_err(),
+        TabularPortError::InvalidCell("Error value #DIV/0!".to_string())
+    );
+}
+
+#[test]
+fn spreadsheet_invalid_date_test() {
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+    let path = write_workbook("invalid_date", |workbook| {
+        let worksheet = workbook.add_worksheet();
+        worksheet.write_string(0, 0, "when").unwrap();
+        worksheet.write_number_with_format(1, 0, -5.0, &date).unwrap();
+    });
+    let mut porter = SpreadsheetPorter::new(&path).unwrap();
+    porter.init_range_by_index(0).unwrap();
+
+    let err = porter.cursor().err().unwrap();
+    assert_eq!(
+        *err.downcast::<TabularPortError>().unwrap(),
+        TabularPortError::InvalidCell("A2: -5 is not a valid date serial".to_string())
+    );
+
+    porter.init_cell_range("Sheet1!A2").unwrap();
+    porter.set_header_row(true);
+    let err = porter.headers().unwrap_err();
+    assert_eq!(
+        *err.downcast::<TabularPortError>().unwrap(),
+        TabularPortError::InvalidCell("A2: -5 is not a valid date serial".to_string())
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn async_cursor_test() {
+    let csv_path = temp_path("async_cursor.csv");
+    let mut contents = String::from("id,name\n");
+    for i in 0..3000 {
+        contents.push_str(&format!("{},name{}\n", i, i));
+    }
+    std::fs::write(&csv_path, contents).unwrap();
+
+    let mut cursor = BlockingTabularCursor::<CsvPorter>::open(&csv_path).await.unwrap();
+    assert_eq!(cursor.headers().await.unwrap(), Some(vec!["id".to_string(), "name".to_string()]));
+    let records: Vec<_> = cursor.stream().map(|record| record.unwrap()).collect().await;
+    std::fs::remove_file(&csv_path).unwrap();
+    assert_eq!(records.len(), 3000);
+    assert_eq!(records[2999], vec!["2999".to_string(), "name2999".to_string()]);
+
+    let missing = BlockingTabularCursor::<CsvPorter>::open(&csv_path).await;
+    assert!(matches!(
+        *missing.err().unwrap().downcast::<TabularPortError>().unwrap(),
+        TabularPortError::ReadFailed(_)
+    ));
+
+    let xlsx_path = write_sheets("async_cursor", &[("Orders", &[&["id", "qty"], &["1", "5"]])]);
+    let mut porter = XlsxPorter::new(&xlsx_path).unwrap();
+    porter.init_range("Orders").unwrap();
+    porter.set_header_row(true);
+    let mut cursor = BlockingTabularCursor::new(porter);
+    assert_eq!(cursor.headers().await.unwrap(), Some(vec!["id".to_string(), "qty".to_string()]));
+    let records: Vec<_> = cursor.stream().collect().await;
+    std::fs::remove_file(xlsx_path).unwrap();
+    assert_eq!(records, vec![Ok(vec!["1".to_string(), "5".to_string()])]);
+
+    let unready_path = write_sheets("async_unready", &[("A", &[&["x"]])]);
+    let unready = BlockingTabularCursor::new(XlsxPorter::new(&unready_path).unwrap());
+    let records: Vec<_> = unready.stream().collect().await;
+    std::fs::remove_file(unready_path).unwrap();
+    assert!(matches!(records[..], [Err(TabularPortError::ReadFailed(_))]));
+
+    let mut lost = BlockingTabularCursor::new(PanickingCursor);
+    assert!(lost.headers().await.is_err());
+    let records: Vec<_> = lost.stream().collect().await;
+    assert!(matches!(records[..], [Err(TabularPortError::NotInitialized(_))]));
+}
+
+struct PanickingCursor;
+
+impl TabularCursor for PanickingCursor {
+    fn cursor(&mut self) -> Result<impl Iterator<Item = Vec<String>>, Box<dyn std::error::Error>> {
+        Ok(std::iter::empty())
+    }
+
+    fn headers(&mut self) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>> {
+        panic!("headers failed");
+    }
+}
+
+#[test]
+fn porter_from_reader_test() {
+    let mut porter = CsvPorter::from_bytes(b"id,name\n1,a\n2,b\n".to_vec());
+    assert_eq!(porter.headers().unwrap(), Some(vec!["id".to_string(), "name".to_string()]));
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows, vec![vec!["1", "a"], vec!["2", "b"]]);
+
+    let mut porter = CsvPorter::from_reader(std::io::Cursor::new("x\n3\n"));
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows, vec![vec!["3"]]);
+
+    let xlsx = write_sheets("from_reader", &[("Orders", &[&["id"], &["1"]])]);
+    let mut porter = SpreadsheetPorter::from_bytes(std::fs::read(&xlsx).unwrap()).unwrap();
+    assert_eq!(porter.format(), SpreadsheetFormat::Xlsx);
+    porter.init_range("Orders").unwrap();
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows, vec![vec!["id"], vec!["1"]]);
+    std::fs::remove_file(xlsx).unwrap();
+
+    let ods = write_ods("from_reader", "bin");
+    let mut porter = SpreadsheetPorter::from_reader(std::fs::File::open(&ods).unwrap()).unwrap();
+    assert_eq!(porter.format(), SpreadsheetFormat::Ods);
+    porter.init_range("Legacy").unwrap();
+    assert_eq!(porter.cursor().unwrap().count(), 2);
+    std::fs::remove_file(ods).unwrap();
+
+    let err = SpreadsheetPorter::from_bytes(b"id,name\n".to_vec()).err().unwrap();
+    assert!(matches!(
+        *err.downcast::<TabularPortError>().unwrap(),
+        TabularPortError::UnsupportedFormat(_)
+    ));
+}
+
+#[tokio::test]
+async fn porter_from_async_reader_test() {
+    let porter = CsvPorter::from_async_reader(&b"id\n1\n2\n"[..]);
+    let mut cursor = BlockingTabularCursor::new(porter);
+    assert_eq!(cursor.headers().await.unwrap(), Some(vec!["id".to_string()]));
+    let records: Vec<_> = cursor.stream().map(|record| record.unwrap()).collect().await;
+    assert_eq!(records, vec![vec!["1".to_string()], vec!["2".to_string()]]);
+
+    let xlsx = write_sheets("from_async_reader", &[("Items", &[&["sku"], &["a"]])]);
+    let file = tokio::fs::File::open(&xlsx).await.unwrap();
+    let porter = SpreadsheetPorter::from_async_reader(file).await.unwrap();
+    std::fs::remove_file(xlsx).unwrap();
+    assert_eq!(porter.sheet_names(), vec!["Items".to_string()]);
+}
+
+#[test]
+fn compressed_csv_test() {
+    let dir = temp_path("compressed");
+    std::fs::create_dir_all(&dir).unwrap();
+    for extension in ["gz", "zst", "bz2", "xz"] {
+        let path = dir.join(format!("items.csv.{}", extension));
+        let mut writer = CsvWriter::create(&path, WriteOptions::new("", None)).unwrap();
+        writer.write_header(&["id".to_string(), "name".to_string()]).unwrap();
+        writer
+            .write_record(&[Some(DataType::I32(1)), Some(DataType::String("a".to_string()))])
+            .unwrap();
+        writer.next_part().unwrap();
+        writer.write_record(&[Some(DataType::I32(2)), None]).unwrap();
+        writer.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(Compression::from_magic(&bytes), Compression::from_extension(extension));
+
+        let mut porter = CsvPorter::new(&path).unwrap();
+        assert_eq!(porter.headers().unwrap(), Some(vec!["id".to_string(), "name".to_string()]));
+        let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+        assert_eq!(rows, vec![vec!["1", "a"]]);
+
+        let mut porter = CsvPorter::from_bytes(bytes.clone());
+        assert_eq!(porter.headers().unwrap(), Some(vec!["id".to_string(), "name".to_string()]));
+
+        let part = dir.join(format!("items_2.csv.{}", extension));
+        let mut porter = CsvPorter::new(&part).unwrap();
+        assert_eq!(porter.headers().unwrap(), Some(vec!["2".to_string(), "".to_string()]));
+
+        let mut contents = String::new();
+        DecompressReader::new(OneByteReader(std::io::Cursor::new(bytes)), None)
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "id,name\n1,a\n");
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+
+    assert_eq!(Compression::from_magic(b"BZh9"), Some(Compression::Bzip2));
+    assert_eq!(Compression::from_magic(b"BZhello"), None);
+}
+
+//NOTE: hands out one byte per read, as pipes and sockets may.
+struct OneByteReader<R>(R);
+
+impl<R: Read> Read for OneByteReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(1);
+        self.0.read(&mut buf[..len])
+    }
+}
+
+fn write_bundle(name: &str) -> std::path::PathBuf {
+    let xlsx = write_sheets(name, &[("Orders", &[&["id", "qty"], &["3", "30"]])]);
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(b"id,qty\n4,40\n").unwrap();
+
+    let path = temp_path(&format!("{}.zip", name));
+    let mut archive = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+    let stored =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let deflated = zip::write::SimpleFileOptions::default();
+    archive.add_directory("orders/", stored).unwrap();
+    archive.start_file("orders/2024-01.csv", stored).unwrap();
+    archive.write_all(b"id,qty\n1,10\n").unwrap();
+    archive.start_file("orders/2024-02.csv", deflated).unwrap();
+    archive.write_all(b"id,qty\n2,20\n").unwrap();
+    archive.start_file("orders/archive/2023-12.csv.gz", deflated).unwrap();
+    archive.write_all(&gzip.finish().unwrap()).unwrap();
+    archive.start_file("summary.xlsx", deflated).unwrap();
+    archive.write_all(&std::fs::read(&xlsx).unwrap()).unwrap();
+    archive.start_file("README.md", deflated).unwrap();
+    archive.write_all(b"# Orders").unwrap();
+    archive.finish().unwrap();
+    std::fs::remove_file(xlsx).unwrap();
+    path
+}
+
+#[test]
+fn zip_porter_test() {
+    let path = write_bundle("zip_porter");
+    let porter = ZipPorter::new(&path).unwrap();
+    assert_eq!(
+        porter.entry_names(),
+        vec![
+            "orders/2024-01.csv",
+            "orders/2024-02.csv",
+            "orders/archive/2023-12.csv.gz",
+            "summary.xlsx",
+            "README.md"
+        ]
+    );
+    assert_eq!(
+        porter.find("orders/*.csv").unwrap(),
+        vec!["orders/2024-01.csv", "orders/2024-02.csv"]
+    );
+    assert_eq!(porter.find("**/*.csv*").unwrap().len(), 3);
+    assert_eq!(porter.find("**/*").unwrap().len(), 5);
+    assert_eq!(porter.tabular_entry_names().len(), 4);
+    let mut entries = porter.open_tabular().unwrap();
+    assert_eq!(entries.cursor().unwrap().count(), 4);
+
+    let mut csv = porter.open_csv("orders/2024-02.csv").unwrap();
+    assert_eq!(csv.headers().unwrap(), Some(vec!["id".to_string(), "qty".to_string()]));
+    let rows: Vec<Vec<String>> = csv.cursor().unwrap().collect();
+    assert_eq!(rows, vec![vec!["2", "20"]]);
+
+    let mut spreadsheet = porter.open_spreadsheet("*.xlsx").unwrap();
+    assert_eq!(spreadsheet.sheet_names(), vec!["Orders".to_string()]);
+    spreadsheet.init_range("Orders").unwrap();
+    assert_eq!(spreadsheet.cursor().unwrap().count(), 2);
+
+    let mut entry = porter.open("orders/archive/*").unwrap();
+    assert!(matches!(entry, ZipEntry::Csv(_)));
+    let rows: Vec<Vec<String>> = entry.cursor().unwrap().collect();
+    assert_eq!(rows, vec![vec!["4", "40"]]);
+
+    let mut entries = porter.open_all("**/2*").unwrap();
+    assert_eq!(entries.names().len(), 3);
+    assert_eq!(entries.headers().unwrap(), Some(vec!["id".to_string(), "qty".to_string()]));
+    let rows: Vec<Vec<String>> = entries.cursor().unwrap().collect();
+    assert_eq!(rows, vec![vec!["1", "10"], vec!["2", "20"], vec!["4", "40"]]);
+
+    let err = porter.open_csv("orders/*.csv").err().unwrap();
+    assert!(matches!(
+        *err.downcast::<TabularPortError>().unwrap(),
+        TabularPortError::AmbiguousEntry(_, _)
+    ));
+    let err = porter.open_csv("missing.csv").err().unwrap();
+    assert!(matches!(
+        *err.downcast::<TabularPortError>().unwrap(),
+        TabularPortError::EntryNotFound(_, _)
+    ));
+    let err = porter.open("README.md").err().unwrap();
+    assert!(matches!(
+        *err.downcast::<TabularPortError>().unwrap(),
+        TabularPortError::UnsupportedFormat(_)
+    ));
+
+    let mut bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let porter = ZipPorter::from_bytes(bytes.clone()).unwrap();
+    let mut entries = porter.open_all("orders/*.csv").unwrap();
+    assert_eq!(entries.cursor().unwrap().count(), 2);
+
+    let stored = bytes.windows(4).position(|window| window == b"1,10").unwrap();
+    bytes[stored + 3] = b'1';
+    let porter = ZipPorter::from_bytes(bytes).unwrap();
+    let err = porter.open_csv("orders/2024-01.csv").unwrap().headers().unwrap_err();
+    assert!(err.to_string().contains("orders/2024-01.csv does not match its CRC-32"));
+}
+
+#[test]
+fn csv_encoding_test() {
+    let read = |bytes: Vec<u8>, encoding: TextEncoding| {
+        let mut porter =
+            CsvPorter::from_reader_with_encoding(std::io::Cursor::new(bytes), encoding);
+        let mut rows = vec![porter.headers().unwrap().unwrap()];
+        rows.extend(porter.cursor().unwrap());
+        rows
+    };
+    let korean = "id,이름,도시\n1,김철수,서울특별시\n2,이영희,부산광역시\n3,박민수,대구광역시\n";
+    let japanese = "id,名前,都市\n1,山田太郎,東京都\n2,鈴木花子,大阪府\n3,佐藤一郎,北海道\n";
+    let rows = |text: &str| -> Vec<Vec<String>> {
+        text.lines().map(|line| line.split(',').map(|cell| cell.to_string()).collect()).collect()
+    };
+
+    let (cp949, _, _) = encoding_rs::EUC_KR.encode(korean);
+    assert_eq!(read(cp949.to_vec(), TextEncoding::Auto), rows(korean));
+    let (shift_jis, _, _) = encoding_rs::SHIFT_JIS.encode(japanese);
+    assert_eq!(read(shift_jis.to_vec(), TextEncoding::Auto), rows(japanese));
+    let explicit = TextEncoding::from_label("shift_jis").unwrap();
+    assert_eq!(read(shift_jis.to_vec(), explicit), rows(japanese));
+
+    let utf16le: Vec<u8> = [0xFF, 0xFE]
+        .into_iter()
+        .chain(korean.encode_utf16().flat_map(|unit| unit.to_le_bytes()))
+        .collect();
+    assert_eq!(read(utf16le, TextEncoding::Auto), rows(korean));
+    let utf16be: Vec<u8> =
+        "id,name\n1,alpha\n".encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect();
+    assert_eq!(read(utf16be, TextEncoding::Auto), rows("id,name\n1,alpha\n"));
+    let utf8_bom = [b"\xEF\xBB\xBF".as_slice(), japanese.as_bytes()].concat();
+    assert_eq!(read(utf8_bom.clone(), TextEncoding::Auto), rows(japanese));
+    assert_eq!(read(utf8_bom, explicit), rows(japanese));
+
+    let invalid = b"id,name\n1,a\xFFb\n2,c\n".to_vec();
+    let utf8 = TextEncoding::from_label("utf-8").unwrap();
+    assert_eq!(read(invalid, utf8), rows("id,name\n1,a\u{FFFD}b\n2,c"));
+
+    let mut late_cp949 = b"id,name\n".to_vec();
+    while late_cp949.len() <= 64 * 1024 {
+        late_cp949.extend_from_slice(b"1,alpha\n");
+    }
+    late_cp949.extend_from_slice(&encoding_rs::EUC_KR.encode("2,김철수\n").0);
+    let mut porter = CsvPorter::from_reader_with_encoding(
+        std::io::Cursor::new(late_cp949.clone()),
+        TextEncoding::Auto,
+    );
+    porter.headers().unwrap();
+    let records: Vec<_> = porter.try_cursor().unwrap().collect();
+    let err = records.last().unwrap().as_ref().unwrap_err();
+    assert!(err.to_string().contains("not valid UTF-8"));
+    assert!(records.iter().rev().skip(1).all(|record| record.is_ok()));
+    let cp949 = TextEncoding::from_label("cp949").unwrap();
+    assert_eq!(read(late_cp949, cp949).last().unwrap(), &vec!["2", "김철수"]);
+    assert_eq!(TextEncoding::from_label("cp949"), Some(TextEncoding::Fixed(encoding_rs::EUC_KR)));
+    assert_eq!(TextEncoding::from_label("klingon"), None);
+}
+
+#[test]
+fn csv_output_encoding_test() {
+    let dir = temp_path("encoding");
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, encoding: &'static encoding_rs::Encoding, bom: bool, cell: &str| {
+        let path = dir.join(name);
+        let mut options = WriteOptions::default();
+        options.set_encoding(encoding, bom);
+        let mut writer = CsvWriter::create(&path, options).unwrap();
+        writer.write_header(&["id".to_string(), "name".to_string()]).unwrap();
+        writer
+            .write_record(&[Some(DataType::I32(1)), Some(DataType::String(cell.to_string()))])
+            .and_then(|_| writer.finish())
+            .map(|_| std::fs::read(&path).unwrap())
+    };
+
+    let bytes = write("sjis.csv", encoding_rs::SHIFT_JIS, false, "山田太郎").unwrap();
+    assert_eq!(bytes, encoding_rs::SHIFT_JIS.encode("id,name\n1,山田太郎\n").0.to_vec());
+    let mut porter = CsvPorter::new(dir.join("sjis.csv")).unwrap();
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows, vec![vec!["1", "山田太郎"]]);
+
+    let bytes = write("utf16.csv", encoding_rs::UTF_16LE, true, "김철수").unwrap();
+    assert_eq!(bytes[..4], [0xFF, 0xFE, b'i', 0x00]);
+    let mut porter = CsvPorter::new(dir.join("utf16.csv")).unwrap();
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows, vec![vec!["1", "김철수"]]);
+
+    let bytes = write("bom.csv", encoding_rs::UTF_8, true, "a").unwrap();
+    assert_eq!(bytes, b"\xEF\xBB\xBFid,name\n1,a\n");
+
+    assert!(write("unmappable.csv", encoding_rs::SHIFT_JIS, false, "김철수").is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn json_porter_test() {
+    let ndjson = b"{\"id\": 1, \"name\": \"alpha\", \"address\": {\"city\": \"Seoul\", \"zip\": \"04524\"}}\n\
+        \n\
+        {\"id\": 18446744073709551615, \"active\": true, \"tags\": [\"a\", \"b\"], \"name\": null}\n\
+        {\"id\": 2.5, \"address\": {\"city\": \"Busan\"}}\n"
+        .to_vec();
+
+    let mut porter = JsonPorter::from_bytes(ndjson.clone(), JsonOptions::default()).unwrap();
+    assert_eq!(porter.columns(), ["id", "name", "address.city", "address.zip", "active", "tags"]);
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows[0], vec!["1", "alpha", "Seoul", "04524", "", ""]);
+    assert_eq!(rows[1], vec!["18446744073709551615", "", "", "", "true", "[\"a\",\"b\"]"]);
+    assert_eq!(rows[2], vec!["2.5", "", "Busan", "", "", ""]);
+    let records: Vec<_> = porter.records().unwrap().map(|record| record.unwrap()).collect();
+    assert_eq!(records[0][0], Some(DataType::I64(1)));
+    assert_eq!(records[1][0], Some(DataType::U64(u64::MAX)));
+    assert_eq!(records[1][4], Some(DataType::Bool(true)));
+    assert_eq!(records[2][0], Some(DataType::F64(2.5)));
+    assert_eq!(records[2][1], None);
+
+    let porter = JsonPorter::from_bytes(ndjson.clone(), JsonOptions::new(Some("_"), None)).unwrap();
+    assert_eq!(porter.columns()[2], "address_city");
+    let mut porter = JsonPorter::from_bytes(ndjson, JsonOptions::new(None, None)).unwrap();
+    assert_eq!(porter.columns(), ["id", "name", "address", "active", "tags"]);
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows[2][2], "{\"city\":\"Busan\"}");
+
+    let array =
+        "\u{FEFF}[\n  {\"id\": \"7\", \"joined\": \"2024-01-02 03:04:05\"},\n  {\"id\": 8}\n]\n";
+    let schema = vec![
+        ("id".to_string(), DataTypeKind::I32),
+        ("joined".to_string(), DataTypeKind::DateTime),
+        ("missing".to_string(), DataTypeKind::String),
+    ];
+    let options = JsonOptions::new(Some("."), Some(schema.clone()));
+    let mut porter = JsonPorter::from_bytes(array.as_bytes().to_vec(), options).unwrap();
+    let records: Vec<_> = porter.records().unwrap().map(|record| record.unwrap()).collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0][0], Some(DataType::I32(7)));
+    assert!(matches!(records[0][1], Some(DataType::DateTime(_))));
+    assert_eq!(records[1], vec![Some(DataType::I32(8)), None, None]);
+
+    let invalid = b"{\"id\": \"seven\"}\n".to_vec();
+    let mut porter = JsonPorter::from_bytes(invalid, JsonOptions::new(None, Some(schema))).unwrap();
+    let records: Vec<_> = porter.records().unwrap().collect();
+    assert!(matches!(records[..], [Err(TabularPortError::InvalidCell(_))]));
+    let records: Vec<_> = porter.try_cursor().unwrap().collect();
+    assert!(matches!(records[..], [Err(TabularPortError::InvalidCell(_))]));
+    let nullable: Vec<_> = JsonPorter::from_bytes(
+        b"{\"id\": 1, \"name\": null}\n{\"name\": \"\"}\n".to_vec(),
+        JsonOptions::default(),
+    )
+    .unwrap()
+    .try_nullable_cursor()
+    .unwrap()
+    .map(|record| record.unwrap())
+    .collect();
+    assert_eq!(nullable, vec![vec![Some("1".to_string()), None], vec![None, Some("".to_string())]]);
+
+    let err = JsonPorter::from_bytes(b"[{\"a\": 1}] garbage {".to_vec(), JsonOptions::default())
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("unexpected content after the array"));
+    let porter = JsonPorter::from_bytes(b"[{\"a\": 1}]\n\n".to_vec(), JsonOptions::default());
+    assert_eq!(porter.unwrap().columns(), ["a"]);
+    let err = JsonPorter::from_bytes(b"[{\"id\": 1} {\"id\": 2}]".to_vec(), JsonOptions::default())
+        .err()
+        .unwrap();
+    assert!(matches!(
+        *err.downcast::<TabularPortError>().unwrap(),
+        TabularPortError::ReadFailed(_)
+    ));
+}
+
+#[test]
+fn json_writer_test() {
+    let dir = temp_path("json");
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str| {
+        let path = dir.join(name);
+        let mut writer = JsonWriter::create(&path, WriteOptions::default()).unwrap();
+        writer.write_header(&["id".to_string(), "name".to_string(), "big".to_string()]).unwrap();
+        writer
+            .write_record(&[
+                Some(DataType::I32(1)),
+                Some(DataType::String("a\"b".to_string())),
+                Some(DataType::I128(i128::MAX)),
+            ])
+            .unwrap();
+        writer
+            .write_record(&[Some(DataType::F64(0.5)), None, Some(DataType::Bool(false))])
+            .unwrap();
+        writer.next_part().unwrap();
+        writer.write_record(&[Some(DataType::I32(3))]).unwrap();
+        writer.finish().unwrap();
+        path
+    };
+
+    let path = write("rows.jsonl");
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "{\"id\":1,\"name\":\"a\\\"b\",\"big\":\"170141183460469231731687303715884105727\"}\n\
+         {\"id\":0.5,\"name\":null,\"big\":false}\n"
+    );
+    assert_eq!(std::fs::read_to_string(dir.join("rows_2.jsonl")).unwrap(), "{\"id\":3}\n");
+
+    let path = write("rows.json.gz");
+    assert_eq!(JsonFormat::from_path(&path), Some(JsonFormat::Array));
+    let mut porter = JsonPorter::new(&path).unwrap();
+    let rows: Vec<Vec<String>> = porter.cursor().unwrap().collect();
+    assert_eq!(rows[1], vec!["0.5", "", "false"]);
+    let mut porter = JsonPorter::new(dir.join("rows_2.json.gz")).unwrap();
+    assert_eq!(porter.headers().unwrap(), Some(vec!["id".to_string()]));
+    std::fs::remove_dir_all(dir).unwrap();
+}